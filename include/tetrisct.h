@@ -0,0 +1,64 @@
+/* The Unictris engine for C, as built with `cargo build --release --features capi`:
+ * link against target/release/libtetrisct.a or libtetrisct.so. Matches src/capi.rs.
+ *
+ * Games are known by a handle, good on the thread that made it; calls with a
+ * handle that isn't one return -1. */
+
+#ifndef TETRISCT_H
+#define TETRISCT_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* moves, for tetris_apply */
+#define TETRIS_LEFT 0
+#define TETRIS_RIGHT 1
+#define TETRIS_ROTATE 2
+#define TETRIS_HARD_DROP 3
+#define TETRIS_SONIC_DROP 4 /* straight to the floor, without locking */
+#define TETRIS_ITEM 5       /* use the item in hand */
+#define TETRIS_HOLD 6       /* put the falling piece aside */
+
+/* cells, as tetris_cell has them besides 0 (empty) and tetromino+1 */
+#define TETRIS_FLOOR 253
+#define TETRIS_ITEM_CELL 254 /* garbage holding an item */
+#define TETRIS_GARBAGE 255
+
+/* a new game on a width x height board (0 for the default, 10 x 20) dealt from
+ * seed; the same seed and moves play out the same. -1 for a size out of
+ * bounds: 4 to 16 wide, 4 to 40 high */
+int32_t tetris_new(uint8_t width, uint8_t height, uint64_t seed);
+
+/* done with game h; its handle may be given to a later game */
+void tetris_free(int32_t h);
+
+/* advance the game ticks ticks of 10ms; 1 while it goes on, 0 once it is over */
+int32_t tetris_step(int32_t h, uint32_t ticks);
+
+/* make move m, one of TETRIS_LEFT and the rest; 0, or -1 with no such move */
+int32_t tetris_apply(int32_t h, int32_t m);
+
+/* the cell at column x, row y from the top, counting the falling piece; -1 off the board */
+int32_t tetris_cell(int32_t h, int32_t x, int32_t y);
+
+int32_t tetris_width(int32_t h);
+int32_t tetris_height(int32_t h);
+
+/* the falling tetromino, numbered from 0 as in the piece set - its cells less one */
+int32_t tetris_piece(int32_t h);
+
+int64_t tetris_score(int32_t h);
+int64_t tetris_lines(int32_t h);
+int64_t tetris_tick(int32_t h);
+
+/* 1 once the game is over, else 0 */
+int32_t tetris_over(int32_t h);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
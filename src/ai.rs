@@ -0,0 +1,338 @@
+use crate::board::Board;
+use crate::game::{Game, Move, Phase};
+use crate::pieces::shape;
+use rand::prelude::*;
+use serde::Deserialize;
+
+// A simple lookahead player: try every orientation and column, score the
+// resulting boards - the harder ones with the next piece's best put down
+// too - and head for the best one, or now and then, the easier ones, one
+// not quite the best.
+
+// Weights for the board evaluation - Yiyuan Lee's tuned "near perfect" player.
+pub struct Weights {
+    pub height: f64,
+    pub lines: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub tetris: f64, // on top of lines, for four at once
+    pub well: f64,   // for the last column kept open, as deep as a tetris wants
+}
+
+pub const WEIGHTS: Weights = Weights {
+    height: -0.510066,
+    lines: 0.760666,
+    holes: -0.35663,
+    bumpiness: -0.184483,
+    tetris: 0.0,
+    well: 0.0,
+};
+
+// ... for a bot out to attack, building up for tetrises with the last
+// column open rather than burning lines on singles
+const ATTACKER: Weights = Weights {
+    height: -0.51,
+    lines: -0.5,
+    holes: -0.9,
+    bumpiness: -0.18,
+    tetris: 5.0,
+    well: 0.6,
+};
+
+// ... and for one keeping its stack down whatever it takes
+const SAFE: Weights = Weights {
+    height: -0.8,
+    lines: 1.0,
+    holes: -0.9,
+    bumpiness: -0.3,
+    tetris: 0.0,
+    well: 0.0,
+};
+
+pub fn evaluate(b: &Board, lines: u32, w: &Weights) -> f64 {
+    w.height * b.aggregate_height() as f64
+        + w.lines * lines as f64
+        + w.holes * b.holes() as f64
+        + w.bumpiness * b.bumpiness() as f64
+        + if lines >= 4 { w.tetris } else { 0.0 }
+        + w.well * well(b) as f64
+}
+
+// how far the last column is down from the one beside it, up to a tetris's worth
+fn well(b: &Board) -> u8 {
+    match b.column_heights()[..] {
+        [.., next, last] => next.saturating_sub(last).min(4),
+        _ => 0,
+    }
+}
+
+pub struct Placement {
+    pub r: u8,
+    pub x: u8,
+    pub y: u8, // the row it comes to rest on
+    pub score: f64,
+}
+
+// a way of putting a piece down: its orientation, column and row, with the
+// board after and the lines that cleared
+type Drop = (u8, u8, u8, Board, u32);
+
+// every way of putting down piece p, from row y of b, by rotating, shifting
+// and dropping it
+fn drops(b: &Board, p: u8, y: u8) -> Vec<Drop> {
+    let s = shape(p);
+    let mut v = Vec::new();
+    for r in 0..4 {
+        for x in 0..=b.width() - s.dim(r).0 {
+            let (x, mut y) = (x as i16, y as i16);
+            if b.collides(s.mask(r), x, y) {
+                continue;
+            }
+            while !b.collides(s.mask(r), x, y + 1) {
+                y += 1;
+            }
+            let mut b = b.clone();
+            b.place(s.coor(r), x as u8, y as u8, p + 1);
+            let lines = b.clear_full_rows().len() as u32;
+            v.push((r, x as u8, y as u8, b, lines));
+        }
+    }
+    v
+}
+
+// a drop scored - by the best next can do after it, if it is looked at,
+// none at all where it would top out
+fn score((r, x, y, b, lines): &Drop, next: Option<u8>, w: &Weights) -> Placement {
+    let score = match next {
+        None => evaluate(b, *lines, w),
+        Some(p) => drops(b, p, 0)
+            .iter()
+            .map(|(_, _, _, b, more)| evaluate(b, lines + more, w))
+            .fold(f64::NEG_INFINITY, f64::max),
+    };
+    Placement {
+        r: *r,
+        x: *x,
+        y: *y,
+        score,
+    }
+}
+
+// every way of putting down the current piece
+pub fn placements(g: &Game, w: &Weights) -> Vec<Placement> {
+    search(g, w, 1)
+}
+
+// ... scored, with depth 2, with the next piece put down after it
+pub fn search(g: &Game, w: &Weights, depth: u8) -> Vec<Placement> {
+    let next = g.next.front().map(|&(p, _)| p).filter(|_| depth > 1);
+    drops(&g.board, g.p, g.y)
+        .iter()
+        .map(|d| score(d, next, w))
+        .collect()
+}
+
+pub fn best(g: &Game, w: &Weights) -> Option<Placement> {
+    placements(g, w)
+        .into_iter()
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+const STEP: usize = 4; // drops a search scores at a time
+
+// The best place for a game's current piece, looking a piece ahead, worked
+// out a few drops at a time - so a frame is never held up for it - and
+// started over by itself once there is another piece or the stack changes.
+#[derive(Default)]
+pub struct Search {
+    piece: Option<(u32, u8, Board)>, // which piece of the game it is for, and on what
+    todo: Vec<Drop>,                 // drops not scored yet
+    best: Option<Placement>,
+}
+
+impl Search {
+    // a few more drops scored for g, and the best place once they all are
+    pub fn step(&mut self, g: &Game, w: &Weights) -> Option<&Placement> {
+        let n = g.stats.pieces();
+        let same = matches!(&self.piece, Some((m, p, b)) if (*m, *p) == (n, g.p) && *b == g.board);
+        if !same {
+            self.todo = drops(&g.board, g.p, g.y);
+            self.best = None;
+            self.piece = Some((n, g.p, g.board.clone()));
+        }
+        let next = g.next.front().map(|&(p, _)| p);
+        let at = self.todo.len().saturating_sub(STEP);
+        for d in self.todo.split_off(at) {
+            let p = score(&d, next, w);
+            if self.best.as_ref().is_none_or(|b| p.score > b.score) {
+                self.best = Some(p);
+            }
+        }
+        self.todo.is_empty().then_some(self.best.as_ref()).flatten()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Difficulty, ()> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    // ticks between moves
+    pub fn think(self) -> u32 {
+        match self {
+            Difficulty::Easy => 30,
+            Difficulty::Normal => 12,
+            Difficulty::Hard => 5,
+        }
+    }
+
+    // pieces looked at for each placement, the current one and maybe the next
+    pub fn depth(self) -> u8 {
+        match self {
+            Difficulty::Hard => 2,
+            _ => 1,
+        }
+    }
+
+    // the share of pieces put somewhere other than the best place
+    pub fn mistakes(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.25,
+            Difficulty::Normal => 0.05,
+            Difficulty::Hard => 0.0,
+        }
+    }
+}
+
+// How the bot plays, whatever its difficulty: the boards it is after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    Balanced, // the tuned weights, clearing as it goes
+    Attacker, // stacking up for tetrises, to send garbage
+    Safe,     // downstacking, as low as it can
+}
+
+impl std::str::FromStr for Style {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Style, ()> {
+        match s {
+            "balanced" => Ok(Style::Balanced),
+            "attacker" => Ok(Style::Attacker),
+            "safe" => Ok(Style::Safe),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Style {
+    pub const ALL: [Style; 3] = [Style::Balanced, Style::Attacker, Style::Safe];
+
+    // what boards like b are judged by - the attacker playing it straight
+    // once its stack is half way up, not to top out building
+    pub fn weights(self, b: &Board) -> &'static Weights {
+        let high = || {
+            let top = b.column_heights().into_iter().max().unwrap_or(0);
+            2 * top > b.height()
+        };
+        match self {
+            Style::Balanced => &WEIGHTS,
+            Style::Attacker if high() => &WEIGHTS,
+            Style::Attacker => &ATTACKER,
+            Style::Safe => &SAFE,
+        }
+    }
+}
+
+const MAX_MOVES: u32 = 12; // give up on reaching the target and just drop
+const NEAR_BEST: usize = 4; // placements a mistake is made from, the best of them left out
+
+pub struct Bot {
+    pub difficulty: Difficulty,
+    pub style: Style,
+    piece: u32,               // which piece of the game the target is for
+    target: Option<(u8, u8)>, // (orientation, column)
+    wait: u32,
+    moves: u32,
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty, style: Style) -> Bot {
+        Bot {
+            difficulty,
+            style,
+            piece: 0,
+            target: None,
+            wait: 0,
+            moves: 0,
+        }
+    }
+
+    // called every tick: the move to make now, if any
+    pub fn act(&mut self, g: &Game) -> Option<Move> {
+        if g.phase != Phase::Falling {
+            return None;
+        }
+        if g.item.is_some() {
+            return Some(Move::Item);
+        }
+        if g.stats.pieces() != self.piece {
+            self.piece = g.stats.pieces();
+            self.target = self.choose(g);
+            self.moves = 0;
+            self.wait = self.difficulty.think();
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return None;
+        }
+        self.wait = self.difficulty.think();
+        self.moves += 1;
+        let Some((r, x)) = self.target else {
+            return Some(Move::HardDrop);
+        };
+        Some(if self.moves > MAX_MOVES {
+            Move::HardDrop
+        } else if g.r != r {
+            Move::Rotate
+        } else if g.x < x {
+            Move::Right
+        } else if g.x > x {
+            Move::Left
+        } else {
+            Move::HardDrop
+        })
+    }
+
+    // where to put g's current piece: the best place, or one of the next
+    // best in the share of pieces the difficulty gets wrong
+    fn choose(&self, g: &Game) -> Option<(u8, u8)> {
+        let d = self.difficulty;
+        let mut v = search(g, self.style.weights(&g.board), d.depth());
+        v.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut rng = thread_rng();
+        let i = match v.len() > 1 && rng.gen_bool(d.mistakes()) {
+            true => rng.gen_range(1..v.len().min(NEAR_BEST + 1)),
+            false => 0,
+        };
+        v.get(i).map(|p| (p.r, p.x))
+    }
+}
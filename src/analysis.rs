@@ -0,0 +1,184 @@
+use crate::ai::{self, WEIGHTS};
+use crate::board::Board;
+use crate::game::{Event, Move, TICK};
+use crate::input::{InputSource, Script};
+use crate::keymap::Action;
+use crate::pieces::{self, shape};
+use crate::replay::Replay;
+use crate::stats;
+use std::fmt;
+
+// A replay gone over piece by piece, as a coach would: each placement
+// weighed with the bot's board evaluation against the best it could have
+// been, dropped straight down - a misdrop when it falls well short - and
+// the moves it took against the fewest that would have got it there, with
+// the pace over the game and the worst placements to look at again.
+
+const MISDROP: f64 = 1.0; // evaluation lost that makes a placement a misdrop
+const WINDOW: u64 = 1000; // ticks in a stretch of the pace, ten seconds
+const WORST: usize = 3; // placements shown
+
+pub struct Placement {
+    pub tick: u64,     // it locked at
+    pub p: u8,         // the piece
+    pub loss: f64,     // evaluation short of the best straight drop, 0 for as good
+    pub moves: u32,    // shifts and rotations made
+    pub least: u32,    // ... and the fewest that would have done
+    pub played: Board, // the board after it, rows cleared
+    pub best: Board,   // ... and after the best straight drop
+}
+
+pub struct Report {
+    pub placements: Vec<Placement>,
+    pub ticks: u64, // the replay's length
+}
+
+// the shifts and rotations that take a piece from (x0, r0) to (x, r), rotating only clockwise
+fn least(from: (u8, u8), to: (u8, u8)) -> u32 {
+    ((to.1 + 4 - from.1) % 4) as u32 + from.0.abs_diff(to.0) as u32
+}
+
+// board b with piece p put down at (x, y) in orientation r, and the lines it cleared
+fn put(b: &Board, p: u8, r: u8, x: u8, y: u8) -> (Board, u32) {
+    let mut b = b.clone();
+    b.place(shape(p).coor(r), x, y, p + 1);
+    let lines = b.clear_full_rows().len() as u32;
+    (b, lines)
+}
+
+// play replay back, weighing every piece placed
+pub fn analyze(replay: &Replay) -> Report {
+    let mut play = replay.playback();
+    let mut script = Script::new(&replay.moves);
+    let g = &mut play.game;
+    let mut placements = Vec::new();
+    // the piece in play as it came in - the board, where, and the best drop - and the moves made with it
+    let mut spawn = None;
+    let mut moves = 0;
+    let mut dealt = u32::MAX;
+    loop {
+        if g.stats.pieces() != dealt {
+            dealt = g.stats.pieces();
+            let best = ai::best(g, &WEIGHTS).map(|b| {
+                let (x, r) = (b.x as i16, b.r);
+                let mut y = g.y as i16;
+                while !g.board.collides(shape(g.p).mask(r), x, y + 1) {
+                    y += 1;
+                }
+                (put(&g.board, g.p, r, x as u8, y as u8).0, b.score)
+            });
+            spawn = Some((g.board.clone(), (g.x, g.r), best));
+            moves = 0;
+        }
+        while !g.over {
+            match script.next(g) {
+                Some(Action::Move(m)) => {
+                    moves += matches!(m, Move::Left | Move::Right | Move::Rotate) as u32;
+                    g.apply(m);
+                }
+                Some(_) => (),
+                None => break,
+            }
+        }
+        let going = g.tick < replay.end && g.do_tick();
+        for e in g.events.drain(..) {
+            let (Event::Lock(p, r, x, y), Some((board, from, best))) = (e, &spawn) else {
+                continue;
+            };
+            let (played, lines) = put(board, p, r, x, y);
+            let score = ai::evaluate(&played, lines, &WEIGHTS);
+            let (best, top) = best.clone().unwrap_or((played.clone(), score));
+            placements.push(Placement {
+                tick: g.tick,
+                p,
+                loss: (top - score).max(0.0),
+                moves,
+                least: least(*from, (x, r)),
+                played,
+                best,
+            });
+        }
+        if !going {
+            break;
+        }
+    }
+    Report {
+        placements,
+        ticks: g.tick,
+    }
+}
+
+impl Report {
+    pub fn misdrops(&self) -> usize {
+        self.placements.iter().filter(|p| p.loss > MISDROP).count()
+    }
+
+    // placements made with more moves than needed
+    pub fn faults(&self) -> usize {
+        self.placements.iter().filter(|p| p.moves > p.least).count()
+    }
+
+    // pieces per second over each WINDOW of the game
+    pub fn pace(&self) -> Vec<f64> {
+        let n = self.ticks.div_ceil(WINDOW).max(1) as usize;
+        let mut counts = vec![0u32; n];
+        for p in &self.placements {
+            counts[((p.tick / WINDOW) as usize).min(n - 1)] += 1;
+        }
+        let secs = WINDOW as f64 * TICK.as_secs_f64();
+        counts.iter().map(|&c| c as f64 / secs).collect()
+    }
+}
+
+// when tick is into the game, m:ss
+fn clock(tick: u64) -> String {
+    let s = tick * TICK.as_millis() as u64 / 1000;
+    format!("{}:{:02}", s / 60, s % 60)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = self.placements.len();
+        let secs = self.ticks as f64 * TICK.as_secs_f64();
+        writeln!(f, "Pieces: {} in {}", n, clock(self.ticks))?;
+        if secs > 0.0 {
+            writeln!(f, "Pieces per second: {:.2}", n as f64 / secs)?;
+        }
+        writeln!(f, "Misdrops: {}", self.misdrops())?;
+        writeln!(f, "Finesse faults: {}", self.faults())?;
+        let pace = self.pace();
+        let samples: Vec<u32> = pace.iter().map(|&p| (p * 100.0) as u32).collect();
+        let top = samples.iter().copied().max().unwrap_or(0);
+        let bars = stats::chart(&samples, top, samples.len(), 1, &stats::BARS);
+        let each: Vec<String> = pace.iter().map(|p| format!("{:.1}", p)).collect();
+        writeln!(
+            f,
+            "Pace, each {}s: {} {}",
+            (WINDOW * TICK.as_millis() as u64) / 1000,
+            bars[0],
+            each.join(" ")
+        )?;
+
+        let mut worst: Vec<&Placement> = self.placements.iter().filter(|p| p.loss > 0.0).collect();
+        worst.sort_by(|a, b| b.loss.total_cmp(&a.loss));
+        for p in worst.into_iter().take(WORST) {
+            let name = &pieces::get().pieces[p.p as usize].name;
+            writeln!(
+                f,
+                "\n{} at {}, {:.2} short of the best:",
+                name,
+                clock(p.tick),
+                p.loss
+            )?;
+            let (played, best) = (p.played.to_ascii(), p.best.to_ascii());
+            let rows = played.lines().zip(best.lines());
+            let w = p.played.width() as usize;
+            writeln!(f, "{:w$}   best", "played", w = w)?;
+            let empty = |row: &str| row.chars().all(|c| c == '.');
+            for (a, b) in rows.skip_while(|(a, b)| empty(a) && empty(b)) {
+                writeln!(f, "{}   {}", a, b)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use crate::config::{self, Config};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+// A profile in one file, to take to another machine: everything in its
+// config directory - settings, keys, bests, puzzles solved, the campaign
+// and any replays kept there - as a toml table of paths and contents.
+// Another player's profiles, in profiles/ under the default one, stay out.
+
+const VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Archive {
+    unictris: u32, // the archive's format
+    files: BTreeMap<String, String>,
+}
+
+// the files under dir, by path from root with / between the parts
+fn gather(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.strip_prefix(root).unwrap_or(&path);
+        if name == Path::new("profiles") {
+            continue;
+        }
+        if path.is_dir() {
+            gather(root, &path, files)?;
+        } else if let Ok(src) = std::fs::read_to_string(&path) {
+            let parts: Vec<_> = name.iter().map(|p| p.to_string_lossy()).collect();
+            files.insert(parts.join("/"), src);
+        }
+    }
+    Ok(())
+}
+
+// the profile being played written to path; the files it took
+pub fn export(path: &Path) -> Result<usize> {
+    let mut files = BTreeMap::new();
+    if let Some(dir) = config::dir().filter(|d| d.is_dir()) {
+        gather(&dir, &dir, &mut files)?;
+    }
+    let n = files.len();
+    let archive = Archive {
+        unictris: VERSION,
+        files,
+    };
+    let src = toml::to_string(&archive).map_err(|e| io::Error::other(e.to_string()))?;
+    std::fs::write(path, src)?;
+    Ok(n)
+}
+
+// name as a path under the config directory, if it stays there
+fn inside(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(name);
+    let plain = path.components().all(|c| matches!(c, Component::Normal(_)));
+    (plain && !name.is_empty() && !path.starts_with("profiles")).then_some(path)
+}
+
+// the archive at path unpacked over the profile being played, its files
+// replacing those there of the same name; the files it held
+pub fn import(path: &Path) -> Result<usize> {
+    let corrupt = |msg: String| Error::Corrupt {
+        path: path.to_path_buf(),
+        msg,
+    };
+    let src = std::fs::read_to_string(path)?;
+    let archive: Archive = toml::from_str(&src).map_err(|e| corrupt(e.to_string()))?;
+    if archive.unictris != VERSION {
+        let msg = format!("format {}, not {}", archive.unictris, VERSION);
+        return Err(corrupt(msg));
+    }
+    let mut files = Vec::new();
+    for (name, src) in &archive.files {
+        let file =
+            inside(name).ok_or_else(|| corrupt(format!("'{}' is not a file to keep", name)))?;
+        files.push((file, src));
+    }
+    // checked before anything is written, not to leave a profile that won't start
+    if let Some(src) = archive.files.get("config.toml") {
+        Config::parse(src).map_err(|e| corrupt(format!("config.toml: {}", e)))?;
+    }
+    let Some(dir) = config::dir() else {
+        return Err(Error::Config(
+            "there is no config directory to import into".to_string(),
+        ));
+    };
+    for (file, src) in &files {
+        let file = dir.join(file);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(file, src)?;
+    }
+    Ok(files.len())
+}
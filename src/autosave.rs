@@ -0,0 +1,46 @@
+use crate::config::{self, Config};
+use crate::replay::Replay;
+use std::io;
+use std::path::PathBuf;
+
+// The game in play, saved as a replay every so often to autosave.txt in
+// the config directory and taken away once the game ends or is left - so
+// one still there at startup is a game the process died in, to take up
+// again where the last save had it.
+
+pub const EVERY: u64 = 500; // ticks between saves, 5s
+
+fn path() -> Option<PathBuf> {
+    config::dir().map(|d| d.join("autosave.txt"))
+}
+
+// rec, the game so far, saved - written aside first, so that dying halfway
+// through doesn't leave half a file
+pub fn save(rec: &Replay) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let aside = path.with_extension("tmp");
+    rec.save(&aside)?;
+    std::fs::rename(aside, path)
+}
+
+// the game left unfinished, if there is one that can still be played
+pub fn load(cfg: &Config) -> Option<Replay> {
+    let src = std::fs::read_to_string(path()?).ok()?;
+    Replay::parse(&src, cfg).ok()
+}
+
+// there's no game to take up
+pub fn clear() -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,93 @@
+use crate::config;
+use crate::error::{Error, Result};
+use crate::game::Game;
+use crate::mode::{self, Mode};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+
+// Personal bests, kept in best.toml in the config directory.
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Best {
+    pub sprint: Option<Sprint>,
+}
+
+// the fastest sprint, in ticks
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sprint {
+    pub time: u64,
+    pub splits: Vec<u64>, // tick each SPLIT lines were reached, the last being the finish
+    #[serde(default)]
+    pub timeline: Vec<u64>, // tick each line was cleared, for the ghost
+}
+
+fn path() -> Option<PathBuf> {
+    config::dir().map(|d| d.join("best.toml"))
+}
+
+impl Best {
+    // the saved bests, none if there are no saved ones
+    pub fn load() -> Result<Best> {
+        let Some(path) = path() else {
+            return Ok(Best::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(src) => toml::from_str(&src).map_err(|e| Error::Corrupt {
+                msg: e.to_string(),
+                path,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Best::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let src = toml::to_string(self).map_err(|e| io::Error::other(e.to_string()))?;
+        std::fs::write(path, src)
+    }
+
+    // the best sprint's splits, to race against
+    pub fn pace(&self) -> Vec<u64> {
+        self.sprint
+            .as_ref()
+            .map_or(Vec::new(), |s| s.splits.clone())
+    }
+
+    // ... and when it cleared each line, to show where it was
+    pub fn ghost(&self) -> Vec<u64> {
+        self.sprint
+            .as_ref()
+            .map_or(Vec::new(), |s| s.timeline.clone())
+    }
+
+    // where a game is against the best: the lines the best had cleared by
+    // the same tick
+    pub fn lines_at(ghost: &[u64], tick: u64) -> u32 {
+        ghost.partition_point(|&t| t <= tick) as u32
+    }
+
+    // take note of a finished game; true if it set a new best
+    pub fn record(&mut self, g: &Game) -> bool {
+        if g.cfg().mode != Mode::Sprint || g.lines < mode::SPRINT_LINES {
+            return false;
+        }
+        if self.sprint.as_ref().is_some_and(|s| s.time <= g.tick) {
+            return false;
+        }
+        self.sprint = Some(Sprint {
+            time: g.tick,
+            splits: g.splits.clone(),
+            timeline: g.timeline.clone(),
+        });
+        true
+    }
+}
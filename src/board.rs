@@ -0,0 +1,438 @@
+use crate::pieces;
+use rand::prelude::*;
+use std::fmt;
+
+// The playfield: one u16 bitmask per row for collision checks and clears,
+// with a colour layer alongside recording which piece filled each cell.
+
+pub const MAX_WIDTH: u8 = 16;
+pub const GARBAGE: u8 = u8::MAX; // colour layer value for garbage cells
+pub const ITEM: u8 = u8::MAX - 1; // ... and for garbage cells holding an item
+pub const FLOOR: u8 = u8::MAX - 2; // ... and for the solid floor rows
+
+#[derive(Clone, PartialEq)]
+pub struct Board {
+    width: u8,
+    rows: Vec<u16>,        // bit j set when column j is filled; row 0 is the top
+    colors: Vec<[u8; 16]>, // tetromino+1 for filled cells, 0 for empty
+    floor: u8,             // solid rows at the bottom, never cleared
+}
+
+impl Board {
+    pub fn new(width: u8, height: u8) -> Board {
+        assert!(width <= MAX_WIDTH, "board is at most {} wide", MAX_WIDTH);
+        Board {
+            width,
+            rows: vec![0; height as usize],
+            colors: vec![[0; 16]; height as usize],
+            floor: 0,
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.rows.len() as u8
+    }
+
+    pub fn floor(&self) -> u8 {
+        self.floor
+    }
+
+    // first row of the floor, the height when there is none
+    fn floor_row(&self) -> usize {
+        self.rows.len() - self.floor as usize
+    }
+
+    fn full(&self) -> u16 {
+        ((1u32 << self.width) - 1) as u16
+    }
+
+    // tetromino+1 at (x, y), 0 if empty
+    pub fn get(&self, x: u8, y: u8) -> u8 {
+        self.colors[y as usize][x as usize]
+    }
+
+    pub fn is_filled(&self, y: u8) -> bool {
+        self.rows[y as usize] == self.full()
+    }
+
+    // would a shape with row masks `mask` at (x, y) overlap the stack or leave the board?
+    pub fn collides(&self, mask: &[u16; 4], x: i16, y: i16) -> bool {
+        mask.iter().enumerate().any(|(i, &m)| {
+            let row = y + i as i16;
+            if m == 0 {
+                false
+            } else if row < 0 || row >= self.height() as i16 || x >= self.width as i16 {
+                true
+            } else if x < 0 {
+                m & ((1 << -x) - 1) != 0 || self.rows[row as usize] & m >> -x != 0
+            } else {
+                let m = (m as u32) << x;
+                m > self.full() as u32 || self.rows[row as usize] as u32 & m != 0
+            }
+        })
+    }
+
+    // fill cells offset by (x, y) with value v (tetromino+1)
+    pub fn place(&mut self, cells: &[(u8, u8); 4], x: u8, y: u8, v: u8) {
+        for &(i, j) in cells {
+            let (row, col) = ((y + i) as usize, x + j);
+            self.rows[row] |= 1 << col;
+            self.colors[row][col as usize] = v;
+        }
+    }
+
+    // filled rows, apart from the floor
+    pub fn full_rows(&self) -> Vec<u8> {
+        (0..self.floor_row() as u8)
+            .filter(|&y| self.is_filled(y))
+            .collect()
+    }
+
+    // remove filled rows, moving everything above down; returns the rows removed
+    pub fn clear_full_rows(&mut self) -> Vec<u8> {
+        let full = self.full_rows();
+        for &y in &full {
+            self.rows.remove(y as usize);
+            self.rows.insert(0, 0);
+            self.colors.remove(y as usize);
+            self.colors.insert(0, [0; 16]);
+        }
+        full
+    }
+
+    // fill cell (x, y) with v, or empty it for 0
+    pub fn set(&mut self, x: u8, y: u8, v: u8) {
+        match v {
+            0 => self.rows[y as usize] &= !(1 << x),
+            _ => self.rows[y as usize] |= 1 << x,
+        }
+        self.colors[y as usize][x as usize] = v;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&r| r == 0)
+    }
+
+    // change what a filled cell holds
+    pub fn mark(&mut self, x: u8, y: u8, v: u8) {
+        if self.rows[y as usize] & 1 << x != 0 {
+            self.colors[y as usize][x as usize] = v;
+        }
+    }
+
+    // take away the bottom n rows above the floor, moving everything above down
+    pub fn remove_bottom(&mut self, n: u8) {
+        let f = self.floor_row();
+        let n = (n as usize).min(f);
+        self.rows.drain(f - n..f);
+        self.colors.drain(f - n..f);
+        self.rows.splice(..0, std::iter::repeat_n(0, n));
+        self.colors.splice(..0, std::iter::repeat_n([0; 16], n));
+    }
+
+    // shuffle the cells of every row - none gets filled or emptied
+    pub fn scramble(&mut self, rng: &mut dyn RngCore) {
+        let w = self.width as usize;
+        for (row, colors) in self.rows.iter_mut().zip(&mut self.colors) {
+            if *row != 0 {
+                colors[..w].shuffle(rng);
+                *row = (0..w)
+                    .filter(|&j| colors[j] != 0)
+                    .fold(0, |r, j| r | 1 << j);
+            }
+        }
+    }
+
+    // push the stack up n rows, filling in garbage above the floor with an empty cell
+    // at column hole; false if that pushed blocks out of the top
+    pub fn insert_garbage(&mut self, n: u8, hole: u8) -> bool {
+        let n = (n as usize).min(self.floor_row());
+        let fits = self.rows[..n].iter().all(|&r| r == 0);
+        let row = self.full() & !(1 << hole);
+        let mut colors = [GARBAGE; 16];
+        colors[hole as usize] = 0;
+        self.rows.drain(..n);
+        self.colors.drain(..n);
+        let f = self.floor_row();
+        self.rows.splice(f..f, std::iter::repeat_n(row, n));
+        self.colors.splice(f..f, std::iter::repeat_n(colors, n));
+        fits
+    }
+
+    // turn the bottom row above the floor into floor, whatever was in it
+    pub fn raise_floor(&mut self) {
+        if self.floor_row() > 0 {
+            self.floor += 1;
+            let y = self.floor_row();
+            self.rows[y] = self.full();
+            self.colors[y] = [FLOOR; 16];
+        }
+    }
+
+    fn filled(&self, x: u8, y: u8) -> bool {
+        self.rows[y as usize] & 1 << x != 0
+    }
+
+    // connected groups of filled cells, as (x, y) lists
+    pub fn groups(&self) -> Vec<Vec<(u8, u8)>> {
+        let mut seen = vec![0u16; self.rows.len()];
+        let mut groups = Vec::new();
+        for y in 0..self.height() {
+            for x in 0..self.width {
+                if !self.filled(x, y) || seen[y as usize] & 1 << x != 0 {
+                    continue;
+                }
+                seen[y as usize] |= 1 << x;
+                let mut group = vec![(x, y)];
+                let mut i = 0;
+                while i < group.len() {
+                    let (x, y) = group[i];
+                    i += 1;
+                    let near = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+                    for (x, y) in near {
+                        if x < self.width
+                            && y < self.height()
+                            && self.filled(x, y)
+                            && seen[y as usize] & 1 << x == 0
+                        {
+                            seen[y as usize] |= 1 << x;
+                            group.push((x, y));
+                        }
+                    }
+                }
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    // let every group of blocks fall until it lands on the floor or another group;
+    // true if anything moved
+    pub fn cascade(&mut self) -> bool {
+        let mut groups = self.groups();
+        let mut moved = false;
+        loop {
+            let mut step = false;
+            for group in &mut groups {
+                let free = group.iter().all(|&(x, y)| {
+                    y + 1 < self.height() && (!self.filled(x, y + 1) || group.contains(&(x, y + 1)))
+                });
+                if !free {
+                    continue;
+                }
+                let cells: Vec<u8> = group.iter().map(|&(x, y)| self.get(x, y)).collect();
+                for &(x, y) in group.iter() {
+                    self.rows[y as usize] &= !(1 << x);
+                    self.colors[y as usize][x as usize] = 0;
+                }
+                for (cell, v) in group.iter_mut().zip(cells) {
+                    cell.1 += 1;
+                    self.rows[cell.1 as usize] |= 1 << cell.0;
+                    self.colors[cell.1 as usize][cell.0 as usize] = v;
+                }
+                step = true;
+            }
+            if !step {
+                return moved;
+            }
+            moved = true;
+        }
+    }
+}
+
+// Board analysis, shared by the AI, the danger indicator and end-of-game stats.
+impl Board {
+    // height of each column's stack, measured from the floor
+    pub fn column_heights(&self) -> Vec<u8> {
+        (0..self.width)
+            .map(|x| {
+                self.rows
+                    .iter()
+                    .position(|r| r & 1 << x != 0)
+                    .map_or(0, |y| self.height() - y as u8)
+            })
+            .collect()
+    }
+
+    // empty cells with a filled cell somewhere above them
+    pub fn holes(&self) -> u32 {
+        let mut covered = 0u16;
+        let mut n = 0;
+        for &r in &self.rows {
+            n += (covered & !r).count_ones();
+            covered |= r;
+        }
+        n
+    }
+
+    // sum of height differences between neighbouring columns
+    pub fn bumpiness(&self) -> u32 {
+        self.column_heights()
+            .windows(2)
+            .map(|w| w[0].abs_diff(w[1]) as u32)
+            .sum()
+    }
+
+    pub fn aggregate_height(&self) -> u32 {
+        self.column_heights().iter().map(|&h| h as u32).sum()
+    }
+
+    // filled/empty changes along each row, the walls counting as filled
+    pub fn row_transitions(&self) -> u32 {
+        let walls = 1u32 << (self.width + 1) | 1;
+        self.rows
+            .iter()
+            .map(|&r| {
+                let r = (r as u32) << 1 | walls;
+                (r ^ r >> 1).count_ones() - 1
+            })
+            .sum()
+    }
+
+    // filled/empty changes down each column, the floor counting as filled
+    pub fn column_transitions(&self) -> u32 {
+        let mut prev = 0u16;
+        let mut n = 0;
+        for &r in &self.rows {
+            n += (prev ^ r).count_ones();
+            prev = r;
+        }
+        n + (prev ^ self.full()).count_ones()
+    }
+
+    // every row as text, top to bottom, for tests and bug reports: '.' for
+    // an empty cell, a piece's letter for its blocks, '#' for garbage, '*'
+    // for an item and '=' for the floor
+    pub fn to_ascii(&self) -> String {
+        self.to_string()
+    }
+
+    // the first way the masks, the colour layer and the floor disagree, if any
+    pub fn check(&self) -> Result<(), String> {
+        for (y, (&row, colors)) in self.rows.iter().zip(&self.colors).enumerate() {
+            if row & !self.full() != 0 {
+                return Err(format!(
+                    "row {} has cells past column {}",
+                    y,
+                    self.width - 1
+                ));
+            }
+            for (x, &v) in colors.iter().enumerate().take(self.width as usize) {
+                let filled = row & 1 << x != 0;
+                if filled != (v != 0) {
+                    return Err(format!(
+                        "cell ({}, {}) is {} in the mask but {} in colour",
+                        x, y, filled, v
+                    ));
+                }
+            }
+            if y >= self.floor_row() && (row != self.full() || colors[0] != FLOOR) {
+                return Err(format!("row {} is in the floor but isn't floor", y));
+            }
+        }
+        Ok(())
+    }
+}
+
+// the character for colour layer value v in the text form
+pub fn symbol(v: u8) -> char {
+    match v {
+        0 => '.',
+        GARBAGE => '#',
+        ITEM => '*',
+        FLOOR => '=',
+        v => pieces::get()
+            .pieces
+            .get(v as usize - 1)
+            .and_then(|p| p.name.chars().next())
+            .unwrap_or('?'),
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height() {
+            for x in 0..self.width {
+                write!(f, "{}", symbol(self.get(x, y)))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a board from its text form, a line a row, floor rows and all
+    fn board(text: &str) -> Board {
+        let lines: Vec<&str> = text.split_whitespace().collect();
+        let mut b = Board::new(lines[0].len() as u8, lines.len() as u8);
+        for _ in lines.iter().filter(|l| l.starts_with('=')) {
+            b.raise_floor();
+        }
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate().filter(|&(_, c)| c != '=') {
+                let v = (0..=u8::MAX).find(|&v| symbol(v) == c).unwrap();
+                b.set(x as u8, y as u8, v);
+            }
+        }
+        b.check().unwrap();
+        b
+    }
+
+    fn text(b: &Board) -> Vec<String> {
+        b.to_ascii().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn the_text_form_is_every_cell() {
+        let src = "..... ..I.. #.I.* ##I## =====";
+        let b = board(src);
+        assert_eq!(text(&b), src.split(' ').collect::<Vec<_>>());
+        assert_eq!(b.to_string(), b.to_ascii());
+    }
+
+    #[test]
+    fn clearing_drops_the_rows_above() {
+        let mut b = board("..O.. ##### .I.I. ##### ##.##");
+        assert_eq!(b.full_rows(), [1, 3]);
+        assert_eq!(b.clear_full_rows(), [1, 3]);
+        assert_eq!(text(&b), [".....", ".....", "..O..", ".I.I.", "##.##"]);
+        b.check().unwrap();
+    }
+
+    #[test]
+    fn garbage_pushes_the_stack_up_with_a_hole() {
+        let mut b = board("..... ..... ..... .OO..");
+        assert!(b.insert_garbage(2, 3));
+        assert_eq!(text(&b), [".....", ".OO..", "###.#", "###.#"]);
+        assert!(!b.insert_garbage(2, 0)); // out of the top
+        assert_eq!(text(&b), ["###.#", "###.#", ".####", ".####"]);
+    }
+
+    #[test]
+    fn the_floor_stays_put() {
+        let mut b = board("..... ..... #.### =====");
+        assert_eq!(b.floor(), 1);
+        b.raise_floor();
+        assert_eq!(text(&b), [".....", ".....", "=====", "====="]);
+        assert!(b.full_rows().is_empty());
+        assert!(b.clear_full_rows().is_empty());
+        assert!(b.insert_garbage(1, 0));
+        assert_eq!(text(&b), [".....", ".####", "=====", "====="]);
+        b.remove_bottom(3);
+        assert_eq!(text(&b), [".....", ".....", "=====", "====="]);
+        b.check().unwrap();
+    }
+}
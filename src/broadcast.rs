@@ -0,0 +1,208 @@
+use crate::cast::escape;
+use crate::clipboard::base64;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tetrisct::board;
+use tetrisct::position;
+use tetrisct::view::View;
+
+// The game as it goes, for overlays in a browser: --broadcast serves
+// WebSocket connections, and every connected client gets the state as a
+// JSON text message, at most every INTERVAL. Only ever written to - what
+// clients send is not read. Sending is done on a thread of its own, so a
+// slow client holds up neither the game nor the screen.
+
+const INTERVAL: Duration = Duration::from_millis(50);
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11"; // RFC 6455's, for the handshake
+
+static CLIENTS: Mutex<Vec<TcpStream>> = Mutex::new(Vec::new());
+static STATES: OnceLock<SyncSender<String>> = OnceLock::new();
+
+// listen on addr, "host:port" or ":port" for this machine only
+pub fn start(addr: &str) -> io::Result<()> {
+    let addr = match addr.strip_prefix(':') {
+        Some(port) => format!("127.0.0.1:{}", port),
+        None => addr.to_string(),
+    };
+    let listener = TcpListener::bind(&addr)
+        .map_err(|e| io::Error::new(e.kind(), format!("--broadcast {}: {}", addr, e)))?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || accept(stream));
+        }
+    });
+    let (tx, rx) = mpsc::sync_channel(1);
+    STATES.set(tx).ok();
+    std::thread::spawn(move || sender(rx));
+    Ok(())
+}
+
+// the handshake, then the client is added to those sent to
+fn accept(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut key = None;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+    let Some(key) = key else {
+        return stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n");
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    stream.set_write_timeout(Some(INTERVAL))?;
+    CLIENTS.lock().unwrap().push(stream);
+    Ok(())
+}
+
+// each state to every client, as a text frame; clients that can't keep up are dropped
+fn sender(states: Receiver<String>) {
+    while let Ok(state) = states.recv() {
+        let mut frame = vec![0x81]; // final fragment, text
+        match state.len() {
+            n @ 0..=125 => frame.push(n as u8),
+            n @ 126..=0xffff => {
+                frame.push(126);
+                frame.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                frame.push(127);
+                frame.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(state.as_bytes());
+        CLIENTS
+            .lock()
+            .unwrap()
+            .retain_mut(|c| c.write_all(&frame).is_ok());
+        std::thread::sleep(INTERVAL);
+    }
+}
+
+// hand v over to be sent, unless the last state is still on its way
+pub fn send(v: &View) {
+    if let Some(tx) = STATES.get() {
+        if !CLIENTS.lock().unwrap().is_empty() {
+            tx.try_send(state(v)).ok();
+        }
+    }
+}
+
+// v as JSON
+pub fn state(v: &View) -> String {
+    let name = |p: u8| format!("\"{}\"", escape(&position::letters(&[p])));
+    let rows: Vec<String> = (0..v.height)
+        .map(|y| {
+            let row: String = (0..v.width).map(|x| board::symbol(v.cell(x, y))).collect();
+            format!("\"{}\"", escape(&row))
+        })
+        .collect();
+    let next: Vec<String> = v.next.iter().map(|&(p, _)| name(p)).collect();
+    let garbage: u32 = v.garbage.iter().map(|b| b.lines).sum();
+    format!(
+        "{{\"tick\":{},\"score\":{},\"level\":{},\"lines\":{},\"combo\":{},\"paused\":{},\"piece\":{},\"next\":[{}],\"garbage\":{},\"board\":[{}]}}",
+        v.tick,
+        v.score,
+        v.level,
+        v.lines,
+        v.combo,
+        v.paused,
+        name(v.p),
+        next.join(","),
+        garbage,
+        rows.join(",")
+    )
+}
+
+// what the handshake answers the client's Sec-WebSocket-Key with
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+// SHA-1, which the WebSocket handshake wants
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(wi);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+    let mut out = [0; 20];
+    for (i, x) in h.iter().enumerate() {
+        out[4 * i..4 * i + 4].copy_from_slice(&x.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha1_of_the_standard_vectors() {
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        let two_blocks = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(
+            hex(&sha1(two_blocks)),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn the_handshake_answers_rfc_6455s_sample_key() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+}
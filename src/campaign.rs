@@ -0,0 +1,215 @@
+use crate::config::{self, Config, MAX_HEIGHT};
+use crate::error::{Error, Result};
+use crate::game::{Game, TICK};
+use crate::mode::{self, Mode};
+use crate::pack::{self, Progress};
+use crate::palette::Theme;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// The campaign: challenges taken one after another, each met unlocking a
+// mode or a theme that came with it and can't be played with until then -
+// what was there before is never locked. How far the player has got is
+// kept in profile.toml in the config directory.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Challenge {
+    Level(u64),         // reach this level in marathon, from level 1
+    Sprint(u64),        // finish a sprint in fewer ticks
+    Pack(&'static str), // solve every puzzle in the bundled pack of this title
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reward {
+    Mode(Mode),
+    Theme(Theme),
+}
+
+pub const CAMPAIGN: [(Challenge, Reward); 3] = [
+    (Challenge::Level(5), Reward::Mode(Mode::Ultra)),
+    (Challenge::Sprint(18000), Reward::Theme(Theme::Neon)),
+    (
+        Challenge::Pack("Clear in one"),
+        Reward::Theme(Theme::Pastel),
+    ),
+];
+
+// ticks as m:ss
+fn clock(ticks: u64) -> String {
+    let s = ticks * TICK.as_millis() as u64 / 1000;
+    format!("{}:{:02}", s / 60, s % 60)
+}
+
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Challenge::Level(n) => write!(f, "Reach level {} in marathon from level 1", n),
+            Challenge::Sprint(t) => write!(
+                f,
+                "Sprint {} lines in under {}",
+                mode::SPRINT_LINES,
+                clock(*t)
+            ),
+            Challenge::Pack(title) => write!(f, "Solve every puzzle in {}", title),
+        }
+    }
+}
+
+impl fmt::Display for Reward {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reward::Mode(m) => write!(f, "{} mode", format!("{:?}", m).to_lowercase()),
+            Reward::Theme(t) => write!(f, "the {} theme", format!("{:?}", t).to_lowercase()),
+        }
+    }
+}
+
+impl Challenge {
+    // is it met by game g, if one was just played, or by the puzzles solved?
+    fn met(&self, g: Option<&Game>, progress: &Progress) -> bool {
+        let played = |m: Mode| g.filter(|g| g.cfg().mode == m);
+        match *self {
+            Challenge::Level(n) => {
+                played(Mode::Marathon).is_some_and(|g| g.cfg().level == 1 && g.level() >= n)
+            }
+            Challenge::Sprint(t) => {
+                played(Mode::Sprint).is_some_and(|g| g.lines >= mode::SPRINT_LINES && g.tick < t)
+            }
+            Challenge::Pack(title) => (pack::bundled(MAX_HEIGHT).unwrap_or_default().iter())
+                .find(|p| p.title == title)
+                .is_some_and(|p| progress.count(p) == p.puzzles.len()),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub campaign: usize, // challenges met
+}
+
+fn path() -> Option<PathBuf> {
+    config::dir().map(|d| d.join("profile.toml"))
+}
+
+impl Profile {
+    pub fn load() -> Result<Profile> {
+        let Some(path) = path() else {
+            return Ok(Profile::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(src) => toml::from_str(&src).map_err(|e| Error::Corrupt {
+                msg: e.to_string(),
+                path,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Profile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let src = toml::to_string(self).map_err(|e| io::Error::other(e.to_string()))?;
+        std::fs::write(path, src)
+    }
+
+    // is reward still to be earned?
+    pub fn locked(&self, reward: Reward) -> bool {
+        CAMPAIGN[self.campaign.min(CAMPAIGN.len())..]
+            .iter()
+            .any(|&(_, r)| r == reward)
+    }
+
+    // can cfg be played with, its mode and theme unlocked?
+    pub fn check(&self, cfg: &Config) -> Result<()> {
+        for reward in [Reward::Mode(cfg.mode), Reward::Theme(cfg.theme)] {
+            if let Some((c, _)) = CAMPAIGN.iter().find(|&&(_, r)| r == reward) {
+                if self.locked(reward) {
+                    let msg = format!(
+                        "{} is locked: {} to unlock it (--campaign shows how far you are)",
+                        reward,
+                        c.to_string().to_lowercase()
+                    );
+                    return Err(Error::Config(msg));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // go on through the challenges met, by game g if one was just played or
+    // the puzzles solved; the rewards they unlocked
+    pub fn advance(&mut self, g: Option<&Game>, progress: &Progress) -> Vec<Reward> {
+        let mut unlocked = Vec::new();
+        while let Some(&(c, reward)) = CAMPAIGN.get(self.campaign) {
+            if !c.met(g, progress) {
+                break;
+            }
+            self.campaign += 1;
+            unlocked.push(reward);
+        }
+        unlocked
+    }
+}
+
+// the campaign, with what has been met and what is next
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let done = self.campaign.min(CAMPAIGN.len());
+        writeln!(f, "Campaign: {} of {} done", done, CAMPAIGN.len())?;
+        for (i, (c, r)) in CAMPAIGN.iter().enumerate() {
+            match i.cmp(&done) {
+                std::cmp::Ordering::Less => writeln!(f, "  done  {} - unlocked {}", c, r)?,
+                std::cmp::Ordering::Equal => writeln!(f, "  next  {} - unlocks {}", c, r)?,
+                std::cmp::Ordering::Greater => writeln!(f, "        {} - unlocks {}", c, r)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_what_came_with_the_campaign_is_locked() {
+        let fresh = Profile::default();
+        let modes = [Mode::Marathon, Mode::Survival, Mode::Rising, Mode::Sprint];
+        let themes = [
+            Theme::Glyphs,
+            Theme::Emoji,
+            Theme::Winter,
+            Theme::Halloween,
+            Theme::Seasonal,
+            Theme::Mono,
+            Theme::Ascii,
+        ];
+        for mode in modes {
+            for theme in themes {
+                let cfg = Config {
+                    mode,
+                    theme,
+                    ..Config::default()
+                };
+                assert!(fresh.check(&cfg).is_ok(), "{:?} {:?}", mode, theme);
+            }
+        }
+        let ultra = Config {
+            mode: Mode::Ultra,
+            ..Config::default()
+        };
+        assert!(fresh.check(&ultra).is_err());
+        let done = Profile {
+            campaign: CAMPAIGN.len(),
+        };
+        assert!(done.check(&ultra).is_ok());
+    }
+}
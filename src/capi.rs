@@ -0,0 +1,192 @@
+use crate::config::Config;
+use crate::game::{Game, MOVES};
+use std::cell::RefCell;
+
+// The engine for frontends not written in Rust, and for research code that
+// wants the real rules without the terminal: games are made, stepped, fed
+// moves and looked at through plain C functions, declared for C in
+// include/tetrisct.h - which is written to match this file, so a change to
+// one is a change to both. Games are held here and known to the caller by a
+// handle, a small number, so nothing is handed out to be freed the wrong way
+// or used after; handles are good on the thread that made them. Calls with a
+// handle that isn't one return -1.
+
+thread_local! {
+    static GAMES: RefCell<Vec<Option<Game>>> = const { RefCell::new(Vec::new()) };
+}
+
+// f on game h, or -1 with no such game
+fn with<T: Into<i64>>(h: i32, f: impl FnOnce(&mut Game) -> T) -> i64 {
+    GAMES.with(|games| {
+        let mut games = games.borrow_mut();
+        match usize::try_from(h)
+            .ok()
+            .and_then(|h| games.get_mut(h)?.as_mut())
+        {
+            Some(g) => f(g).into(),
+            None => -1,
+        }
+    })
+}
+
+// a new game on a width x height board (0 for the default, 10 x 20) dealt
+// from seed; the same seed and moves play out the same. -1 for a size the
+// config wouldn't take
+#[no_mangle]
+pub extern "C" fn tetris_new(width: u8, height: u8, seed: u64) -> i32 {
+    let mut cfg = Config::default();
+    if width > 0 {
+        cfg.width = width;
+    }
+    if height > 0 {
+        cfg.height = height;
+    }
+    let Ok(cfg) = cfg.validate() else {
+        return -1;
+    };
+    let g = Game::with_seed(&cfg, seed);
+    GAMES.with(|games| {
+        let mut games = games.borrow_mut();
+        match games.iter().position(Option::is_none) {
+            Some(h) => {
+                games[h] = Some(g);
+                h as i32
+            }
+            None => {
+                games.push(Some(g));
+                games.len() as i32 - 1
+            }
+        }
+    })
+}
+
+// done with game h; its handle may be given to a later game
+#[no_mangle]
+pub extern "C" fn tetris_free(h: i32) {
+    GAMES.with(|games| {
+        let mut games = games.borrow_mut();
+        if let Some(g) = usize::try_from(h).ok().and_then(|h| games.get_mut(h)) {
+            *g = None;
+        }
+    });
+}
+
+// advance the game ticks ticks of 10ms; 1 while it goes on, 0 once it is over
+#[no_mangle]
+pub extern "C" fn tetris_step(h: i32, ticks: u32) -> i32 {
+    with(h, |g| (0..ticks).all(|_| g.do_tick()) as i32) as i32
+}
+
+// make move m, numbered as TETRIS_LEFT and the rest; 0, or -1 with no such move
+#[no_mangle]
+pub extern "C" fn tetris_apply(h: i32, m: i32) -> i32 {
+    let Some(&m) = usize::try_from(m).ok().and_then(|m| MOVES.get(m)) else {
+        return -1;
+    };
+    with(h, |g| {
+        if !g.over {
+            g.apply(m);
+        }
+        0
+    }) as i32
+}
+
+// the cell at column x, row y from the top, counting the falling piece:
+// 0 empty, 1 to 7 a tetromino's, TETRIS_GARBAGE and the rest; -1 off the board
+#[no_mangle]
+pub extern "C" fn tetris_cell(h: i32, x: i32, y: i32) -> i32 {
+    with(h, |g| {
+        let (w, ht) = (g.board.width() as i32, g.board.height() as i32);
+        if (0..w).contains(&x) && (0..ht).contains(&y) {
+            g.cell(x as u8, y as u8) as i32
+        } else {
+            -1
+        }
+    }) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_width(h: i32) -> i32 {
+    with(h, |g| g.board.width()) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_height(h: i32) -> i32 {
+    with(h, |g| g.board.height()) as i32
+}
+
+// the falling tetromino, numbered from 0 as in the piece set - its cells less one
+#[no_mangle]
+pub extern "C" fn tetris_piece(h: i32) -> i32 {
+    with(h, |g| g.p) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_score(h: i32) -> i64 {
+    with(h, |g| g.score)
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_lines(h: i32) -> i64 {
+    with(h, |g| g.lines)
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_tick(h: i32) -> i64 {
+    with(h, |g| g.tick as i64)
+}
+
+// 1 once the game is over, else 0
+#[no_mangle]
+pub extern "C" fn tetris_over(h: i32) -> i32 {
+    with(h, |g| g.over as i32) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MAX_WIDTH;
+    use crate::config::{MAX_HEIGHT, MIN_SIZE};
+
+    #[test]
+    fn sizes_the_config_wouldnt_take_make_no_game() {
+        for (w, h) in [
+            (MAX_WIDTH + 1, 0),
+            (MIN_SIZE - 1, 0),
+            (0, MIN_SIZE - 1),
+            (0, MAX_HEIGHT + 1),
+        ] {
+            assert_eq!(tetris_new(w, h, 1), -1, "{} x {}", w, h);
+        }
+        let h = tetris_new(0, 0, 1);
+        assert_eq!((tetris_width(h), tetris_height(h)), (10, 20));
+        tetris_free(h);
+    }
+
+    #[test]
+    fn a_handle_that_isnt_one_is_refused() {
+        for h in [-1, 1000] {
+            assert_eq!(tetris_step(h, 1), -1);
+            assert_eq!(tetris_apply(h, 0), -1);
+            assert_eq!(tetris_cell(h, 0, 0), -1);
+            assert_eq!(tetris_score(h), -1);
+        }
+        let h = tetris_new(0, 0, 1);
+        tetris_free(h);
+        assert_eq!(tetris_over(h), -1); // freed
+    }
+
+    #[test]
+    fn a_drop_and_a_step_lock_the_piece() {
+        let h = tetris_new(0, 0, 7);
+        assert_eq!(tetris_apply(h, MOVES.len() as i32), -1);
+        let filled = |h| (0..10).filter(|&x| tetris_cell(h, x, 19) > 0).count();
+        assert_eq!(filled(h), 0);
+        assert_eq!(tetris_apply(h, 3), 0); // TETRIS_HARD_DROP
+        assert_eq!(tetris_step(h, 1), 1);
+        assert!(filled(h) > 0);
+        assert_eq!(tetris_tick(h), 1);
+        assert_eq!(tetris_cell(h, 10, 0), -1);
+        tetris_free(h);
+    }
+}
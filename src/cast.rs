@@ -0,0 +1,197 @@
+use crossterm::style::Color;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tetrisct::palette::{self, rgb};
+
+// Recording to an asciicast (asciinema v2) file: everything drawn goes
+// through Term, which passes it on to the terminal and, while recording,
+// writes each flushed frame as an output event with its time. Without
+// colour, or with few, Term takes the colours out of what it is given on the
+// way or brings them down to those there are.
+
+struct Cast {
+    file: BufWriter<File>,
+    start: Instant,
+    frame: Vec<u8>, // written since the last flush
+}
+
+static CAST: Mutex<Option<Cast>> = Mutex::new(None);
+static COLORS: AtomicU8 = AtomicU8::new(FULL); // what colours the terminal gets
+static ESCAPE: Mutex<Vec<u8>> = Mutex::new(Vec::new()); // an escape sequence begun in an earlier write
+const FULL: u8 = 0; // all there are
+const BASIC: u8 = 1; // ... the 16 basic ones
+const NONE: u8 = 2;
+
+// the terminal, as the game draws on it
+pub struct Term(io::Stdout);
+
+pub fn stdout() -> Term {
+    Term(io::stdout())
+}
+
+impl Write for Term {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let colors = COLORS.load(Ordering::Relaxed);
+        if colors != FULL {
+            let out = recolor(buf, colors);
+            self.0.write_all(&out)?;
+            if let Some(cast) = CAST.lock().unwrap().as_mut() {
+                cast.frame.extend_from_slice(&out);
+            }
+            return Ok(buf.len());
+        }
+        let n = self.0.write(buf)?;
+        if let Some(cast) = CAST.lock().unwrap().as_mut() {
+            cast.frame.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        match CAST.lock().unwrap().as_mut() {
+            Some(cast) if !cast.frame.is_empty() => {
+                let t = cast.start.elapsed().as_secs_f64();
+                let s = escape(&String::from_utf8_lossy(&cast.frame));
+                cast.frame.clear();
+                writeln!(cast.file, "[{:.6}, \"o\", \"{}\"]", t, s)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// draw without colour from now on
+pub fn plain() {
+    COLORS.store(NONE, Ordering::Relaxed);
+}
+
+// ... or with the 16 basic colours only, for terminals that have no more
+pub fn basic() {
+    COLORS.fetch_max(BASIC, Ordering::Relaxed);
+}
+
+// buf with the colours of its SGR sequences taken out, or brought down to
+// the basic ones - the bold, dim and the rest kept - holding on to a
+// sequence cut off at the end until the next
+fn recolor(buf: &[u8], colors: u8) -> Vec<u8> {
+    let mut esc = ESCAPE.lock().unwrap();
+    let mut out = Vec::with_capacity(buf.len());
+    for &b in buf {
+        if esc.is_empty() && b != 0x1b {
+            out.push(b);
+            continue;
+        }
+        esc.push(b);
+        match esc[..] {
+            [0x1b] | [0x1b, b'['] => (),
+            [0x1b, _] => out.append(&mut esc), // not a CSI sequence
+            [.., b'm'] => out.extend(sgr(&std::mem::take(&mut *esc), colors)),
+            [.., f] if (0x40..=0x7e).contains(&f) => out.append(&mut esc),
+            _ => (),
+        }
+    }
+    out
+}
+
+// SGR sequence seq with its colours as colors has them, nothing if that leaves none of it
+fn sgr(seq: &[u8], colors: u8) -> Vec<u8> {
+    let params = String::from_utf8_lossy(&seq[2..seq.len() - 1]).into_owned();
+    if params.is_empty() {
+        return seq.to_vec(); // a reset
+    }
+    let mut kept = Vec::new();
+    let mut it = params.split(';');
+    while let Some(p) = it.next() {
+        let n = p.parse::<u8>().unwrap_or(0);
+        match n {
+            38 | 48 | 58 => {
+                let rgb = match it.next() {
+                    Some("5") => it
+                        .next()
+                        .and_then(|v| v.parse().ok())
+                        .map(|v| rgb(Color::AnsiValue(v))),
+                    Some("2") => {
+                        let v: Vec<u8> =
+                            it.by_ref().take(3).filter_map(|v| v.parse().ok()).collect();
+                        v.try_into().ok()
+                    }
+                    _ => None,
+                };
+                if let (BASIC, Some(rgb), 38 | 48) = (colors, rgb, n) {
+                    let (i, base) = (palette::basic(rgb), if n == 38 { 30 } else { 40 });
+                    kept.push(match i {
+                        0..=7 => (base + i).to_string(),
+                        _ => (base + 60 + i - 8).to_string(),
+                    });
+                }
+            }
+            30..=37 | 39..=47 | 49 | 59 | 90..=97 | 100..=107 if colors == NONE => (),
+            _ => kept.push(p.to_string()),
+        }
+    }
+    if kept.is_empty() {
+        return Vec::new();
+    }
+    format!("\x1b[{}m", kept.join(";")).into_bytes()
+}
+
+// start recording to path, for a terminal of the given size
+pub fn start(path: &Path, (width, height): (u16, u16)) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    writeln!(
+        file,
+        "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}}}",
+        width, height, timestamp
+    )?;
+    *CAST.lock().unwrap() = Some(Cast {
+        file,
+        start: Instant::now(),
+        frame: Vec::new(),
+    });
+    Ok(())
+}
+
+// the terminal changed size
+pub fn resize((width, height): (u16, u16)) -> io::Result<()> {
+    match CAST.lock().unwrap().as_mut() {
+        Some(cast) => {
+            let t = cast.start.elapsed().as_secs_f64();
+            writeln!(cast.file, "[{:.6}, \"r\", \"{}x{}\"]", t, width, height)
+        }
+        None => Ok(()),
+    }
+}
+
+// stop recording, writing out what is left
+pub fn finish() -> io::Result<()> {
+    match CAST.lock().unwrap().take() {
+        Some(mut cast) => cast.file.flush(),
+        None => Ok(()),
+    }
+}
+
+// s as the inside of a JSON string
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
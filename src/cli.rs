@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+use tetrisct::ai::{Difficulty, Style};
+use tetrisct::board::MAX_WIDTH;
+use tetrisct::config::{Config, Handicap, MAX_HEIGHT, MAX_LEVEL, MAX_PREVIEWS, MIN_SIZE, SPEEDS};
+use tetrisct::rules::Ruleset;
+use tetrisct::versus::{Target, MAX_OPPONENTS};
+
+pub const USAGE: &str = "\
+usage: tetrisct [options]
+       tetrisct share R | fetch URL | analyze R [options]
+                    the same as --share R, --fetch URL and --analyze R
+
+options:
+  --mode M          what to play for: marathon, survival, rising, sprint or ultra (points in
+                    two minutes, earned in the campaign)
+  --level N         starting level (1-15)
+  --previews N      number of next pieces shown (0-6)
+  --width N         board width in cells (4-16)
+  --height N        board height in cells (4-40)
+  --randomizer R    how pieces are dealt: random, 7-bag, 14-bag, tgm or nes
+  --lock-reset L    what puts off locking a landed piece: step, move or infinite
+  --lock-delay MS   time a landed piece waits before locking (0-5000)
+  --spawn-delay MS  entry delay (ARE) between locking and the next piece (0-5000)
+  --clear-delay MS  time full rows stay up before they are cleared (0-5000)
+  --curve C         gravity and the delays by level: unictris (the game's own, with the delays
+                    above), nes, tgm or guideline
+  --scoring S       what clears are worth: unictris (by how far down the rows were) or nes
+                    (40/100/300/1200 times the level, with points for dropping)
+  --rules R         a ruleset, setting the options it takes in one: modern (the defaults) or
+                    classic (the NES game's speed curve, scoring, randomizer and colours)
+  --speed X         run the game X times as fast (0.5-3)
+  --versus D        play against the computer: easy, normal or hard
+  --style S         ... playing balanced, attacker (building up for tetrises) or safe
+                    (keeping its stack down); both can be changed before the match
+  --opponents N     ... against N of them at once (1-3), their boards drawn small
+  --target T        ... garbage going to one of them at random, the leader (highest score),
+                    all of them or whoever has the most knockouts (badges)
+  --teams           ... 2v2: you and an ally against two of them, the garbage each team has
+                    coming countered by either's clears and the team with anyone left winning
+  --handicap H      even up a versus match: your gravity and garbage sent scaled, and your
+                    previews, as gravity=1.5,garbage=0.5,previews=1 (any of them)
+  --cpu-handicap H  ... and the computer's
+  --background B    behind empty cells: plain, gradient, stars or tint
+  --ghost G         the ghost piece: dim, outline, glyph or off
+  --hint            assist: outline where the computer would put the falling piece
+  --color C         auto (colour unless NO_COLOR is set or the output isn't a terminal),
+                    always or never
+  --legacy          draw for an old console: ASCII and the 16 basic colours (on Windows this
+                    is picked by itself in the old console, cmd.exe's)
+  --stack S         colour the locked blocks by piece, gradient (height), rainbow or level
+                    (every piece in the level's colours, as on the NES)
+  --theme T         the blocks: glyphs, emoji, winter, halloween, seasonal (by the date),
+                    mono (no colour), ascii, or neon and pastel, earned in the campaign
+  --smooth          draw the falling piece between rows as it goes, in half blocks
+  --reduced-motion  no animations: the piece doesn't slide sideways or fall smoothly, no
+                    sparks fly from cleared rows, the screen doesn't shake
+  --cascade         after a clear, loose blocks fall and can clear more rows
+  --fever           quick clears fill a gauge that sets off a spell of double points
+  --items           party play: garbage can carry items (with --versus)
+  --board-file F    start each game from the stack in F (rows of . and piece letters) and
+                    the pieces on its queue line
+  --edit F          paint a puzzle - a stack and the pieces to play on it - and save it to F,
+                    for --board-file
+  --fumen S         start each game from fumen S (v115@...): its stack and pieces
+  --puzzles         play puzzles from the packs that come with the game
+  --pack F          ... and from pack F, too
+  --campaign        show the campaign's challenges, those met and the modes and themes
+                    they unlock
+  --record F        save a replay of each game to F, overwriting the last one
+  --play R          watch replay R
+  --share R         upload replay R to the share endpoint in the config and print its address
+  --fetch URL       watch the replay at URL, as --share printed it
+  --tas R F         write replay R to F in the bare seed and tick:move form
+  --to-fumen R      print replay R as a fumen, a page for every piece placed
+  --analyze R       go over replay R piece by piece: misdrops, finesse faults, the pace
+                    and the worst placements
+  --export R F      turn replay R into an animation F, a .gif or .png (APNG)
+  --step            frame advance: the game moves on one tick per press of .
+  --broadcast A     serve the game state as JSON over WebSocket on A (host:port, or :port)
+  --serve A         answer GET /state and /stats with the game as JSON on A (host:port, or
+                    :port; needs the http feature)
+  --commands A      take moves voted for by an audience, a line each, on A (host:port,
+                    :port or a UNIX socket's path)
+  --record-cast F   record the session to F, an asciinema (asciicast v2) file
+  --trace F         write the game loop's timed spans to F as they close (needs the trace
+                    feature)
+  --profile NAME    play as NAME, with settings, keys, bests and campaign of their own
+                    (a new name starts a new profile); asked at startup if there are any
+  --export-profile F
+                    write the profile - settings, bests, campaign, replays kept there - to F
+  --import-profile F
+                    ... and unpack F over the profile played, to carry it to another machine
+  -h, --help        show this message
+
+Settings are also read from config.toml in the config directory
+(~/.config/unictris on most systems); options given here win.";
+
+fn value<T: std::str::FromStr>(opt: &str, v: Option<String>) -> Result<T, String> {
+    let v = v.ok_or_else(|| format!("{} needs a value", opt))?;
+    v.parse().map_err(|_| format!("{}: bad value '{}'", opt, v))
+}
+
+// what to play, as opposed to how - the settings go in the config
+#[derive(Default)]
+pub struct Options {
+    pub versus: Option<Difficulty>,
+    pub style: Option<Style>,     // ... and how it plays
+    pub opponents: Option<usize>, // ... and how many there are
+    pub target: Option<Target>,   // ... and where garbage goes among them
+    pub teams: bool,              // ... or 2v2
+    pub record_cast: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub export: Option<(PathBuf, PathBuf)>, // replay, animation
+    pub tas: Option<(PathBuf, PathBuf)>,    // replay, bare replay
+    pub play: Option<PathBuf>,
+    pub share: Option<PathBuf>,
+    pub fetch: Option<String>,  // a shared replay's address, to watch it
+    pub board: Option<PathBuf>, // the starting stack
+    pub fumen: Option<String>,  // ... and pieces, as a fumen
+    pub edit: Option<PathBuf>,  // a puzzle to paint instead of playing
+    pub puzzles: bool,          // pick puzzles from packs to play
+    pub packs: Vec<PathBuf>,    // ... those besides the bundled ones
+    pub campaign: bool,
+    pub profile: Option<String>,
+    pub export_profile: Option<PathBuf>,
+    pub import_profile: Option<PathBuf>,
+    pub to_fumen: Option<PathBuf>,
+    pub analyze: Option<PathBuf>,
+    pub broadcast: Option<String>, // address overlays connect to
+    pub commands: Option<String>,  // ... and a chat bot sends votes to
+    pub serve: Option<String>,     // ... and dashboards poll
+    pub step: bool,                // frame advance, with the state shown
+    pub trace: Option<PathBuf>,    // where the loop's spans are written
+}
+
+impl Options {
+    // is a game to be played, rather than a replay looked at or the campaign shown?
+    pub fn plays(&self) -> bool {
+        self.export.is_none()
+            && self.tas.is_none()
+            && self.play.is_none()
+            && self.share.is_none()
+            && self.fetch.is_none()
+            && self.to_fumen.is_none()
+            && self.analyze.is_none()
+            && self.edit.is_none()
+            && !self.campaign
+            && self.export_profile.is_none()
+            && self.import_profile.is_none()
+    }
+}
+
+const SUBCOMMANDS: [&str; 3] = ["share", "fetch", "analyze"];
+
+// apply command line options on top of cfg; Ok(None) means just show the usage
+pub fn parse(
+    args: impl Iterator<Item = String>,
+    cfg: &mut Config,
+) -> Result<Option<Options>, String> {
+    let mut opts = Options::default();
+    // a subcommand first, "share R", is its option without the dashes
+    let mut args = args.peekable();
+    let first = args.next_if(|a| SUBCOMMANDS.contains(&a.as_str()));
+    let mut args = first.map(|a| format!("--{}", a)).into_iter().chain(args);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => cfg.mode = value(&arg, args.next())?,
+            "--level" => {
+                cfg.level = value(&arg, args.next())?;
+                if !(1..=MAX_LEVEL).contains(&cfg.level) {
+                    return Err(format!("--level: 1 to {}", MAX_LEVEL));
+                }
+            }
+            "--previews" => {
+                cfg.previews = value(&arg, args.next())?;
+                if cfg.previews > MAX_PREVIEWS {
+                    return Err(format!("--previews: at most {}", MAX_PREVIEWS));
+                }
+            }
+            "--width" => {
+                cfg.width = value(&arg, args.next())?;
+                if !(MIN_SIZE..=MAX_WIDTH).contains(&cfg.width) {
+                    return Err(format!("--width: {} to {}", MIN_SIZE, MAX_WIDTH));
+                }
+            }
+            "--height" => {
+                cfg.height = value(&arg, args.next())?;
+                if !(MIN_SIZE..=MAX_HEIGHT).contains(&cfg.height) {
+                    return Err(format!("--height: {} to {}", MIN_SIZE, MAX_HEIGHT));
+                }
+            }
+            "--randomizer" => cfg.randomizer = value(&arg, args.next())?,
+            "--lock-reset" => cfg.lock_reset = value(&arg, args.next())?,
+            "--lock-delay" => cfg.lock_delay = value(&arg, args.next())?,
+            "--spawn-delay" => cfg.spawn_delay = value(&arg, args.next())?,
+            "--clear-delay" => cfg.clear_delay = value(&arg, args.next())?,
+            "--curve" => cfg.curve = value(&arg, args.next())?,
+            "--scoring" => cfg.scoring = value(&arg, args.next())?,
+            "--rules" => value::<Ruleset>(&arg, args.next())?.apply(cfg),
+            "--speed" => {
+                cfg.speed = value(&arg, args.next())?;
+                if !(SPEEDS.0..=SPEEDS.1).contains(&cfg.speed) {
+                    return Err(format!("--speed: {} to {}", SPEEDS.0, SPEEDS.1));
+                }
+            }
+            "--background" => cfg.background = value(&arg, args.next())?,
+            "--ghost" => cfg.ghost = value(&arg, args.next())?,
+            "--hint" => cfg.hint = true,
+            "--color" => cfg.color = value(&arg, args.next())?,
+            "--legacy" => cfg.legacy = true,
+            "--stack" => cfg.stack = value(&arg, args.next())?,
+            "--theme" => cfg.theme = value(&arg, args.next())?,
+            "--smooth" => cfg.smooth = true,
+            "--reduced-motion" => cfg.reduced_motion = true,
+            "--cascade" => cfg.cascade = true,
+            "--items" => cfg.items = true,
+            "--fever" => cfg.fever = true,
+            "--versus" => opts.versus = Some(value(&arg, args.next())?),
+            "--style" => opts.style = Some(value(&arg, args.next())?),
+            "--opponents" => {
+                let n = value(&arg, args.next())?;
+                if !(1..=MAX_OPPONENTS).contains(&n) {
+                    return Err(format!("--opponents: 1 to {}", MAX_OPPONENTS));
+                }
+                opts.opponents = Some(n);
+            }
+            "--target" => opts.target = Some(value(&arg, args.next())?),
+            "--teams" => opts.teams = true,
+            "--handicap" | "--cpu-handicap" => {
+                let h: Handicap = value(&arg, args.next())?;
+                h.check().map_err(|e| format!("{} {}", arg, e))?;
+                match arg.as_str() {
+                    "--handicap" => cfg.handicap = h,
+                    _ => cfg.opponent = h,
+                }
+            }
+            "--record" => opts.record = Some(value(&arg, args.next())?),
+            "--export" => {
+                let replay = value(&arg, args.next())?;
+                opts.export = Some((replay, value(&arg, args.next())?));
+            }
+            "--tas" => {
+                let replay = value(&arg, args.next())?;
+                opts.tas = Some((replay, value(&arg, args.next())?));
+            }
+            "--board-file" => opts.board = Some(value(&arg, args.next())?),
+            "--puzzles" => opts.puzzles = true,
+            "--campaign" => opts.campaign = true,
+            "--profile" => opts.profile = Some(value(&arg, args.next())?),
+            "--export-profile" => opts.export_profile = Some(value(&arg, args.next())?),
+            "--import-profile" => opts.import_profile = Some(value(&arg, args.next())?),
+            "--pack" => {
+                opts.packs.push(value(&arg, args.next())?);
+                opts.puzzles = true;
+            }
+            "--edit" => opts.edit = Some(value(&arg, args.next())?),
+            "--fumen" => opts.fumen = Some(value(&arg, args.next())?),
+            "--to-fumen" => opts.to_fumen = Some(value(&arg, args.next())?),
+            "--analyze" => opts.analyze = Some(value(&arg, args.next())?),
+            "--play" => opts.play = Some(value(&arg, args.next())?),
+            "--share" => opts.share = Some(value(&arg, args.next())?),
+            "--fetch" => opts.fetch = Some(value(&arg, args.next())?),
+            "--step" => opts.step = true,
+            "--broadcast" => opts.broadcast = Some(value(&arg, args.next())?),
+            "--serve" => opts.serve = Some(value(&arg, args.next())?),
+            "--commands" => opts.commands = Some(value(&arg, args.next())?),
+            "--record-cast" => opts.record_cast = Some(value(&arg, args.next())?),
+            "--trace" => opts.trace = Some(value(&arg, args.next())?),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+    if opts.play.is_some() && opts.fetch.is_some() {
+        return Err("--play and --fetch both give the replay to watch".to_string());
+    }
+    if opts.style.is_some() && opts.versus.is_none() {
+        return Err("--style: the computer's, with --versus".to_string());
+    }
+    if (opts.opponents.is_some() || opts.target.is_some() || opts.teams) && opts.versus.is_none() {
+        return Err("--opponents, --target and --teams: with --versus".to_string());
+    }
+    if opts.teams && opts.opponents.is_some_and(|n| n != 3) {
+        return Err("--teams: 2v2, three opponents".to_string());
+    }
+    if opts.puzzles && opts.versus.is_some() {
+        return Err("--puzzles: puzzles are single-player".to_string());
+    }
+    if opts.board.is_some() && opts.fumen.is_some() {
+        return Err("--board-file and --fumen both give the starting stack".to_string());
+    }
+    Ok(Some(opts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        let args = args.iter().map(|a| a.to_string());
+        parse(args, &mut Config::default()).map(|o| o.unwrap())
+    }
+
+    #[test]
+    fn a_subcommand_is_its_option() {
+        let o = options(&["share", "a.replay"]).unwrap();
+        assert_eq!(o.share, Some(PathBuf::from("a.replay")));
+        let o = options(&["analyze", "a.replay", "--width", "8"]).unwrap();
+        assert_eq!(o.analyze, Some(PathBuf::from("a.replay")));
+        let o = options(&["fetch", "https://0x0.st/abc"]).unwrap();
+        assert_eq!(o.fetch.as_deref(), Some("https://0x0.st/abc"));
+        assert!(options(&["--share", "a.replay"]).unwrap().share.is_some());
+    }
+
+    #[test]
+    fn only_first_is_a_subcommand() {
+        assert!(options(&["--level", "3", "share", "a.replay"]).is_err());
+        assert!(options(&["share"]).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+// Copying text out of the game: to the terminal's clipboard with an OSC 52
+// escape, which most terminals take (some only once allowed to), and kept
+// to be printed on the way out for those that don't. The escape goes
+// straight to the terminal, past any --record-cast recording.
+
+static LAST: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn copy(text: &str) -> io::Result<()> {
+    *LAST.lock().unwrap() = Some(text.to_string());
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+// the last text copied, if any
+pub fn last() -> Option<String> {
+    LAST.lock().unwrap().clone()
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => s.push(DIGITS[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => s.push('='),
+            }
+        }
+    }
+    s
+}
@@ -0,0 +1,114 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+// Time as the game loop sees it. The game itself counts ticks; a clock says
+// how much real time has gone by, so the loop knows how many ticks are due.
+// Wall is the real thing, Virtual moves on only when told to - for tests and
+// headless runs that should not depend on how fast the machine is.
+
+pub trait Clock {
+    // time since the clock started
+    fn now(&self) -> Duration;
+}
+
+pub struct Wall(Instant);
+
+impl Default for Wall {
+    fn default() -> Self {
+        Wall(Instant::now())
+    }
+}
+
+impl Clock for Wall {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+#[derive(Default)]
+pub struct Virtual(Cell<Duration>);
+
+impl Virtual {
+    pub fn advance(&self, d: Duration) {
+        self.0.set(self.0.get() + d);
+    }
+}
+
+impl Clock for Virtual {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+// longest the loop catches up on after being stopped or starved
+pub const MAX_LAG: Duration = Duration::from_millis(250);
+
+// A fixed timestep over a clock: the ticks due by now, each tick long.
+pub struct Ticker<'a> {
+    clock: &'a dyn Clock,
+    next: Duration, // when the next tick is due
+}
+
+impl<'a> Ticker<'a> {
+    pub fn new(clock: &'a dyn Clock) -> Ticker<'a> {
+        Ticker {
+            clock,
+            next: clock.now(),
+        }
+    }
+
+    // ticks due since the last call; after a stall of more than MAX_LAG,
+    // starts over from now rather than trying to catch up
+    pub fn due(&mut self, tick: Duration) -> u32 {
+        let now = self.clock.now();
+        if now > self.next + MAX_LAG {
+            self.next = now;
+        }
+        let mut n = 0;
+        while self.next <= now {
+            self.next += tick;
+            n += 1;
+        }
+        n
+    }
+
+    // time until the next tick is due
+    pub fn wait(&self) -> Duration {
+        self.next.saturating_sub(self.clock.now())
+    }
+
+    // ... and until n ticks are, each tick long
+    pub fn wait_for(&self, n: u32, tick: Duration) -> Duration {
+        (self.next + tick * n.saturating_sub(1)).saturating_sub(self.clock.now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn ticks_come_due_as_virtual_time_goes_by() {
+        let clock = Virtual::default();
+        let mut t = Ticker::new(&clock);
+        assert_eq!(t.due(TICK), 1); // the first, straight away
+        assert_eq!(t.due(TICK), 0);
+        clock.advance(Duration::from_millis(25));
+        assert_eq!(t.due(TICK), 2);
+        assert_eq!(t.wait(), Duration::from_millis(5));
+        assert_eq!(t.wait_for(3, TICK), Duration::from_millis(25));
+    }
+
+    #[test]
+    fn a_stall_starts_over_rather_than_catching_up() {
+        let clock = Virtual::default();
+        let mut t = Ticker::new(&clock);
+        t.due(TICK);
+        clock.advance(MAX_LAG * 4);
+        assert_eq!(t.due(TICK), 1);
+        clock.advance(MAX_LAG / 2);
+        assert_eq!(t.due(TICK), 12);
+    }
+}
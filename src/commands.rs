@@ -0,0 +1,157 @@
+use crate::events::{self, Wake};
+use smol::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use smol::net::TcpListener;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Instant;
+use tetrisct::game::Move;
+
+// Moves from outside, for an audience to play through a chat bot: --commands
+// listens on a TCP port or a UNIX socket, and each line a client sends is a
+// vote - a move as named in replays, "left" or "hard_drop". A connection is
+// a voter, whatever it says it is, so a bot passing on its viewers opens
+// one for each; and a connection sending faster than BURST lines and then
+// RATE a second has the rest dropped unread, before they reach the game.
+// The crowd decides what is made of the votes. The sockets are tasks on the
+// event hub's executor.
+
+const MAX_LINE: u64 = 256; // longest line taken, in bytes
+const BURST: f64 = 4.0; // lines a connection can send at once
+const RATE: f64 = 4.0; // ... and a second after that
+
+// a connection's allowance of lines: BURST to start with, topped up by
+// RATE a second up to BURST again, each line taking one
+struct Bucket {
+    tokens: f64,
+    at: Instant, // when it was last topped up
+}
+
+impl Bucket {
+    fn new(now: Instant) -> Bucket {
+        Bucket {
+            tokens: BURST,
+            at: now,
+        }
+    }
+
+    // is there a line's worth left at now? - taking it if so
+    fn take(&mut self, now: Instant) -> bool {
+        let secs = now.saturating_duration_since(self.at).as_secs_f64();
+        self.tokens = (self.tokens + secs * RATE).min(BURST);
+        self.at = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+// listen on addr - "host:port", ":port" for this machine only, or the path of a UNIX socket
+pub fn start(addr: &str) -> io::Result<Receiver<(String, Move)>> {
+    let (tx, rx) = mpsc::channel();
+    let err = |e: io::Error| io::Error::new(e.kind(), format!("--commands {}: {}", addr, e));
+    if addr.contains('/') {
+        listen_unix(addr, tx).map_err(err)?;
+    } else {
+        let addr = match addr.strip_prefix(':') {
+            Some(port) => format!("127.0.0.1:{}", port),
+            None => addr.to_string(),
+        };
+        let listener = smol::block_on(TcpListener::bind(&addr)).map_err(err)?;
+        events::spawn(async move {
+            for n in 0.. {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                events::spawn(serve(stream, format!("tcp{}", n), tx.clone()));
+            }
+        });
+    }
+    Ok(rx)
+}
+
+#[cfg(unix)]
+fn listen_unix(path: &str, tx: Sender<(String, Move)>) -> io::Result<()> {
+    use smol::net::unix::UnixListener;
+    std::fs::remove_file(path).ok(); // left over from the last time
+    let listener = UnixListener::bind(path)?;
+    events::spawn(async move {
+        for n in 0.. {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            events::spawn(serve(stream, format!("unix{}", n), tx.clone()));
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_: &str, _: Sender<(String, Move)>) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "UNIX sockets are for UNIX systems",
+    ))
+}
+
+// a client's votes until it hangs up, answering "?" to lines that aren't
+// one; each vote within its allowance wakes the game loop, for the crowd
+// to count it
+async fn serve<S>(stream: S, client: String, tx: Sender<(String, Move)>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut bucket = Bucket::new(Instant::now());
+    loop {
+        line.clear();
+        match (&mut reader).take(MAX_LINE).read_line(&mut line).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) => (),
+        }
+        if !bucket.take(Instant::now()) {
+            continue; // too fast
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let vote = match words[..] {
+            [m] => m.parse().ok().map(|m| (client.clone(), m)),
+            _ => None,
+        };
+        let sent = match vote {
+            Some(v) => {
+                let sent = tx.send(v).is_ok();
+                events::wake(Wake::Vote);
+                sent
+            }
+            None if words.is_empty() => true,
+            None => reader.get_mut().write_all(b"?\n").await.is_ok(),
+        };
+        if !sent {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn a_connection_gets_a_burst_and_then_its_rate() {
+        let t = Instant::now();
+        let mut b = Bucket::new(t);
+        let taken = (0..10).filter(|_| b.take(t)).count();
+        assert_eq!(taken, BURST as usize);
+        let later = t + Duration::from_secs_f64(1.0 / RATE);
+        assert!(b.take(later));
+        assert!(!b.take(later));
+        let much_later = t + Duration::from_secs(60); // topped up no higher than BURST
+        assert_eq!(
+            (0..10).filter(|_| b.take(much_later)).count(),
+            BURST as usize
+        );
+    }
+}
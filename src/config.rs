@@ -0,0 +1,331 @@
+use crate::board::MAX_WIDTH;
+use crate::error::{Error, Result};
+use crate::game::{LockReset, TICK};
+use crate::hud::{self, Side, Widget};
+use crate::keymap::Keymap;
+use crate::mode::Mode;
+use crate::palette::{self, Background, ColorChoice, Ghost, Skin, Stack, Theme};
+use crate::pieces::{self, RawColor};
+use crate::randomizer;
+use crate::rules::Scoring;
+use crate::speed::Curve;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+pub const MAX_PREVIEWS: usize = 6;
+pub const MIN_SIZE: u8 = 4; // smallest board, either way
+pub const MAX_HEIGHT: u8 = 40;
+pub const MAX_HUD: usize = 12; // sidebar rows
+pub const MAX_LEVEL: u64 = 15; // highest starting level
+pub const MAX_DELAY: u32 = 5000; // longest the lock, spawn and clear delays go, in ms
+pub const SPEEDS: (f64, f64) = (0.5, 3.0); // slowest and fastest the game runs
+pub const GRAVITIES: (f64, f64) = (0.25, 4.0); // ... and the handicaps' gravity goes
+pub const MAX_GARBAGE: f64 = 4.0; // most a handicap multiplies the garbage sent by
+
+// board sizes offered on the title screen
+pub const PRESETS: [(&str, u8, u8); 4] = [
+    ("Classic", 10, 20),
+    ("Tall", 10, 24),
+    ("Wide", 12, 20),
+    ("Tiny", 6, 12),
+];
+
+// Player settings, read from config.toml in the config directory.
+// Every field is optional in the file; missing ones keep their defaults.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub mode: Mode,
+    pub level: u64, // starting level, 1-15: gravity starts there, and points are multiplied by it
+    pub previews: usize, // next pieces shown, 0-6
+    pub width: u8,  // board size in cells
+    pub height: u8,
+    pub randomizer: randomizer::Kind,
+    pub lock_reset: LockReset,
+    pub lock_delay: u32,        // ms a landed piece waits before locking
+    pub spawn_delay: u32,       // ms from locking to the next piece (ARE)
+    pub clear_delay: u32,       // ms full rows stay up before they are cleared
+    pub curve: Curve,           // gravity and the delays by level (the game's own: those above)
+    pub scoring: Scoring,       // what clears are worth
+    pub speed: f64,             // how fast the whole game runs, 1 being normal
+    pub items: bool,            // party play: garbage can carry items
+    pub cascade: bool,          // after a clear, loose blocks fall and can clear more rows
+    pub fever: bool,            // arcade: quick clears fill a gauge for a spell of double points
+    pub background: Background, // behind the empty cells
+    pub ghost: Ghost,           // how the ghost piece is drawn
+    pub hint: bool,             // the bot's best place for the falling piece outlined, for learners
+    pub theme: Theme,           // ... and the blocks
+    pub smooth: bool,           // the falling piece drawn between rows, not a row at a time
+    pub reduced_motion: bool,   // none of the animations: sliding, smooth falling, sparks, shaking
+    pub stack: Stack,           // ... and what colours the locked ones
+    pub color: ColorChoice,     // colour or the mono theme
+    pub legacy: bool,           // the ASCII theme and basic colours, as for old Windows consoles
+    pub hud: Vec<Widget>,       // sidebar lines, top to bottom
+    pub swap_preview: bool,     // the hold line shows what a hold would leave, not just what's held
+    pub sidebar: Side,          // where the sidebar goes
+    pub keys: BTreeMap<String, Vec<String>>, // action -> keys, replacing its default keys
+    pub glyphs: BTreeMap<String, Glyph>, // how a piece's cells, or garbage, ..., look, over the theme
+    pub share: String,                   // where --share uploads replays to
+    pub handicap: Handicap,              // yours, to even up a versus match
+    pub opponent: Handicap,              // ... and the bot's
+}
+
+// What evens up a match between players who aren't: the gravity and the
+// garbage sent scaled, and the previews shown set, for one side alone.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Handicap {
+    pub gravity: f64,            // times the level's
+    pub garbage: f64,            // times the lines cleared would send
+    pub previews: Option<usize>, // next pieces shown, instead of the setting
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            gravity: 1.0,
+            garbage: 1.0,
+            previews: None,
+        }
+    }
+}
+
+impl Handicap {
+    pub fn check(&self) -> std::result::Result<(), String> {
+        if !(GRAVITIES.0..=GRAVITIES.1).contains(&self.gravity) {
+            return Err(format!("gravity: {} to {}", GRAVITIES.0, GRAVITIES.1));
+        }
+        if !(0.0..=MAX_GARBAGE).contains(&self.garbage) {
+            return Err(format!("garbage: 0 to {}", MAX_GARBAGE));
+        }
+        if self.previews.is_some_and(|n| n > MAX_PREVIEWS) {
+            return Err(format!("previews: at most {}", MAX_PREVIEWS));
+        }
+        Ok(())
+    }
+}
+
+// "gravity=1.5,garbage=0.5,previews=1", any of them left out
+impl std::str::FromStr for Handicap {
+    type Err = ();
+    fn from_str(s: &str) -> std::result::Result<Handicap, ()> {
+        let mut h = Handicap::default();
+        for part in s.split(',') {
+            match part.split_once('=').ok_or(())? {
+                ("gravity", v) => h.gravity = v.parse().map_err(|_| ())?,
+                ("garbage", v) => h.garbage = v.parse().map_err(|_| ())?,
+                ("previews", v) => h.previews = Some(v.parse().map_err(|_| ())?),
+                _ => return Err(()),
+            }
+        }
+        Ok(h)
+    }
+}
+
+// what to change of how a kind of cell is drawn - each part left out is the theme's
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Glyph {
+    pub text: Option<String>, // two columns of it
+    pub fg: Option<RawColor>,
+    pub bg: Option<RawColor>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::Marathon,
+            level: 1,
+            previews: 3,
+            width: 10,
+            height: 20,
+            randomizer: randomizer::Kind::Random,
+            lock_reset: LockReset::Move,
+            lock_delay: 500,
+            spawn_delay: 0,
+            clear_delay: 0,
+            curve: Curve::Unictris,
+            scoring: Scoring::Unictris,
+            speed: 1.0,
+            items: false,
+            cascade: false,
+            fever: false,
+            background: Background::Plain,
+            ghost: Ghost::Dim,
+            hint: false,
+            theme: Theme::Glyphs,
+            stack: Stack::Piece,
+            color: ColorChoice::Auto,
+            legacy: false,
+            smooth: false,
+            reduced_motion: false,
+            hud: hud::DEFAULT.to_vec(),
+            swap_preview: false,
+            sidebar: Side::Right,
+            keys: BTreeMap::new(),
+            glyphs: BTreeMap::new(),
+            share: "https://0x0.st".to_string(),
+            handicap: Handicap::default(),
+            opponent: Handicap::default(),
+        }
+    }
+}
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+// $XDG_CONFIG_HOME/unictris, falling back on ~/.config/unictris (%APPDATA% on Windows)
+fn base() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("unictris"))
+}
+
+// where the settings and records are kept: the config directory, or the
+// profile's own in profiles/ under it when playing as one
+pub fn dir() -> Option<PathBuf> {
+    let base = base()?;
+    Some(match PROFILE.get() {
+        Some(name) => base.join("profiles").join(name),
+        None => base,
+    })
+}
+
+// play as profile name from here on - each player with settings, keys,
+// bests and campaign of their own; a new name starts a new profile
+pub fn set_profile(name: &str) -> Result<()> {
+    let ok = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(ok) {
+        let msg = format!("profile '{}': letters, digits, - and _ only", name);
+        return Err(Error::Config(msg));
+    }
+    PROFILE.get_or_init(|| name.to_string());
+    if let Some(dir) = dir() {
+        std::fs::create_dir_all(dir)?; // for it to be offered next time
+    }
+    Ok(())
+}
+
+// the profiles there are, by name
+pub fn profiles() -> Vec<String> {
+    let Some(dirs) = base().and_then(|b| std::fs::read_dir(b.join("profiles")).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = (dirs.flatten())
+        .filter(|d| d.path().is_dir())
+        .filter_map(|d| d.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+impl Config {
+    pub fn parse(src: &str) -> Result<Config> {
+        let cfg: Config = toml::from_str(src).map_err(|e| Error::Config(e.to_string()))?;
+        cfg.validate()
+    }
+
+    pub fn validate(self) -> Result<Config> {
+        if !(1..=MAX_LEVEL).contains(&self.level) {
+            let msg = format!("level: 1 to {}", MAX_LEVEL);
+            return Err(Error::Config(msg));
+        }
+        if self.previews > MAX_PREVIEWS {
+            let msg = format!("previews: at most {}", MAX_PREVIEWS);
+            return Err(Error::Config(msg));
+        }
+        if !(MIN_SIZE..=MAX_WIDTH).contains(&self.width) {
+            let msg = format!("width: {} to {}", MIN_SIZE, MAX_WIDTH);
+            return Err(Error::Config(msg));
+        }
+        if !(MIN_SIZE..=MAX_HEIGHT).contains(&self.height) {
+            let msg = format!("height: {} to {}", MIN_SIZE, MAX_HEIGHT);
+            return Err(Error::Config(msg));
+        }
+        if !(SPEEDS.0..=SPEEDS.1).contains(&self.speed) {
+            let msg = format!("speed: {} to {}", SPEEDS.0, SPEEDS.1);
+            return Err(Error::Config(msg));
+        }
+        for (name, ms) in [
+            ("lock_delay", self.lock_delay),
+            ("spawn_delay", self.spawn_delay),
+            ("clear_delay", self.clear_delay),
+        ] {
+            if ms > MAX_DELAY {
+                return Err(Error::Config(format!("{}: at most {} ms", name, MAX_DELAY)));
+            }
+        }
+        if self.hud.iter().map(|w| w.rows() as usize).sum::<usize>() > MAX_HUD {
+            let msg = format!("hud: at most {} rows of widgets", MAX_HUD);
+            return Err(Error::Config(msg));
+        }
+        for (name, h) in [("handicap", &self.handicap), ("opponent", &self.opponent)] {
+            h.check()
+                .map_err(|e| Error::Config(format!("{}.{}", name, e)))?;
+        }
+        self.keymap()?;
+        Skin::new(&self.glyphs).map_err(Error::Config)?;
+        if self.theme == Theme::Mono && pieces::get().pieces.len() > palette::MONO.len() {
+            let msg = format!(
+                "theme: mono has glyphs for {} pieces at most",
+                palette::MONO.len()
+            );
+            return Err(Error::Config(msg));
+        }
+        Ok(self)
+    }
+
+    // real time between ticks, at the configured speed
+    pub fn tick(&self) -> Duration {
+        TICK.div_f64(self.speed)
+    }
+
+    pub fn keymap(&self) -> Result<Keymap> {
+        Keymap::new(&self.keys).map_err(Error::Config)
+    }
+
+    // the config file if there is one, defaults otherwise
+    pub fn load() -> Result<Config> {
+        let Some(path) = dir().map(|d| d.join("config.toml")) else {
+            return Ok(Config::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(src) => {
+                Config::parse(&src).map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_delays_are_held_to_their_bounds() {
+        let ok = Config {
+            lock_delay: MAX_DELAY,
+            ..Config::default()
+        };
+        assert!(ok.validate().is_ok());
+        for cfg in [
+            Config {
+                lock_delay: MAX_DELAY + 1,
+                ..Config::default()
+            },
+            Config {
+                clear_delay: u32::MAX,
+                ..Config::default()
+            },
+        ] {
+            assert!(cfg.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::cast::stdout;
+use crate::events::{self, Wake};
+use crate::layout::{self, Layout};
+use crate::{blank, draw_frame, is_ctrl_c};
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use crossterm::style::{self, Color, Stylize};
+use crossterm::{cursor, Result};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tetrisct::board::GARBAGE;
+use tetrisct::config::Config;
+use tetrisct::fumen::Puzzle;
+use tetrisct::palette::{self, Skin};
+use tetrisct::{pieces, position};
+
+// The puzzle editor, --edit F: the board painted a cell at a time with a
+// brush - a piece's colour or garbage - and the pieces to play on it typed
+// in, then saved to F as a puzzle for --board-file. Rows can't be filled
+// right across, as they would clear before the first piece fell.
+
+const HELP: [&str; 5] = [
+    "[Arrows] Move  [Space] Paint",
+    "[IOTSZJL #] Brush  [Del] Erase",
+    "[Tab] Queue: letters, [Bksp]",
+    "[Enter] Save",
+    "[Esc] Leave",
+];
+
+#[derive(PartialEq)]
+enum Focus {
+    Board,
+    Queue,
+}
+
+struct Editor {
+    puzzle: Puzzle,
+    at: (u8, u8), // the cell under the cursor
+    brush: u8,    // what painting fills a cell with, tetromino+1 or GARBAGE
+    focus: Focus,
+    changed: bool, // since the last save
+    warned: bool,  // ... and leaving without saving is a key away
+    note: String,  // what the last key did, if it needs saying
+}
+
+// the piece a key names, tetromino+1, or garbage for '#'
+fn brush(c: char) -> Option<u8> {
+    match c {
+        '#' => Some(GARBAGE),
+        c => Some(position::queue(&c.to_string()).ok()?[0] + 1),
+    }
+}
+
+impl Editor {
+    // set the cell under the cursor to v, unless that would fill its row
+    fn paint(&mut self, v: u8) {
+        let (x, y) = self.at;
+        let b = &mut self.puzzle.board;
+        let old = b.get(x, y);
+        b.set(x, y, v);
+        if b.is_filled(y) {
+            b.set(x, y, old);
+            self.note = "A full row would clear at once".to_string();
+        } else if old != v {
+            self.changed = true;
+        }
+    }
+
+    // a key, with false when it is time to leave
+    fn key(&mut self, code: KeyCode, path: &Path) -> bool {
+        let (w, h) = (self.puzzle.board.width(), self.puzzle.board.height());
+        self.note.clear();
+        let warned = std::mem::take(&mut self.warned);
+        match (&self.focus, code) {
+            (_, KeyCode::Enter) => {
+                match std::fs::write(path, position::puzzle_text(&self.puzzle)) {
+                    Ok(()) => {
+                        self.changed = false;
+                        self.note = format!("Saved to {}", path.display());
+                    }
+                    Err(e) => self.note = e.to_string(),
+                }
+            }
+            (Focus::Board, KeyCode::Esc) if self.changed && !warned => {
+                self.warned = true;
+                self.note = "Not saved - Esc again to leave".to_string();
+            }
+            (Focus::Board, KeyCode::Esc) => return false,
+            (Focus::Board, KeyCode::Tab) => self.focus = Focus::Queue,
+            (Focus::Queue, KeyCode::Tab | KeyCode::Esc) => self.focus = Focus::Board,
+            (Focus::Board, KeyCode::Left) => self.at.0 = (self.at.0 + w - 1) % w,
+            (Focus::Board, KeyCode::Right) => self.at.0 = (self.at.0 + 1) % w,
+            (Focus::Board, KeyCode::Up) => self.at.1 = (self.at.1 + h - 1) % h,
+            (Focus::Board, KeyCode::Down) => self.at.1 = (self.at.1 + 1) % h,
+            (Focus::Board, KeyCode::Char(' ')) => {
+                let (x, y) = self.at;
+                match self.puzzle.board.get(x, y) == self.brush {
+                    true => self.paint(0),
+                    false => self.paint(self.brush),
+                }
+            }
+            (Focus::Board, KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('.')) => {
+                self.paint(0)
+            }
+            (Focus::Board, KeyCode::Char(c)) => match brush(c) {
+                Some(v) => self.brush = v,
+                None => self.note = format!("What is '{}'?", c),
+            },
+            (Focus::Queue, KeyCode::Backspace) => {
+                self.changed |= self.puzzle.queue.pop().is_some();
+            }
+            (Focus::Queue, KeyCode::Char(c)) => match brush(c) {
+                Some(v) if v != GARBAGE => {
+                    self.puzzle.queue.push(v - 1);
+                    self.changed = true;
+                }
+                _ => self.note = format!("What piece is '{}'?", c),
+            },
+            _ => (),
+        }
+        true
+    }
+
+    // the board, the cursor on it reversed, and the sidebar
+    fn draw(&self, cfg: &Config, lay: &Layout) -> Result<()> {
+        let mut stdout = stdout();
+        let b = &self.puzzle.board;
+        // the rows shown, around the cursor when they can't all be
+        let top = (self.at.1.saturating_sub(lay.rows / 2)).min(b.height() - lay.rows);
+        let skin = Skin::new(&cfg.glyphs).unwrap_or_default(); // checked with the config
+        for y in top..top + lay.rows {
+            crossterm::queue!(stdout, cursor::MoveTo(lay.x + 1, (y - top) as u16 + 1))?;
+            for x in 0..b.width() {
+                let v = b.get(x, y);
+                let s = match v {
+                    0 => palette::empty(cfg.background, x, y, b.height(), cfg.level),
+                    v => palette::block(cfg.theme, v),
+                };
+                let s = skin.dress(v, s);
+                let s = match (x, y) == self.at && self.focus == Focus::Board {
+                    true => s.reverse(),
+                    false => s,
+                };
+                crossterm::queue!(stdout, style::PrintStyledContent(s))?;
+            }
+        }
+
+        let n = lay.right - lay.left;
+        let queue = position::letters(&self.puzzle.queue);
+        let queue = match self.focus {
+            Focus::Queue => format!("Queue : {}_", queue).bold().yellow(),
+            Focus::Board => format!("Queue : {}", queue).bold().white(),
+        };
+        let mut row = lay.hud;
+        for s in [
+            "Puzzle editor".to_string().bold().cyan(),
+            "".to_string().white(),
+            match self.brush {
+                GARBAGE => "Brush : garbage".to_string(),
+                p => format!("Brush : {}", pieces::get().pieces[p as usize - 1].name),
+            }
+            .bold()
+            .white(),
+            queue,
+            "".to_string().white(),
+        ]
+        .into_iter()
+        .chain(HELP.iter().map(|s| s.to_string().white()))
+        {
+            blank(lay, row)?;
+            let s = style::StyledContent::new(*s.style(), layout::fit(s.content(), n));
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(lay.left, row),
+                style::PrintStyledContent(s)
+            )?;
+            row += 1;
+        }
+        blank(lay, row + 1)?;
+        let s = layout::fit(&self.note, n);
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(lay.left, row + 1),
+            style::PrintStyledContent(s.with(Color::Yellow))
+        )?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+// edit puzzle, saving it to path, until the player leaves
+pub fn edit(puzzle: Puzzle, path: &Path, cfg: &Config, quit: &AtomicBool) -> Result<()> {
+    let lay = Layout::new(cfg, 0, 0);
+    let mut ed = Editor {
+        at: (0, puzzle.board.height() - 1),
+        puzzle,
+        brush: GARBAGE,
+        focus: Focus::Board,
+        changed: false,
+        warned: false,
+        note: String::new(),
+    };
+    draw_frame(&lay)?;
+    while !quit.load(Ordering::Relaxed) {
+        ed.draw(cfg, &lay)?;
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                ev if is_ctrl_c(&ev) => break,
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) if !ed.key(key.code, path) => break,
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
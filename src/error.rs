@@ -0,0 +1,51 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// What can stop the game from starting or going on, each with a message
+// that says what to do about it - shown once the terminal is back to normal.
+#[derive(Debug)]
+pub enum Error {
+    TerminalTooSmall { need: (u16, u16), have: (u16, u16) }, // columns, rows
+    Config(String),                                          // a setting that won't do
+    Usage(String),                                           // ... or an option, as given here
+    Corrupt { path: PathBuf, msg: String },                  // a saved file that can't be read back
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::TerminalTooSmall { need, have } => write!(
+                f,
+                "the terminal is {}x{} and the board needs {}x{}: make the window bigger or pick a smaller board",
+                have.0, have.1, need.0, need.1
+            ),
+            Error::Config(msg) | Error::Usage(msg) => write!(f, "{}", msg),
+            Error::Corrupt { path, msg } => write!(
+                f,
+                "{} can't be read back - fix it, or move it out of the way to start afresh: {}",
+                path.display(),
+                msg
+            ),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,96 @@
+use crossterm::event::{Event, EventStream};
+use signal_hook::iterator::Signals;
+use smol::channel::{self, Receiver, Sender};
+use smol::stream::StreamExt;
+use smol::{future, Executor, Timer};
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+// Everything the loops wait on, brought together on one channel by tasks
+// on a smol executor: the terminal's events, the signals to quit by and
+// the audience's sockets with --commands. A loop waits for whichever comes
+// first or for its next tick to be due - a timer racing the channel - so
+// keys, ticks and the rest are taken in turn without the loop polling each. The executor runs while a loop waits, on
+// the loop's own thread, and between waits the tasks' wakes queue up.
+
+// how long a loop with nothing moving on its own - a menu, the pause
+// screen - waits before looking again, at whether it was told to quit, say;
+// anything coming in wakes it sooner, and otherwise the process sleeps
+pub const IDLE: Duration = Duration::from_secs(1);
+
+// what woke a loop
+pub enum Wake {
+    Term(Event), // a key, a resize, ...
+    Quit,        // SIGINT or SIGTERM, with the quit flag set for it
+    Vote,        // a vote from the audience, waiting in the crowd's channel
+}
+
+static EXECUTOR: Executor<'static> = Executor::new();
+
+struct Hub {
+    tx: Sender<Wake>,
+    rx: Receiver<Wake>,
+}
+
+static HUB: OnceLock<Hub> = OnceLock::new();
+
+// the hub, started with the terminal's task the first time it is wanted;
+// from then on only that task reads the terminal
+fn hub() -> &'static Hub {
+    HUB.get_or_init(|| {
+        let (tx, rx) = channel::unbounded();
+        let term = tx.clone();
+        EXECUTOR
+            .spawn(async move {
+                let mut events = EventStream::new();
+                while let Some(Ok(ev)) = events.next().await {
+                    if term.send(Wake::Term(ev)).await.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+        Hub { tx, rx }
+    })
+}
+
+// run task alongside the loops' waits, for as long as the program does
+pub fn spawn(task: impl Future<Output = ()> + Send + 'static) {
+    EXECUTOR.spawn(task).detach();
+}
+
+// wake whichever loop is waiting, or the next to wait
+pub fn wake(w: Wake) {
+    hub().tx.try_send(w).ok(); // unbounded: it only fails once nobody waits
+}
+
+// sigs setting quit as they come, and waking the loop to see it - the
+// signals are taken on a thread of smol's blocking pool, as they can only
+// be waited for by blocking
+pub fn on_signals(sigs: &[i32], quit: Arc<AtomicBool>) -> io::Result<()> {
+    let mut signals = Signals::new(sigs)?;
+    let tx = hub().tx.clone();
+    spawn(smol::unblock(move || {
+        for _ in signals.forever() {
+            quit.store(true, Ordering::Relaxed);
+            if tx.send_blocking(Wake::Quit).is_err() {
+                break;
+            }
+        }
+    }));
+    Ok(())
+}
+
+// the next thing to come in, waiting at most timeout for it
+pub fn next(timeout: Duration) -> Option<Wake> {
+    let hub = hub();
+    let woken = async { hub.rx.recv().await.ok() };
+    let timer = async {
+        Timer::after(timeout).await;
+        None
+    };
+    future::block_on(EXECUTOR.run(future::or(woken, timer)))
+}
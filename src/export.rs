@@ -0,0 +1,87 @@
+use crate::frame::{self, Frame};
+use crate::replay::Replay;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+// Replays as animations: GIF, or APNG for a .png/.apng file. Frames are
+// taken every STEP ticks, and a frame that stays the same is shown longer
+// rather than repeated.
+
+const STEP: u64 = 4; // ticks per frame - 25 a second
+const HOLD: u16 = 300; // centiseconds to linger on the last frame
+
+// call f with each distinct frame of the replay and how long it shows, in centiseconds
+fn frames(replay: &Replay, mut f: impl FnMut(&Frame, u16) -> io::Result<()>) -> io::Result<()> {
+    let cs = (STEP * crate::game::TICK.as_millis() as u64 / 10) as u16;
+    let mut play = replay.playback();
+    let mut shown = frame::draw(&play.game);
+    let mut delay = 0;
+    loop {
+        let mut more = true;
+        for _ in 0..STEP {
+            more = more && play.step();
+        }
+        delay += cs;
+        let next = frame::draw(&play.game);
+        if next.pixels != shown.pixels {
+            f(&shown, delay)?;
+            shown = next;
+            delay = 0;
+        }
+        if !more {
+            return f(&shown, delay + HOLD);
+        }
+    }
+}
+
+pub fn export(replay: &Replay, path: &Path) -> io::Result<()> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match ext.to_lowercase().as_str() {
+        "gif" => gif(replay, BufWriter::new(File::create(path)?)),
+        "png" | "apng" => apng(replay, BufWriter::new(File::create(path)?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: export to .gif, .png or .apng", path.display()),
+        )),
+    }
+}
+
+fn gif(replay: &Replay, out: impl io::Write) -> io::Result<()> {
+    let palette: Vec<u8> = frame::palette().concat();
+    let first = frame::draw(&replay.playback().game);
+    let (w, h) = (first.width as u16, first.height as u16);
+    let mut enc = gif::Encoder::new(out, w, h, &palette).map_err(io::Error::other)?;
+    enc.set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+    frames(replay, |fr, delay| {
+        let frame = gif::Frame {
+            width: w,
+            height: h,
+            delay,
+            buffer: fr.pixels.as_slice().into(),
+            ..gif::Frame::default()
+        };
+        enc.write_frame(&frame).map_err(io::Error::other)
+    })
+}
+
+fn apng(replay: &Replay, out: impl io::Write) -> io::Result<()> {
+    let mut n = 0;
+    frames(replay, |_, _| {
+        n += 1;
+        Ok(())
+    })?;
+    let first = frame::draw(&replay.playback().game);
+    let mut enc = png::Encoder::new(out, first.width as u32, first.height as u32);
+    enc.set_color(png::ColorType::Indexed);
+    enc.set_depth(png::BitDepth::Eight);
+    enc.set_palette(frame::palette().concat());
+    enc.set_animated(n, 0).map_err(io::Error::other)?;
+    let mut w = enc.write_header().map_err(io::Error::other)?;
+    frames(replay, |fr, delay| {
+        w.set_frame_delay(delay, 100).map_err(io::Error::other)?;
+        w.write_image_data(&fr.pixels).map_err(io::Error::other)
+    })?;
+    w.finish().map_err(io::Error::other)
+}
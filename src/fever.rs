@@ -0,0 +1,60 @@
+// Fever, for arcade flair: clears fill a gauge - quick ones, coming one after
+// another, fill it faster - and a full gauge sets off a spell of double points.
+// The gauge drains slowly while nothing is cleared.
+
+pub const FULL: u32 = 100; // gauge level that sets off a fever
+pub const FILL: u32 = 10; // gauge per row cleared
+pub const QUICK_FILL: u32 = 25; // ... when the clear came soon after the last one
+pub const QUICK: u64 = 300; // ticks between clears for the second to count as quick - 3s
+pub const DRAIN: u64 = 10; // ticks per point the gauge drains
+pub const TICKS: u32 = 1000; // how long a fever lasts - ten seconds
+pub const MULTIPLIER: u32 = 2; // points during a fever
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fever {
+    Charging { gauge: u32, last: Option<u64> }, // last: tick of the last clear
+    On(u32),                                    // ticks of it left
+}
+
+impl Default for Fever {
+    fn default() -> Self {
+        Fever::Charging {
+            gauge: 0,
+            last: None,
+        }
+    }
+}
+
+impl Fever {
+    pub fn multiplier(&self) -> u32 {
+        match self {
+            Fever::On(_) => MULTIPLIER,
+            Fever::Charging { .. } => 1,
+        }
+    }
+
+    // rows cleared at tick; true if that set off a fever
+    pub fn clear(&mut self, rows: u32, tick: u64) -> bool {
+        let Fever::Charging { gauge, last } = self else {
+            return false;
+        };
+        let quick = last.is_some_and(|t| tick - t <= QUICK);
+        *gauge += rows * if quick { QUICK_FILL } else { FILL };
+        *last = Some(tick);
+        if *gauge >= FULL {
+            *self = Fever::On(TICKS);
+        }
+        matches!(self, Fever::On(_))
+    }
+
+    pub fn tick(&mut self, tick: u64) {
+        match self {
+            Fever::On(n) if *n > 1 => *n -= 1,
+            Fever::On(_) => *self = Fever::default(),
+            Fever::Charging { gauge, .. } if tick.is_multiple_of(DRAIN) => {
+                *gauge = gauge.saturating_sub(1)
+            }
+            Fever::Charging { .. } => (),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::board::{FLOOR, GARBAGE, ITEM};
+use crate::game::Game;
+use crate::palette;
+use crate::pieces;
+
+// The board drawn as a picture, for exporting games outside the terminal:
+// one square of CELL pixels per cell, inside a border, as palette indices.
+
+pub const CELL: usize = 8; // pixels per cell, each way
+const BORDER: usize = CELL / 2;
+
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>, // indices into palette(), row by row
+}
+
+// palette index of garbage, item and floor cells, the border and flashing rows;
+// 0 is an empty cell and 1.. the pieces
+fn special() -> (u8, u8, u8, u8, u8) {
+    let n = pieces::get().pieces.len() as u8;
+    (n + 1, n + 2, n + 3, n + 4, n + 5)
+}
+
+pub fn palette() -> Vec<[u8; 3]> {
+    let mut colors = vec![[16, 16, 24]];
+    colors.extend(pieces::get().pieces.iter().map(|p| palette::rgb(p.color)));
+    colors.extend([
+        palette::rgb(palette::GARBAGE),
+        palette::rgb(palette::ITEM),
+        palette::rgb(palette::FLOOR),
+        [200, 200, 200],
+        [255, 255, 255],
+    ]);
+    colors
+}
+
+pub fn draw(g: &Game) -> Frame {
+    let (garbage, item, floor, border, flash) = special();
+    let (w, h) = (g.board.width() as usize, g.board.height() as usize);
+    let width = w * CELL + 2 * BORDER;
+    let height = h * CELL + 2 * BORDER;
+    let mut pixels = vec![border; width * height];
+    let flashing = (g.tick / 5).is_multiple_of(2);
+    for y in 0..h {
+        let clearing = flashing && g.clearing().contains(&(y as u8));
+        for x in 0..w {
+            let v = match g.cell(x as u8, y as u8) {
+                _ if clearing => flash,
+                GARBAGE => garbage,
+                ITEM => item,
+                FLOOR => floor,
+                v => v,
+            };
+            for i in 0..CELL {
+                let row = (BORDER + y * CELL + i) * width + BORDER + x * CELL;
+                // a dark line between filled cells, so the pieces show
+                let edge = |j: usize| v != 0 && (i == CELL - 1 || j == CELL - 1);
+                for j in 0..CELL {
+                    pixels[row + j] = if edge(j) { 0 } else { v };
+                }
+            }
+        }
+    }
+    Frame {
+        width,
+        height,
+        pixels,
+    }
+}
@@ -0,0 +1,400 @@
+use crate::board::Board;
+use crate::game::Event;
+use crate::pieces::{self, shape};
+use crate::replay::Replay;
+use std::io;
+
+// The community's "fumen" encoding (v115), as used by fumen.zui.jp and the
+// tools around it: a list of pages, each a 10-wide field and the piece
+// placed on it. Importing takes the first page's field as the starting
+// stack and the pieces placed over all pages as the queue, which is how
+// puzzles are usually shared; exporting turns each piece locked in a
+// replay into a page. Comments are skipped over, and only the seven
+// tetrominoes are known - by their names in the piece set.
+
+pub const WIDTH: u8 = 10;
+const TOP: usize = 23; // rows in the field, besides the garbage row below
+const CELLS: usize = (TOP + 1) * WIDTH as usize;
+const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const PREFIX: &str = "v115@";
+const GRAY: u8 = 8;
+const NAMES: [char; 7] = ['I', 'L', 'O', 'Z', 'T', 'J', 'S']; // fumen's pieces, 1-7
+
+// cells of a piece around its centre, y up, as fumen puts it at spawn
+fn blocks(kind: u8) -> [(i8, i8); 4] {
+    match NAMES[kind as usize - 1] {
+        'I' => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        'T' => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        'O' => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        'L' => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        'J' => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        'S' => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+        _ => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+    }
+}
+
+// ... turned to rotation 0-3, fumen's reverse, right, spawn and left
+fn rotated(kind: u8, rotation: u8) -> [(i8, i8); 4] {
+    blocks(kind).map(|(x, y)| match rotation {
+        0 => (-x, -y),
+        1 => (y, -x),
+        2 => (x, y),
+        _ => (-y, x),
+    })
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// fumen's piece for piece set piece p, if it is one of the seven
+fn kind(p: u8) -> Option<u8> {
+    let name = pieces::get().pieces.get(p as usize)?.name.chars().next()?;
+    NAMES.iter().position(|&c| c == name).map(|k| k as u8 + 1)
+}
+
+// ... and the other way round
+fn piece(kind: u8) -> Option<u8> {
+    let c = *NAMES.get(kind as usize - 1)?;
+    let p = pieces::get()
+        .pieces
+        .iter()
+        .position(|p| p.name.starts_with(c))?;
+    Some(p as u8)
+}
+
+// the values of the encoding, read off a few digits at a time
+struct Reader<'a> {
+    digits: std::slice::Iter<'a, u8>,
+}
+
+impl Reader<'_> {
+    fn done(&self) -> bool {
+        self.digits.len() == 0
+    }
+
+    // n digits, least significant first
+    fn read(&mut self, n: u32) -> io::Result<u32> {
+        let mut v = 0;
+        for i in 0..n {
+            let &d = self
+                .digits
+                .next()
+                .ok_or_else(|| invalid("cut short".to_string()))?;
+            let bad = || invalid(format!("bad character '{}'", d as char));
+            let d = DIGITS.iter().position(|&c| c == d).ok_or_else(bad)?;
+            v += (d as u32) << (6 * i);
+        }
+        Ok(v)
+    }
+}
+
+fn write(s: &mut String, mut v: u32, n: u32) {
+    for _ in 0..n {
+        s.push(DIGITS[(v % 64) as usize] as char);
+        v /= 64;
+    }
+}
+
+// A fumen to start from: the stack, and the pieces to place on it.
+pub struct Puzzle {
+    pub board: Board,
+    pub queue: Vec<u8>, // pieces of the piece set
+}
+
+// a fumen, as "v115@..." or a link with one in it, for a board height rows high
+pub fn parse(src: &str, height: u8) -> io::Result<Puzzle> {
+    let data = match src.find(PREFIX) {
+        Some(i) => &src[i + PREFIX.len()..],
+        None => return Err(invalid("not a v115 fumen".to_string())),
+    };
+    let data: Vec<u8> = data.trim().bytes().filter(|&c| c != b'?').collect();
+    let mut r = Reader {
+        digits: data.iter(),
+    };
+    let mut first = None; // the first page's field
+    let mut queue = Vec::new();
+    let mut repeat = 0; // pages to come that leave the field as it is
+    let mut prev = [0u8; CELLS];
+    while !r.done() {
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut field = prev;
+            let mut i = 0;
+            while i < CELLS {
+                let v = r.read(2)? as usize;
+                let (diff, n) = (v / CELLS, v % CELLS + 1);
+                if i + n > CELLS || diff > 16 {
+                    return Err(invalid("bad field".to_string()));
+                }
+                for cell in &mut field[i..i + n] {
+                    *cell = (*cell as usize + diff).wrapping_sub(8) as u8;
+                }
+                if n == CELLS && diff == 8 {
+                    repeat = r.read(1)?;
+                }
+                i += n;
+            }
+            prev = field;
+        }
+        first.get_or_insert(prev);
+        let action = r.read(3)?;
+        let flags = action / (32 * CELLS as u32);
+        if action % 8 != 0 {
+            queue.push(action as u8 % 8);
+        }
+        if flags & 8 != 0 {
+            // a comment: its length, then four characters to every five digits
+            let n = r.read(2)?;
+            for _ in 0..n.div_ceil(4) {
+                r.read(5)?;
+            }
+        }
+    }
+    let field = first.ok_or_else(|| invalid("no pages".to_string()))?;
+    let mut board = Board::new(WIDTH, height);
+    for row in 0..TOP {
+        let cells = &field[row * WIDTH as usize..][..WIDTH as usize];
+        if cells.iter().all(|&c| c == 0) {
+            continue;
+        }
+        let y = (height as usize + row)
+            .checked_sub(TOP)
+            .ok_or_else(|| invalid(format!("stack higher than the board's {} rows", height)))?;
+        for (x, &c) in cells.iter().enumerate() {
+            let v = match c {
+                0 => 0,
+                GRAY => crate::board::GARBAGE,
+                k => piece(k).ok_or_else(|| invalid("unknown piece".to_string()))? + 1,
+            };
+            board.set(x as u8, y as u8, v);
+        }
+    }
+    let queue = queue
+        .into_iter()
+        .map(|k| piece(k).ok_or_else(|| invalid("unknown piece".to_string())))
+        .collect::<io::Result<_>>()?;
+    Ok(Puzzle { board, queue })
+}
+
+// A page: the field, and the piece locked on it as (piece, orientation, x, y).
+struct Page {
+    board: Board,
+    lock: (u8, u8, u8, u8),
+}
+
+// b as a fumen field, its bottom row fumen's row 0
+fn field(b: &Board) -> io::Result<[u8; CELLS]> {
+    let mut f = [0; CELLS];
+    let h = b.height() as usize;
+    for y in 0..h {
+        for x in 0..b.width() {
+            let v = b.get(x, y as u8);
+            if v == 0 {
+                continue;
+            }
+            let i = (TOP + y)
+                .checked_sub(h)
+                .ok_or_else(|| invalid(format!("stack higher than fumen's {} rows", TOP)))?;
+            f[i * WIDTH as usize + x as usize] = kind(v.wrapping_sub(1)).unwrap_or(GRAY);
+        }
+    }
+    Ok(f)
+}
+
+// one piece's placement as fumen's (kind, rotation, position of its centre)
+fn action(b: &Board, (p, r, x, y): (u8, u8, u8, u8)) -> io::Result<(u8, u8, usize)> {
+    let k = kind(p).ok_or_else(|| invalid("fumen knows only the seven tetrominoes".to_string()))?;
+    let h = b.height() as i16;
+    // cells as fumen's (x, y), counting up from the bottom row
+    let cells: Vec<(i16, i16)> = shape(p)
+        .coor(r)
+        .iter()
+        .map(|&(i, j)| ((x + j) as i16, h - 1 - (y + i) as i16))
+        .collect();
+    for rotation in 0..4 {
+        let offsets = rotated(k, rotation);
+        for (dx, dy) in offsets {
+            let (cx, cy) = (cells[0].0 - dx as i16, cells[0].1 - dy as i16);
+            let fits = offsets
+                .iter()
+                .all(|&(dx, dy)| cells.contains(&(cx + dx as i16, cy + dy as i16)));
+            if !fits {
+                continue;
+            }
+            // where fumen keeps the centre of the pieces that turn about a corner
+            let (cx, cy) = match (NAMES[k as usize - 1], rotation) {
+                ('O', 3) => (cx + 1, cy - 1),
+                ('O', 0) | ('I', 0) => (cx + 1, cy),
+                ('O', 2) | ('I', 3) | ('S', 2) | ('Z', 2) => (cx, cy - 1),
+                ('S', 1) => (cx - 1, cy),
+                ('Z', 3) => (cx + 1, cy),
+                _ => (cx, cy),
+            };
+            let pos = (TOP as i16 - cy - 1) * WIDTH as i16 + cx;
+            return Ok((k, rotation, pos as usize));
+        }
+    }
+    Err(invalid(format!("piece {} doesn't match fumen's", p)))
+}
+
+fn encode(pages: &[Page]) -> io::Result<String> {
+    let mut s = String::new();
+    let mut prev = [0u8; CELLS];
+    for (n, page) in pages.iter().enumerate() {
+        let f = field(&page.board)?;
+        // runs of the same change from the last field
+        let diffs: Vec<usize> = (0..CELLS)
+            .map(|i| f[i] as usize + 8 - prev[i] as usize)
+            .collect();
+        let mut i = 0;
+        while i < CELLS {
+            let run = diffs[i..].iter().take_while(|&&d| d == diffs[i]).count();
+            write(&mut s, (diffs[i] * CELLS + run - 1) as u32, 2);
+            i += run;
+        }
+        if diffs.iter().all(|&d| d == 8) {
+            write(&mut s, 0, 1); // the pages after it change the field again
+        }
+        let (k, rotation, pos) = action(&page.board, page.lock)?;
+        let colour = (n == 0) as usize; // fumen's flag for the pieces' own colours
+        let v = ((colour << 2) * CELLS + pos) * 32 + rotation as usize * 8 + k as usize;
+        write(&mut s, v as u32, 3);
+        // fumen's field after the lock: the piece in, full rows out
+        let mut b = page.board.clone();
+        let (p, r, x, y) = page.lock;
+        b.place(shape(p).coor(r), x, y, p + 1);
+        b.clear_full_rows();
+        prev = field(&b)?;
+    }
+    // a '?' after the first 42 digits and every 47 after that, as fumen writes it
+    let mut out = String::from(PREFIX);
+    for (i, c) in s.chars().enumerate() {
+        if i >= 42 && (i - 42) % 47 == 0 {
+            out.push('?');
+        }
+        out.push(c);
+    }
+    Ok(out)
+}
+
+// replay r as a fumen, a page for every piece locked
+pub fn from_replay(r: &Replay) -> io::Result<String> {
+    if r.config.width != WIDTH {
+        return Err(invalid(format!("fumen boards are {} wide", WIDTH)));
+    }
+    let mut play = r.playback();
+    let mut pages = Vec::new();
+    let mut before = play.game.board.clone();
+    loop {
+        let going = play.step();
+        for e in play.game.events.drain(..) {
+            if let Event::Lock(p, r, x, y) = e {
+                pages.push(Page {
+                    board: before.clone(),
+                    lock: (p, r, x, y),
+                });
+            }
+        }
+        before = play.game.board.clone();
+        if !going {
+            break;
+        }
+    }
+    if pages.is_empty() {
+        return Err(invalid("no pieces locked".to_string()));
+    }
+    encode(&pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::game::{Game, Move, Phase};
+    use crate::position;
+
+    const EMPTY: u32 = 8 * CELLS as u32 + CELLS as u32 - 1; // a field of no change
+    const COMMENT: u32 = 8; // page flags
+    const COLOUR: u32 = 4;
+
+    // a page placing fumen piece kind with flags, on the field as it was
+    fn page(s: &mut String, kind: u8, flags: u32) {
+        write(s, EMPTY, 2);
+        write(s, 0, 1); // no pages repeating it
+        write(
+            s,
+            (flags * CELLS as u32 + 100) * 32 + 2 * 8 + kind as u32,
+            3,
+        );
+    }
+
+    fn fumen_kind(name: char) -> u8 {
+        NAMES.iter().position(|&c| c == name).unwrap() as u8 + 1
+    }
+
+    #[test]
+    fn a_replay_round_trips_as_its_stack_and_pieces() {
+        let cfg = Config::default();
+        let stack = position::parse("#####.####\n####.#####", cfg.width, cfg.height).unwrap();
+        let mut g = Game::with_seed(&cfg, 3);
+        g.set_board(stack.clone());
+        let mut r = Replay::new(&g);
+        let mut locked = Vec::new();
+        for i in 0..6 {
+            let moves = std::iter::repeat_n(Move::Rotate, i % 4)
+                .chain(std::iter::repeat_n(Move::Left, i % 3))
+                .chain([Move::HardDrop]);
+            for m in moves {
+                r.record(&g, m);
+                g.apply(m);
+            }
+            while g.phase != Phase::Falling {
+                g.do_tick();
+            }
+            for e in g.events.drain(..) {
+                if let Event::Lock(p, ..) = e {
+                    locked.push(p);
+                }
+            }
+        }
+        r.finish(&g);
+        let src = from_replay(&r).unwrap();
+        assert!(src.starts_with(PREFIX));
+        let puzzle = parse(&src, cfg.height).unwrap();
+        assert_eq!(puzzle.board.to_ascii(), stack.to_ascii());
+        assert_eq!(puzzle.queue, locked);
+    }
+
+    #[test]
+    fn comments_and_flags_are_skipped_over() {
+        let mut s = String::from(PREFIX);
+        page(&mut s, fumen_kind('T'), COMMENT | COLOUR);
+        write(&mut s, 6, 2); // "a note"
+        write(&mut s, 0, 5);
+        write(&mut s, 0, 5);
+        page(&mut s, fumen_kind('I'), 0);
+        let puzzle = parse(&s, 20).unwrap();
+        assert!(puzzle.board.is_empty());
+        let letters: Vec<char> = puzzle
+            .queue
+            .iter()
+            .map(|&p| {
+                pieces::get().pieces[p as usize]
+                    .name
+                    .chars()
+                    .next()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(letters, ['T', 'I']);
+    }
+
+    #[test]
+    fn a_broken_fumen_is_an_error() {
+        assert!(parse("v114@vhAAgH", 20).is_err());
+        assert!(parse("v115@vh", 20).is_err()); // cut short
+        assert!(parse("v115@!!AAgH", 20).is_err());
+    }
+}
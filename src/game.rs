@@ -0,0 +1,1191 @@
+use crate::board::Board;
+use crate::board::ITEM;
+use crate::config::Config;
+use crate::fever::Fever;
+use crate::garbage::{self, Garbage};
+use crate::items::{self, Item, ITEMS};
+use crate::mode::{self, Mode};
+use crate::pack::Goal;
+use crate::pieces::{self, shape};
+use crate::randomizer::{self, Randomizer};
+use crate::speed::Timing;
+use crate::stats::{self, Stats};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const TICK: Duration = Duration::from_millis(10); // one simulation step
+pub const TICK_LEVEL: u64 = 6000; // ticks per level - a minute
+
+// What puts off locking a piece that has landed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockReset {
+    Step,     // only moving down a row restarts the lock delay
+    Move,     // so does moving or rotating, up to MOVE_RESETS times per row
+    Infinite, // moving or rotating always restarts it
+}
+
+pub const MOVE_RESETS: u32 = 15;
+
+impl std::str::FromStr for LockReset {
+    type Err = ();
+    fn from_str(s: &str) -> Result<LockReset, ()> {
+        match s {
+            "step" => Ok(LockReset::Step),
+            "move" => Ok(LockReset::Move),
+            "infinite" => Ok(LockReset::Infinite),
+            _ => Err(()),
+        }
+    }
+}
+
+// What the game is doing between pieces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Falling,                // a piece is in play
+    Clearing(Vec<u8>, u32), // full rows on their way out, ticks left
+    Entry(u32),             // ticks until the next piece appears (ARE)
+}
+
+// ms to whole ticks
+fn ticks(ms: u32) -> u32 {
+    ms / TICK.as_millis() as u32
+}
+
+// The player's moves - everything that can change the game state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    Left,
+    Right,
+    Rotate,
+    HardDrop,
+    SonicDrop, // straight to the floor, but without locking
+    Item,      // use the item in hand
+    Hold,      // put the falling piece aside, bringing in the one held or the next
+}
+
+// in the order the C API numbers them, new moves going last
+pub const MOVES: [Move; 7] = [
+    Move::Left,
+    Move::Right,
+    Move::Rotate,
+    Move::HardDrop,
+    Move::SonicDrop,
+    Move::Item,
+    Move::Hold,
+];
+
+impl Move {
+    // as in the [keys] table and replay files
+    pub fn name(self) -> &'static str {
+        match self {
+            Move::Left => "left",
+            Move::Right => "right",
+            Move::Rotate => "rotate",
+            Move::HardDrop => "hard_drop",
+            Move::SonicDrop => "sonic_drop",
+            Move::Item => "item",
+            Move::Hold => "hold",
+        }
+    }
+}
+
+impl std::str::FromStr for Move {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Move, ()> {
+        MOVES.into_iter().find(|m| m.name() == s).ok_or(())
+    }
+}
+
+// A T-spin: a T that rotated into place with three of the four cells
+// diagonal to its centre filled - a mini unless both on the side it points
+// to are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+// What a locked piece did, for the player to be told: lines cleared, with
+// a spin or not, and whether it came straight after another difficult
+// clear - a tetris or a spin - back to back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clear {
+    pub lines: u32,
+    pub spin: Spin,
+    pub b2b: bool,
+}
+
+impl Clear {
+    // a tetris or a spin clearing lines, which keep the back to back going
+    pub fn difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.spin != Spin::None)
+    }
+
+    // as the game calls it, "T-SPIN MINI SINGLE" or "BACK-TO-BACK TETRIS" -
+    // nothing for an ordinary single, or a piece that cleared nothing short
+    // of a full T-spin
+    pub fn name(&self) -> Option<String> {
+        let lines = ["", "SINGLE", "DOUBLE", "TRIPLE", "TETRIS"][self.lines.min(4) as usize];
+        let s = match self.spin {
+            Spin::None if self.lines < 2 => return None,
+            Spin::Mini if self.lines == 0 => return None,
+            Spin::None => lines.to_string(),
+            Spin::Mini => format!("T-SPIN MINI {}", lines),
+            Spin::Full => format!("T-SPIN {}", lines),
+        };
+        let s = s.trim_end().to_string();
+        Some(if self.b2b {
+            format!("BACK-TO-BACK {}", s)
+        } else {
+            s
+        })
+    }
+}
+
+// Things worth telling the player about, collected for the renderer to take.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Chain(u32),           // a cascade set off clear number n
+    Fever,                // the fever gauge filled up
+    Lock(u8, u8, u8, u8), // a piece locked, as (tetromino, orientation, x, y)
+    Clear(Clear),         // ... and what it cleared, if it was worth a mention
+    PerfectClear,         // the board was left empty
+    Garbage(u32),         // garbage rows rose, this many
+    Row(u8),              // a row cleared, where it was
+}
+
+pub struct Game {
+    pub x: u8, // coor
+    pub y: u8,
+    pub r: u8,                     // orientation
+    pub p: u8,                     // tetromino
+    pub next: VecDeque<(u8, u8)>,  // upcoming (tetromino, orientation)
+    pub held: Option<(u8, u8)>,    // put aside with a hold
+    pub can_hold: bool,            // ... which can be done once a piece
+    pub puzzle: Vec<u8>,           // pieces set to come first, as in a puzzle
+    pub goal: Option<(Goal, u32)>, // ... and what it asks for, with the pieces there are for it
+    pub solved: bool,
+    cfg: Config,
+    randomizer: Box<dyn Randomizer>,
+    rng: Box<dyn RngCore>, // everything random in the game, so a seed and the moves replay it
+    pub seed: u64,         // ... and the seed it started from
+    pub tick: u64,
+    pub phase: Phase,
+    pub fall: f64,        // progress towards the next gravity step, in cells
+    pub lock_ticks: u32,  // ticks spent on the ground
+    pub lock_resets: u32, // lock delay restarts since reaching the lowest row
+    pub lowest: u8,       // lowest row the piece has reached
+    pub score: u32,
+    pub lines: u32,
+    pub splits: Vec<u64>,   // tick each SPLIT lines were reached, in sprint
+    pub pace: Vec<u64>,     // ... in the personal best, to race against
+    pub timeline: Vec<u64>, // tick each line was cleared, in sprint
+    pub ghost: Vec<u64>,    // ... in the personal best, for its ghost
+    pub chain: u32,         // clears set off by the last piece, counting cascades
+    pub combo: u32,         // pieces in a row that cleared lines
+    pub b2b: u32,           // difficult clears in a row, tetrises and spins
+    pub spun: bool,         // the falling piece's last move was a rotation
+    pub fever: Fever,
+    pub events: Vec<Event>,
+    pub stats: Stats,
+    pub garbage: Garbage, // incoming, in versus play and survival
+    pub rising: u64,      // tick the next garbage row comes in in survival, or floor row in rising
+    pub sent: u32,        // attack lines not yet handed to the opponent
+    pub item: Option<Item>,
+    pub slow: u32,           // ticks of slow gravity left
+    pub sent_scramble: bool, // scramble not yet handed to the opponent
+    pub scramble: bool,      // scramble the stack before the next piece
+    pub board: Board,        // 20 rows x 10 cols unless configured otherwise
+    pub paused: bool,
+    pub over: bool,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(&Config::default())
+    }
+}
+
+impl Game {
+    pub fn new(cfg: &Config) -> Game {
+        Game::with_seed(cfg, thread_rng().gen())
+    }
+
+    pub fn with_seed(cfg: &Config, seed: u64) -> Game {
+        let mut g = Game::with_rng(cfg, Box::new(StdRng::seed_from_u64(seed)));
+        g.seed = seed;
+        g
+    }
+
+    // a game drawing on rng for everything random, as a test might with a
+    // fixed sequence; with no seed to go by, it can't be replayed
+    pub fn with_rng(cfg: &Config, rng: Box<dyn RngCore>) -> Game {
+        let mut cfg = cfg.clone();
+        cfg.previews = cfg.handicap.previews.unwrap_or(cfg.previews);
+        let cfg = &cfg;
+        let mut g = Game {
+            x: 0,
+            y: 0,
+            r: 0,
+            p: 0,
+            next: VecDeque::new(),
+            held: None,
+            can_hold: true,
+            puzzle: Vec::new(),
+            goal: None,
+            solved: false,
+            cfg: cfg.clone(),
+            randomizer: randomizer::new(cfg.randomizer),
+            rng,
+            seed: 0,
+            tick: 0,
+            phase: Phase::Falling,
+            fall: 0.0,
+            lock_ticks: 0,
+            lock_resets: 0,
+            lowest: 0,
+            score: 0,
+            lines: 0,
+            splits: Vec::new(),
+            pace: Vec::new(),
+            timeline: Vec::new(),
+            ghost: Vec::new(),
+            chain: 0,
+            combo: 0,
+            b2b: 0,
+            spun: false,
+            fever: Fever::default(),
+            events: Vec::new(),
+            stats: Stats::default(),
+            garbage: Garbage::default(),
+            rising: mode::rising_interval(cfg.mode, 0),
+            sent: 0,
+            item: None,
+            slow: 0,
+            sent_scramble: false,
+            scramble: false,
+            board: Board::new(cfg.width, cfg.height),
+            paused: false,
+            over: false,
+        };
+        g.new_tetramino();
+        g
+    }
+
+    // start from the given stack rather than an empty board
+    pub fn set_board(&mut self, board: Board) {
+        self.board = board;
+        if self.hits(self.x as i16, self.y as i16, self.r) {
+            self.over = true;
+        }
+    }
+
+    // deal the given pieces first, starting with the one in play
+    pub fn set_queue(&mut self, queue: &[u8]) {
+        let Some((&p, rest)) = queue.split_first() else {
+            return;
+        };
+        self.puzzle = queue.to_vec();
+        for &q in rest.iter().rev() {
+            self.next.push_front((q, 0));
+        }
+        (self.p, self.r) = (p, 0);
+        self.x = (self.board.width() - shape(p).dim(0).0) / 2;
+        self.over = self.hits(self.x as i16, self.y as i16, self.r);
+    }
+
+    // the pieces still to come of the bag the piece in play was dealt from,
+    // queued or not yet dealt - the queue runs ahead of the randomizer, into
+    // the bags after that one - or none, the next bag to come whole
+    pub fn bag(&self) -> Option<Vec<u8>> {
+        let (left, round) = self.randomizer.bag()?;
+        let queued: Vec<u8> = self.next.iter().map(|&(p, _)| p).collect();
+        let dealt = round - left.len(); // of the randomizer's bag, into the queue
+        match queued.len().checked_sub(dealt) {
+            Some(n @ 1..) => Some(queued[..(n - 1) % round + 1].to_vec()), // of bags before
+            _ => Some(queued.iter().chain(left).copied().collect()),
+        }
+    }
+
+    // the settings the game was started with
+    pub fn cfg(&self) -> &Config {
+        &self.cfg
+    }
+
+    pub fn level(&self) -> u64 {
+        self.cfg.level + self.tick / TICK_LEVEL
+    }
+
+    // how fast the level played is, on the game's speed curve
+    pub fn timing(&self) -> Timing {
+        self.cfg.curve.timing(self.level(), &self.cfg)
+    }
+
+    fn random_tetramino(&mut self) -> (u8, u8) {
+        let p = self.randomizer.next(&mut self.rng);
+        (p, self.rng.gen_range(0..4)) // tetromino, orientation
+    }
+
+    fn new_tetramino(&mut self) {
+        while self.next.len() <= self.cfg.previews {
+            let t = self.random_tetramino();
+            self.next.push_back(t);
+        }
+        let (p, r) = self.next.pop_front().unwrap();
+        self.stats.deal(p);
+        self.spawn(p, r);
+    }
+
+    // bring p in at the top, in orientation r
+    fn spawn(&mut self, p: u8, r: u8) {
+        (self.p, self.r) = (p, r);
+        self.spun = false;
+        self.x = self
+            .rng
+            .gen_range(0..=self.board.width() - shape(self.p).dim(self.r).0);
+        self.y = 0;
+        self.phase = Phase::Falling;
+        self.fall = 0.0;
+        self.lock_ticks = 0;
+        self.lock_resets = 0;
+        self.lowest = 0;
+        if self.hits(self.x as i16, self.y as i16, self.r) {
+            self.over = true; // no room to spawn
+        }
+    }
+
+    // check if placing p at (x,y,r) will hit something
+    pub fn hits(&self, x: i16, y: i16, r: u8) -> bool {
+        self.board.collides(shape(self.p).mask(r), x, y)
+    }
+
+    // tetromino+1 at (x, y) counting the falling piece, 0 if empty
+    pub fn cell(&self, x: u8, y: u8) -> u8 {
+        let active = self.phase == Phase::Falling
+            && shape(self.p)
+                .coor(self.r)
+                .iter()
+                .any(|&(i, j)| (self.x + j, self.y + i) == (x, y));
+        if active {
+            self.p + 1
+        } else {
+            self.board.get(x, y)
+        }
+    }
+
+    // row the falling piece would land on if dropped
+    pub fn landing(&self) -> u8 {
+        let mut y = self.y;
+        while !self.hits(self.x as i16, y as i16 + 1, self.r) {
+            y += 1;
+        }
+        y
+    }
+
+    // is (x, y) under the ghost piece - where the falling piece would land?
+    pub fn ghost_cell(&self, x: u8, y: u8) -> bool {
+        if self.phase != Phase::Falling {
+            return false;
+        }
+        let land = self.landing();
+        land != self.y
+            && shape(self.p)
+                .coor(self.r)
+                .iter()
+                .any(|&(i, j)| (self.x + j, land + i) == (x, y))
+    }
+
+    // rows flashing on their way out, if any
+    pub fn clearing(&self) -> &[u8] {
+        match &self.phase {
+            Phase::Clearing(rows, _) => rows,
+            _ => &[],
+        }
+    }
+
+    // fix the piece on the board, then clear rows and bring on the next one
+    fn lock(&mut self) {
+        self.can_hold = true;
+        self.events
+            .push(Event::Lock(self.p, self.r, self.x, self.y));
+        let spin = self.spin();
+        let (x, y) = (self.x, self.y);
+        let cells = shape(self.p)
+            .coor(self.r)
+            .iter()
+            .map(|&(i, j)| (x + j, y + i));
+        let (w, h) = (self.board.width(), self.board.height());
+        self.stats.lock(cells, w, h);
+        self.board
+            .place(shape(self.p).coor(self.r), self.x, self.y, self.p + 1);
+        let full = self.board.full_rows();
+        self.chain = 0;
+        let mut clear = Clear {
+            lines: full.len() as u32,
+            spin,
+            b2b: false,
+        };
+        if clear.difficult() {
+            self.b2b += 1;
+            clear.b2b = self.b2b > 1;
+        } else if clear.lines > 0 {
+            self.b2b = 0;
+        }
+        if clear.name().is_some() {
+            self.events.push(Event::Clear(clear));
+        }
+        if let Some((goal, _)) = self.goal {
+            self.solved |= goal.met(&clear, self.lines + clear.lines);
+        }
+        if self.y == 0 {
+            // overflow - game over
+            self.clear_rows();
+            self.over = true;
+        } else if full.is_empty() {
+            self.combo = 0;
+            self.raise_garbage();
+            if !self.over {
+                self.entry();
+            }
+        } else {
+            self.combo += 1;
+            self.clear(full);
+        }
+    }
+
+    // the spin the falling piece makes if it locks where it is
+    fn spin(&self) -> Spin {
+        if !self.spun || pieces::get().pieces[self.p as usize].name != "T" {
+            return Spin::None;
+        }
+        let cells = shape(self.p).coor(self.r);
+        let has = |i: i16, j: i16| cells.iter().any(|&(a, b)| (a as i16, b as i16) == (i, j));
+        let steps = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+        // the centre, with its three neighbours in the piece, and the way it points
+        let Some((ci, cj)) = (cells.iter())
+            .map(|&(i, j)| (i as i16, j as i16))
+            .find(|&(i, j)| steps.iter().filter(|(di, dj)| has(i + di, j + dj)).count() == 3)
+        else {
+            return Spin::None;
+        };
+        let Some(&(pi, pj)) = steps.iter().find(|(di, dj)| !has(ci - di, cj - dj)) else {
+            return Spin::None;
+        };
+        let filled = |di: i16, dj: i16| {
+            let (x, y) = (self.x as i16 + cj + dj, self.y as i16 + ci + di);
+            !(0..self.board.width() as i16).contains(&x)
+                || !(0..self.board.height() as i16).contains(&y)
+                || self.board.get(x as u8, y as u8) != 0
+        };
+        let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+        if corners.iter().filter(|&&(di, dj)| filled(di, dj)).count() < 3 {
+            return Spin::None;
+        }
+        // the corners on the pointing side: the diagonals sharing its step
+        let front = corners
+            .iter()
+            .filter(|&&(di, dj)| (pi != 0 && di == pi) || (pj != 0 && dj == pj))
+            .all(|&(di, dj)| filled(di, dj));
+        if front {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    // clear full rows now, or after the clear delay
+    fn clear(&mut self, full: Vec<u8>) {
+        self.chain += 1;
+        if self.chain > 1 {
+            self.events.push(Event::Chain(self.chain));
+        }
+        match ticks(self.timing().clear) {
+            0 => {
+                self.clear_rows();
+                self.cleared();
+            }
+            n => self.phase = Phase::Clearing(full, n),
+        }
+    }
+
+    // rows gone - on to the next piece, unless falling blocks fill more rows
+    fn cleared(&mut self) {
+        if self.cfg.cascade && self.board.cascade() {
+            let full = self.board.full_rows();
+            if !full.is_empty() {
+                return self.clear(full);
+            }
+        }
+        self.entry();
+    }
+
+    fn clear_rows(&mut self) {
+        let points = self.cfg.mode != Mode::Survival;
+        let w = self.board.width();
+        let item = self
+            .board
+            .full_rows()
+            .iter()
+            .any(|&y| (0..w).any(|x| self.board.get(x, y) == ITEM));
+        if item && self.item.is_none() {
+            self.item = ITEMS.choose(&mut self.rng).copied();
+        }
+        let rows = self.board.clear_full_rows();
+        (self.events).extend(rows.iter().map(|&y| Event::Row(y)));
+        let floor = self.board.floor();
+        if !rows.is_empty() && self.board.column_heights().iter().all(|&h| h == floor) {
+            self.events.push(Event::PerfectClear);
+            self.solved |= self.goal.is_some_and(|(g, _)| g == Goal::Perfect);
+        }
+        if points {
+            // the chain and the starting level, doubled in a fever, for the scoring to go by
+            let n = self.chain.max(1) * self.cfg.level as u32 * self.fever.multiplier();
+            self.score += self.cfg.scoring.clear(&rows, self.level(), n);
+        }
+        self.lines += rows.len() as u32;
+        if self.cfg.mode == Mode::Sprint {
+            self.timeline.resize(self.lines as usize, self.tick);
+            while self.lines >= (self.splits.len() as u32 + 1) * mode::SPLIT {
+                self.splits.push(self.tick);
+            }
+            if self.lines >= mode::SPRINT_LINES {
+                self.over = true; // finished
+            }
+        }
+        if self.cfg.fever && self.fever.clear(rows.len() as u32, self.tick) {
+            self.events.push(Event::Fever);
+        }
+        let attack = garbage::attack(rows.len() as u32) as f64 * self.cfg.handicap.garbage;
+        self.sent += self.garbage.cancel(attack.round() as u32);
+    }
+
+    fn raise_garbage(&mut self) {
+        let ready = self.garbage.ready();
+        if !ready.is_empty() {
+            self.events.push(Event::Garbage(ready.iter().sum()));
+        }
+        for n in ready {
+            let hole = self.rng.gen_range(0..self.board.width());
+            if !self.board.insert_garbage(n.min(255) as u8, hole) {
+                self.over = true;
+            }
+            if self.cfg.items && self.rng.gen_ratio(1, items::CHANCE) {
+                let x = (hole + self.rng.gen_range(1..self.board.width())) % self.board.width();
+                let top = self.board.height() - self.board.floor(); // the floor's first row
+                let y = top - (n as u8).min(top);
+                self.board.mark(x, y, ITEM);
+            }
+        }
+    }
+
+    // next piece now, or after the entry delay
+    fn entry(&mut self) {
+        if let Some((_, pieces)) = self.goal {
+            if self.solved || self.stats.pieces() >= pieces {
+                self.over = true; // the puzzle is done, one way or the other
+                return;
+            }
+        }
+        if std::mem::take(&mut self.scramble) {
+            self.board.scramble(&mut self.rng);
+        }
+        match ticks(self.timing().entry) {
+            0 => self.new_tetramino(),
+            n => self.phase = Phase::Entry(n),
+        }
+    }
+
+    // move/rotate to (x, y, r) if there is room
+    fn try_move(&mut self, x: i16, y: i16, r: u8) -> bool {
+        if self.hits(x, y, r) {
+            return false;
+        }
+        self.spun = r != self.r;
+        (self.x, self.y, self.r) = (x as u8, y as u8, r);
+        if self.y > self.lowest {
+            self.lowest = self.y;
+            self.lock_resets = 0;
+        }
+        true
+    }
+
+    // a move or rotation succeeded - put off locking if the rules allow
+    fn moved(&mut self) {
+        if self.lock_ticks == 0 {
+            return;
+        }
+        match self.cfg.lock_reset {
+            LockReset::Step => (),
+            LockReset::Move if self.lock_resets >= MOVE_RESETS => (),
+            LockReset::Move => {
+                self.lock_resets += 1;
+                self.lock_ticks = 0;
+            }
+            LockReset::Infinite => self.lock_ticks = 0,
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.try_move(self.x as i16 - 1, self.y as i16, self.r) {
+            self.moved();
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.try_move(self.x as i16 + 1, self.y as i16, self.r) {
+            self.moved();
+        }
+    }
+
+    // rotate clockwise, trying the kicks of the piece set in turn
+    fn rotate(&mut self) {
+        let r = (self.r + 1) % 4;
+        for &(dx, dy) in &pieces::get().kicks {
+            if self.try_move(self.x as i16 + dx as i16, self.y as i16 + dy as i16, r) {
+                self.moved();
+                return;
+            }
+        }
+    }
+
+    // how far the falling piece is on its way to the next row down, 0 to 1,
+    // for drawing it in between; 0 once it has landed
+    pub fn between(&self) -> f64 {
+        match self.phase == Phase::Falling && !self.grounded() {
+            true => self.fall,
+            false => 0.0,
+        }
+    }
+
+    pub fn grounded(&self) -> bool {
+        self.hits(self.x as i16, self.y as i16 + 1, self.r)
+    }
+
+    pub fn apply(&mut self, m: Move) {
+        let score = self.score;
+        self.make(m);
+        self.check(score, m.name());
+    }
+
+    fn make(&mut self, m: Move) {
+        if self.over || self.phase != Phase::Falling {
+            return;
+        }
+        match m {
+            Move::Left => self.move_left(),
+            Move::Right => self.move_right(),
+            Move::Rotate => self.rotate(),
+            Move::HardDrop => self.hard_drop(),
+            Move::SonicDrop => self.sonic_drop(),
+            Move::Item => self.use_item(),
+            Move::Hold => self.hold(),
+        }
+    }
+
+    // the piece a hold would bring into play: the one held, or else the next
+    pub fn swap(&self) -> Option<(u8, u8)> {
+        self.held.or_else(|| self.next.front().copied())
+    }
+
+    fn hold(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        self.can_hold = false;
+        match self.held.replace((self.p, self.r)) {
+            Some((p, r)) => self.spawn(p, r),
+            None => self.new_tetramino(),
+        }
+    }
+
+    fn use_item(&mut self) {
+        match self.item.take() {
+            Some(Item::ClearBottom) => {
+                self.board.remove_bottom(items::CLEAR_ROWS);
+                while self.y > 0 && self.hits(self.x as i16, self.y as i16, self.r) {
+                    self.y -= 1; // the stack came down onto the piece
+                }
+            }
+            Some(Item::Scramble) => self.sent_scramble = true,
+            Some(Item::SlowGravity) => self.slow = items::SLOW_TICKS,
+            None => (),
+        }
+    }
+
+    fn sonic_drop(&mut self) {
+        let y = self.y;
+        while self.try_move(self.x as i16, self.y as i16 + 1, self.r) {}
+        if self.y != y {
+            self.lock_ticks = 0;
+        }
+        self.dropped(self.y - y);
+    }
+
+    fn hard_drop(&mut self) {
+        let y = self.y;
+        while self.try_move(self.x as i16, self.y as i16 + 1, self.r) {}
+        self.dropped(self.y - y);
+        self.lock();
+    }
+
+    // points, if the scoring gives any, for the piece dropped rows rows
+    fn dropped(&mut self, rows: u8) {
+        if self.cfg.mode != Mode::Survival {
+            self.score += self.cfg.scoring.drop(rows);
+        }
+    }
+
+    // ticks that can go by before anything in the game changes by itself -
+    // the piece falling a row or locking, a timer running out - for a loop
+    // with nothing else to do to sleep through; 1 while anything counts down
+    pub fn quiet(&self) -> u64 {
+        let busy = self.phase != Phase::Falling
+            || !self.garbage.queue.is_empty()
+            || self.slow > 0
+            || self.cfg.fever;
+        if busy {
+            return 1;
+        }
+        let mut n = u64::MAX;
+        if matches!(self.cfg.mode, Mode::Survival | Mode::Rising) {
+            n = n.min(self.rising.saturating_sub(self.tick));
+        }
+        if self.cfg.mode == Mode::Survival {
+            n = n.min(100 - self.tick % 100); // the score, in seconds
+        }
+        if self.cfg.mode == Mode::Ultra {
+            n = n.min(mode::ULTRA_TICKS.saturating_sub(self.tick));
+        }
+        if self.grounded() {
+            let delay = ticks(self.timing().lock) as u64;
+            n = n.min(delay.saturating_sub(self.lock_ticks as u64));
+        } else {
+            let handicap = self.cfg.handicap.gravity;
+            let per = handicap * self.timing().gravity * TICK.as_secs_f64();
+            if per > 0.0 {
+                n = n.min(((1.0 - self.fall) / per).ceil() as u64);
+            }
+        }
+        n.max(1)
+    }
+
+    // advance the game one tick; false once the game is over
+    pub fn do_tick(&mut self) -> bool {
+        let score = self.score;
+        let going = self.step();
+        self.check(score, "tick");
+        going
+    }
+
+    fn step(&mut self) -> bool {
+        if self.over {
+            return false;
+        }
+        if self.paused {
+            return true;
+        }
+        self.tick = (self.tick + 1) % u64::MAX;
+        if self.cfg.mode == Mode::Ultra && self.tick >= mode::ULTRA_TICKS {
+            self.over = true; // time's up
+            return false;
+        }
+        if self.tick.is_multiple_of(stats::SAMPLE) {
+            self.stats.scores.push(self.score);
+        }
+        self.garbage.tick();
+        self.slow = self.slow.saturating_sub(1);
+        self.fever.tick(self.tick);
+        if self.cfg.mode == Mode::Survival {
+            self.score = (self.tick / 100) as u32; // seconds survived
+            if self.tick >= self.rising {
+                self.garbage.push(1);
+                self.rising = self.tick + mode::rising_interval(self.cfg.mode, self.tick);
+            }
+        }
+        match &mut self.phase {
+            Phase::Falling => (),
+            Phase::Clearing(_, n) | Phase::Entry(n) if *n > 1 => {
+                *n -= 1;
+                return true;
+            }
+            Phase::Clearing(..) => {
+                self.clear_rows();
+                self.cleared();
+                return true;
+            }
+            Phase::Entry(_) => {
+                self.new_tetramino();
+                return !self.over;
+            }
+        }
+        if self.cfg.mode == Mode::Rising && self.tick >= self.rising {
+            self.board.raise_floor();
+            self.rising = self.tick + mode::rising_interval(self.cfg.mode, self.tick);
+            while self.y > 0 && self.hits(self.x as i16, self.y as i16, self.r) {
+                self.y -= 1; // pushed up by the floor
+            }
+            if self.hits(self.x as i16, self.y as i16, self.r) {
+                self.over = true;
+                return false;
+            }
+        }
+        let slow = if self.slow > 0 { 0.5 } else { 1.0 };
+        let timing = self.timing();
+        let handicap = self.cfg.handicap.gravity;
+        self.fall += slow * handicap * timing.gravity * TICK.as_secs_f64();
+        while self.fall >= 1.0 {
+            self.fall -= 1.0;
+            if !self.try_move(self.x as i16, self.y as i16 + 1, self.r) {
+                self.fall = 0.0;
+                break;
+            }
+            self.lock_ticks = 0; // stepping down always restarts the lock delay
+        }
+        if self.grounded() {
+            self.lock_ticks += 1;
+            if self.lock_ticks * TICK.as_millis() as u32 >= timing.lock {
+                self.lock();
+            }
+        }
+        !self.over
+    }
+
+    // with debug-invariants, stop the game at the first sign of a bug: after
+    // every move and tick the board must agree with itself, the falling piece
+    // must sit on the board clear of the stack, and the score can only go up
+    #[cfg(feature = "debug-invariants")]
+    fn check(&self, score: u32, after: &str) {
+        let err = if let Err(e) = self.board.check() {
+            Some(e)
+        } else if self.phase == Phase::Falling
+            && !self.over
+            && self.hits(self.x as i16, self.y as i16, self.r)
+        {
+            Some("the falling piece overlaps the stack or leaves the board".to_string())
+        } else if self.score < score {
+            Some(format!(
+                "the score went down from {} to {}",
+                score, self.score
+            ))
+        } else {
+            None
+        };
+        if let Some(e) = err {
+            panic!(
+                "invariant broken after {} at tick {}: {}\npiece {} orientation {} at ({}, {}), {:?}\n{}",
+                after, self.tick, e, self.p, self.r, self.x, self.y, self.phase, self.board
+            );
+        }
+    }
+
+    #[cfg(not(feature = "debug-invariants"))]
+    fn check(&self, _: u32, _: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::GARBAGE;
+    use crate::position;
+    use crate::rules::Scoring;
+
+    const O: u8 = 2;
+    const I: u8 = 5;
+
+    // a game on the given stack, piece p in play, flat
+    fn game(cfg: &Config, stack: &str, p: u8) -> Game {
+        let mut g = Game::with_seed(cfg, 1);
+        g.set_board(position::parse(stack, cfg.width, cfg.height).unwrap());
+        g.set_queue(&[p]);
+        g
+    }
+
+    #[test]
+    fn the_bag_left_is_of_the_piece_in_play() {
+        for previews in [0, 3, 6] {
+            let cfg = Config {
+                randomizer: randomizer::Kind::Bag7,
+                previews,
+                ..Config::default()
+            };
+            let mut g = Game::with_seed(&cfg, 1);
+            let mut spawned = vec![g.p];
+            let mut left = vec![g.bag().unwrap()];
+            for _ in 0..30 {
+                g.new_tetramino();
+                spawned.push(g.p);
+                left.push(g.bag().unwrap());
+            }
+            for (i, left) in left.iter_mut().enumerate() {
+                let end = (i / 7 + 1) * 7;
+                let end = if end == i + 1 { end + 7 } else { end };
+                let mut want = spawned[i + 1..end.min(spawned.len())].to_vec();
+                if want.len() < end - i - 1 {
+                    continue; // runs past what was spawned
+                }
+                if left.is_empty() {
+                    *left = (0..7).collect(); // the next bag, whole
+                }
+                want.sort();
+                left.sort();
+                assert_eq!(*left, want, "previews {}, piece {}", previews, i);
+            }
+        }
+    }
+
+    fn run(g: &mut Game, n: u32) {
+        for _ in 0..n {
+            g.do_tick();
+        }
+    }
+
+    // the falling piece moved all the way left, then x columns right
+    fn shift(g: &mut Game, x: u8) {
+        for _ in 0..g.board.width() {
+            g.apply(Move::Left);
+        }
+        for _ in 0..x {
+            g.apply(Move::Right);
+        }
+    }
+
+    fn locked(g: &Game) -> bool {
+        g.events.iter().any(|e| matches!(e, Event::Lock(..)))
+    }
+
+    #[test]
+    fn gravity_goes_by_the_level() {
+        let cfg = Config::default(); // a row every 0.3s at level 1
+        let mut g = game(&cfg, "", I);
+        run(&mut g, 29);
+        assert_eq!(g.y, 0);
+        run(&mut g, 2);
+        assert_eq!(g.y, 1);
+    }
+
+    #[test]
+    fn gravity_falls_a_row_at_a_time() {
+        let cfg = Config {
+            level: 10,
+            height: 40,
+            ..Config::default()
+        };
+        let mut g = game(&cfg, "", I);
+        let mut y = g.y;
+        for _ in 0..90 {
+            g.do_tick();
+            assert!(g.y - y <= 1, "fell {} rows in one tick", g.y - y);
+            y = g.y;
+        }
+        let per_tick = g.timing().gravity * TICK.as_secs_f64();
+        assert_eq!(g.y as f64, (90.0 * per_tick).floor());
+    }
+
+    #[test]
+    fn locks_after_the_lock_delay() {
+        let cfg = Config::default();
+        let mut g = game(&cfg, "", O);
+        g.apply(Move::SonicDrop);
+        assert_eq!(g.y, 18);
+        run(&mut g, ticks(cfg.lock_delay) - 1);
+        assert!(!locked(&g));
+        let x = g.x;
+        g.do_tick();
+        assert!(g.events.contains(&Event::Lock(O, 0, x, 18)));
+    }
+
+    #[test]
+    fn moving_puts_off_locking() {
+        let cfg = Config::default();
+        let mut g = game(&cfg, "", O);
+        g.apply(Move::SonicDrop);
+        run(&mut g, ticks(cfg.lock_delay) - 1);
+        g.apply(Move::Left);
+        run(&mut g, ticks(cfg.lock_delay) - 1);
+        assert!(!locked(&g));
+    }
+
+    #[test]
+    fn hold_puts_the_piece_aside_once_a_piece() {
+        let cfg = Config::default();
+        let mut g = Game::with_seed(&cfg, 1);
+        g.set_queue(&[O, I]);
+        g.apply(Move::Hold);
+        assert_eq!((g.p, g.held), (I, Some((O, 0))));
+        g.apply(Move::Hold); // not again until the piece locks
+        assert_eq!((g.p, g.held), (I, Some((O, 0))));
+        g.apply(Move::HardDrop);
+        run(&mut g, 1);
+        let next = g.p;
+        assert_eq!(g.swap(), Some((O, 0)));
+        g.apply(Move::Hold);
+        assert_eq!((g.p, g.held.map(|(p, _)| p)), (O, Some(next)));
+    }
+
+    #[test]
+    fn a_tetris_clears_four_rows() {
+        let cfg = Config::default();
+        let well = "#########.\n".repeat(4) + "######...#";
+        let mut g = game(&cfg, &well, I);
+        g.apply(Move::Rotate); // upright
+        shift(&mut g, 9);
+        g.apply(Move::HardDrop);
+        assert_eq!(g.lines, 4);
+        assert_eq!(g.board.to_ascii().lines().last(), Some("######...#"));
+        let tetris = Clear {
+            lines: 4,
+            spin: Spin::None,
+            b2b: false,
+        };
+        assert!(g.events.contains(&Event::Clear(tetris)));
+        assert_eq!(g.sent, garbage::attack(4));
+    }
+
+    #[test]
+    fn the_clear_delay_holds_the_rows_up() {
+        let cfg = Config {
+            clear_delay: 100,
+            ..Config::default()
+        };
+        let mut g = game(&cfg, "........#.\n##..######", O);
+        shift(&mut g, 2);
+        g.apply(Move::HardDrop);
+        assert_eq!(g.phase, Phase::Clearing(vec![19], 10));
+        run(&mut g, 9);
+        assert_eq!(g.lines, 0);
+        g.do_tick();
+        assert_eq!(g.lines, 1);
+        assert_eq!(g.board.to_ascii().lines().last(), Some("..OO....#."));
+    }
+
+    // the score for clearing the given full rows, the chain, starting level
+    // and fever being as given
+    fn points(rows: &[u8], level: u64, chain: u32, fever: bool, scoring: Scoring) -> u32 {
+        let cfg = Config {
+            level,
+            scoring,
+            ..Config::default()
+        };
+        let mut g = Game::with_seed(&cfg, 1);
+        for &y in rows {
+            for x in 0..cfg.width {
+                g.board.set(x, y, GARBAGE);
+            }
+        }
+        g.chain = chain;
+        if fever {
+            g.fever = Fever::On(100);
+        }
+        g.clear_rows();
+        assert_eq!(g.lines, rows.len() as u32);
+        g.score
+    }
+
+    #[test]
+    fn rows_score_by_how_far_down_they_were() {
+        assert_eq!(points(&[19], 1, 0, false, Scoring::Unictris), 19);
+        assert_eq!(points(&[18, 19], 1, 1, false, Scoring::Unictris), 18 + 19);
+        assert_eq!(points(&[5, 19], 1, 1, false, Scoring::Unictris), 5 + 19);
+    }
+
+    #[test]
+    fn chains_levels_and_fever_multiply_the_score() {
+        assert_eq!(points(&[19], 1, 2, false, Scoring::Unictris), 2 * 19);
+        assert_eq!(points(&[19], 3, 1, false, Scoring::Unictris), 3 * 19);
+        let n = 2 * 3 * crate::fever::MULTIPLIER;
+        assert_eq!(points(&[19], 3, 2, true, Scoring::Unictris), n * 19);
+    }
+
+    #[test]
+    fn nes_scoring_goes_by_the_rows_at_once_and_the_level() {
+        assert_eq!(points(&[19], 1, 1, false, Scoring::Nes), 40);
+        assert_eq!(points(&[16, 17, 18, 19], 1, 1, false, Scoring::Nes), 1200);
+        // neither the chain nor a fever counts
+        assert_eq!(points(&[16, 17, 18, 19], 2, 3, true, Scoring::Nes), 2400);
+    }
+
+    #[test]
+    fn survival_scores_no_points_for_rows() {
+        let cfg = Config {
+            mode: Mode::Survival,
+            ..Config::default()
+        };
+        let mut g = Game::with_seed(&cfg, 1);
+        for x in 0..cfg.width {
+            g.board.set(x, 19, GARBAGE);
+        }
+        g.clear_rows();
+        assert_eq!((g.lines, g.score), (1, 0));
+    }
+
+    #[test]
+    fn locking_in_the_top_row_ends_the_game() {
+        let cfg = Config::default();
+        let stack = "#.#.#.#.#.\n".repeat(18);
+        let mut g = game(&cfg, &stack, O);
+        assert!(!g.over);
+        let x = g.x;
+        g.apply(Move::HardDrop);
+        assert_eq!(g.events.first(), Some(&Event::Lock(O, 0, x, 0)));
+        assert!(g.over);
+    }
+
+    #[test]
+    fn locking_below_the_top_row_goes_on() {
+        let cfg = Config::default();
+        let stack = "#.#.#.#.#.\n".repeat(17);
+        let mut g = game(&cfg, &stack, O);
+        let x = g.x;
+        g.apply(Move::HardDrop);
+        assert_eq!(g.events.first(), Some(&Event::Lock(O, 0, x, 1)));
+        assert!(!g.over && g.phase == Phase::Falling);
+    }
+
+    // the pieces a game deals, dropping each as it comes
+    fn dealt(mut g: Game, n: usize) -> Vec<u8> {
+        (0..n)
+            .map(|_| {
+                let p = g.p;
+                g.apply(Move::HardDrop);
+                run(&mut g, 100);
+                p
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_seed_or_an_rng_given_deals_the_same_game() {
+        let cfg = Config::default();
+        let a = dealt(Game::with_seed(&cfg, 5), 8);
+        assert_eq!(a, dealt(Game::with_seed(&cfg, 5), 8));
+        assert_ne!(a, dealt(Game::with_seed(&cfg, 6), 8));
+        let fixed = || Box::new(rand::rngs::mock::StepRng::new(0, 1 << 29));
+        let b = dealt(Game::with_rng(&cfg, fixed()), 8);
+        assert_eq!(b, dealt(Game::with_rng(&cfg, fixed()), 8));
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    #[should_panic(expected = "the score went down from 11 to 10")]
+    fn the_score_going_down_is_caught() {
+        let mut g = Game::with_seed(&Config::default(), 1);
+        g.score = 10;
+        g.check(11, "test");
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    #[should_panic(expected = "the falling piece overlaps the stack")]
+    fn a_piece_in_the_stack_is_caught() {
+        let cfg = Config::default();
+        let mut g = game(&cfg, "", O);
+        for (x, y) in (0..cfg.width).flat_map(|x| (0..4).map(move |y| (x, y))) {
+            g.board.set(x, y, GARBAGE); // under the piece, wherever in its box it is
+        }
+        g.apply(Move::Rotate);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+// Garbage - the rows players send each other in versus play.
+// Clearing lines attacks; attacks first cancel garbage on its way in,
+// and whatever arrives rises from the bottom once its delay has run out
+// and the player locks a piece without clearing anything.
+
+pub const DELAY: u32 = 100; // ticks before sent garbage can rise - a second
+
+// lines sent for clearing n rows with one piece
+pub fn attack(n: u32) -> u32 {
+    match n {
+        0 | 1 => 0,
+        2 => 1,
+        3 => 2,
+        n => n,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Batch {
+    pub lines: u32,
+    pub delay: u32, // ticks until it can rise
+}
+
+#[derive(Default)]
+pub struct Garbage {
+    pub queue: VecDeque<Batch>,
+}
+
+impl Garbage {
+    pub fn push(&mut self, lines: u32) {
+        if lines > 0 {
+            self.queue.push_back(Batch {
+                lines,
+                delay: DELAY,
+            });
+        }
+    }
+
+    // lines on their way in
+    pub fn pending(&self) -> u32 {
+        self.queue.iter().map(|b| b.lines).sum()
+    }
+
+    // counter incoming garbage with n lines of attack; returns what is left to send
+    pub fn cancel(&mut self, mut n: u32) -> u32 {
+        while n > 0 {
+            let Some(b) = self.queue.front_mut() else {
+                break;
+            };
+            let c = n.min(b.lines);
+            b.lines -= c;
+            n -= c;
+            if b.lines == 0 {
+                self.queue.pop_front();
+            }
+        }
+        n
+    }
+
+    pub fn tick(&mut self) {
+        for b in &mut self.queue {
+            b.delay = b.delay.saturating_sub(1);
+        }
+    }
+
+    // take the batches whose delay has run out
+    pub fn ready(&mut self) -> Vec<u32> {
+        let n = self.queue.iter().take_while(|b| b.delay == 0).count();
+        self.queue.drain(..n).map(|b| b.lines).collect()
+    }
+}
@@ -0,0 +1,100 @@
+use crate::broadcast;
+use crate::cast::escape;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tetrisct::game::TICK;
+use tetrisct::position;
+use tetrisct::view::View;
+
+// The game for whatever would rather ask than read the screen - dashboards,
+// test harnesses: --serve answers GET /state with the board as --broadcast
+// sends it, and GET /stats with the numbers, both JSON and both as of the
+// last frame drawn. Read-only; nothing sent here reaches the game.
+
+static LATEST: OnceLock<Mutex<Option<View>>> = OnceLock::new();
+
+// listen on addr, "host:port" or ":port" for this machine only
+pub fn start(addr: &str) -> io::Result<()> {
+    let addr = match addr.strip_prefix(':') {
+        Some(port) => format!("127.0.0.1:{}", port),
+        None => addr.to_string(),
+    };
+    let listener = TcpListener::bind(&addr)
+        .map_err(|e| io::Error::new(e.kind(), format!("--serve {}: {}", addr, e)))?;
+    LATEST.set(Mutex::new(None)).ok();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || answer(stream));
+        }
+    });
+    Ok(())
+}
+
+// keep v for the next request
+pub fn publish(v: &View) {
+    if let Some(latest) = LATEST.get() {
+        *latest.lock().unwrap() = Some(v.clone());
+    }
+}
+
+// one request, then the connection is closed
+fn answer(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader.read_line(&mut request)?;
+    // the headers, which tell nothing wanted here
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        line.clear();
+    }
+    let view = LATEST.get().and_then(|l| l.lock().unwrap().clone());
+    let (status, body) = match (request.split_whitespace().nth(1), &view) {
+        (Some("/state"), Some(v)) => ("200 OK", broadcast::state(v)),
+        (Some("/stats"), Some(v)) => ("200 OK", stats(v)),
+        (Some("/state" | "/stats"), None) => ("503 Service Unavailable", "{}".to_string()),
+        _ => ("404 Not Found", "{}".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+// v's numbers as JSON: the time is game time, tick by tick
+fn stats(v: &View) -> String {
+    let seconds = v.tick as f64 * TICK.as_secs_f64();
+    let pieces: u32 = v.dealt.iter().sum();
+    let per = |n: u32, unit: f64| {
+        if seconds > 0.0 {
+            n as f64 * unit / seconds
+        } else {
+            0.0
+        }
+    };
+    let dealt: Vec<String> = (v.dealt.iter().enumerate())
+        .map(|(p, n)| format!("\"{}\":{}", escape(&position::letters(&[p as u8])), n))
+        .collect();
+    format!(
+        "{{\"mode\":\"{}\",\"tick\":{},\"seconds\":{:.2},\"score\":{},\"level\":{},\"lines\":{},\"pieces\":{},\"pps\":{:.2},\"lpm\":{:.2},\"combo\":{},\"drought\":{},\"dealt\":{{{}}},\"paused\":{},\"over\":{}}}",
+        format!("{:?}", v.mode).to_lowercase(),
+        v.tick,
+        seconds,
+        v.score,
+        v.level,
+        v.lines,
+        pieces,
+        per(pieces, 1.0),
+        per(v.lines, 60.0),
+        v.combo,
+        v.drought,
+        dealt.join(","),
+        v.paused,
+        v.over
+    )
+}
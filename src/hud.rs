@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+// What the sidebar shows, one line per widget in the order configured.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Widget {
+    Score,
+    Level,
+    Time,     // time played
+    Split,    // the latest split time in sprint, against the personal best
+    Ghost,    // how far the personal best had got by now, in sprint
+    Item,     // the item in hand, in party play
+    Fever,    // the fever gauge, with fever on
+    Shape,    // the falling piece and its orientation
+    Previews, // the next pieces
+    Hold,     // the piece put aside
+    Stats,    // the I-piece drought
+    Combo,    // pieces in a row that cleared lines, and difficult clears back to back
+    Heights,  // how high the stack is in each column
+    Bag,      // the pieces left in the current bag, with a bag randomizer
+    Blank,    // an empty line, for spacing
+}
+
+impl Widget {
+    // sidebar rows it takes - the pieces are drawn two cells to a row
+    pub fn rows(self) -> u16 {
+        match self {
+            Widget::Shape | Widget::Previews | Widget::Hold | Widget::Heights => 2,
+            _ => 1,
+        }
+    }
+}
+
+pub const DEFAULT: [Widget; 9] = [
+    Widget::Score,
+    Widget::Level,
+    Widget::Split,
+    Widget::Ghost,
+    Widget::Item,
+    Widget::Fever,
+    Widget::Shape,
+    Widget::Previews,
+    Widget::Stats,
+];
+
+// which side of the board the sidebar goes; in versus play, where the player's board is
+// decides it - the opponent's goes on the other side
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
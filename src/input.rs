@@ -0,0 +1,213 @@
+use crate::ai::{Bot, Difficulty, Style};
+use crate::game::{Game, Move, MOVES};
+use crate::keymap::{Action, Keymap};
+use crossterm::event::KeyEvent;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::Receiver;
+
+// Where a game's inputs come from: the keyboard, the moves of a replay, an
+// audience voting over a socket, or the bot. The loop driving a game hands
+// the source each key pressed and takes what is due until it says there is
+// nothing more for now, so it plays the same whoever is at the controls.
+
+pub trait InputSource {
+    // the next input due in g, None when there is none for now
+    fn next(&mut self, g: &Game) -> Option<Action>;
+
+    // a key pressed at the terminal - those not at the keyboard ignore it
+    fn key(&mut self, _key: &KeyEvent) {}
+
+    // the tick it next has an input for without a key being pressed, if
+    // it knows - for a loop not to sleep through it
+    fn due(&self) -> Option<u64> {
+        None
+    }
+}
+
+// the player at the keyboard, through their key bindings
+pub struct Keyboard<'a> {
+    keymap: &'a Keymap,
+    pressed: VecDeque<Action>,
+}
+
+impl<'a> Keyboard<'a> {
+    pub fn new(keymap: &'a Keymap) -> Keyboard<'a> {
+        Keyboard {
+            keymap,
+            pressed: VecDeque::new(),
+        }
+    }
+}
+
+impl InputSource for Keyboard<'_> {
+    fn next(&mut self, _: &Game) -> Option<Action> {
+        self.pressed.pop_front()
+    }
+
+    fn key(&mut self, key: &KeyEvent) {
+        self.pressed.extend(self.keymap.action(key));
+    }
+}
+
+// moves set down in advance, each made at its tick - as in a replay
+pub struct Script<'a> {
+    moves: &'a [(u64, Move)],
+    next: usize, // index of the next move to make
+}
+
+impl<'a> Script<'a> {
+    pub fn new(moves: &'a [(u64, Move)]) -> Script<'a> {
+        Script { moves, next: 0 }
+    }
+}
+
+impl InputSource for Script<'_> {
+    fn next(&mut self, g: &Game) -> Option<Action> {
+        let &(tick, m) = self.moves.get(self.next)?;
+        if tick > g.tick {
+            return None;
+        }
+        self.next += 1;
+        Some(Action::Move(m))
+    }
+
+    fn due(&self) -> Option<u64> {
+        self.moves.get(self.next).map(|&(tick, _)| tick)
+    }
+}
+
+// the bot, making a move a tick at most
+pub struct Ai {
+    pub bot: Bot,
+    last: Option<u64>, // the tick it last made up its mind
+}
+
+impl Ai {
+    pub fn new(difficulty: Difficulty, style: Style) -> Ai {
+        Ai {
+            bot: Bot::new(difficulty, style),
+            last: None,
+        }
+    }
+}
+
+impl InputSource for Ai {
+    fn next(&mut self, g: &Game) -> Option<Action> {
+        if self.last == Some(g.tick) {
+            return None;
+        }
+        self.last = Some(g.tick);
+        self.bot.act(g).map(Action::Move)
+    }
+}
+
+// ticks a crowd's vote stays open
+pub const VOTE_TICKS: u64 = 50;
+
+// an audience playing together, as a chat bot passes on what its viewers
+// type: (voter, move) pairs come in, each voter has one vote a round - a
+// later one replaces theirs - and at the end of each VOTE_TICKS round the
+// move with the most votes is made
+pub struct Crowd {
+    votes: Receiver<(String, Move)>,
+    round: BTreeMap<String, Move>, // this round's votes, by voter
+    ends: u64,                     // tick the round closes
+}
+
+impl Crowd {
+    pub fn new(votes: Receiver<(String, Move)>) -> Crowd {
+        Crowd {
+            votes,
+            round: BTreeMap::new(),
+            ends: VOTE_TICKS,
+        }
+    }
+
+    // the votes cast so far this round, for each move that has any
+    pub fn tally(&self) -> Vec<(Move, u32)> {
+        MOVES
+            .iter()
+            .map(|&m| (m, self.round.values().filter(|&&v| v == m).count() as u32))
+            .filter(|&(_, n)| n > 0)
+            .collect()
+    }
+}
+
+impl InputSource for Crowd {
+    fn next(&mut self, g: &Game) -> Option<Action> {
+        self.round.extend(self.votes.try_iter());
+        if g.tick + VOTE_TICKS < self.ends {
+            self.ends = g.tick + VOTE_TICKS; // a new game, or one rewound
+        }
+        if g.tick < self.ends {
+            return None;
+        }
+        self.ends = g.tick + VOTE_TICKS;
+        // ties go to the move first in MOVES
+        let winner = self
+            .tally()
+            .into_iter()
+            .rev()
+            .max_by_key(|&(_, n)| n)
+            .map(|(m, _)| m);
+        self.round.clear();
+        winner.map(Action::Move)
+    }
+    fn due(&self) -> Option<u64> {
+        Some(self.ends) // the round closing
+    }
+}
+
+// two sources at once - the keyboard alongside another, say
+pub struct Both<A, B>(pub A, pub B);
+
+impl<A: InputSource, B: InputSource> InputSource for Both<A, B> {
+    fn next(&mut self, g: &Game) -> Option<Action> {
+        self.0.next(g).or_else(|| self.1.next(g))
+    }
+
+    fn key(&mut self, key: &KeyEvent) {
+        self.0.key(key);
+        self.1.key(key);
+    }
+
+    fn due(&self) -> Option<u64> {
+        [self.0.due(), self.1.due()].into_iter().flatten().min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::sync::mpsc;
+
+    #[test]
+    fn a_script_makes_each_move_at_its_tick() {
+        let moves = [(0, Move::Left), (3, Move::Rotate), (3, Move::HardDrop)];
+        let mut s = Script::new(&moves);
+        let mut g = Game::with_seed(&Config::default(), 1);
+        assert_eq!(s.next(&g), Some(Action::Move(Move::Left)));
+        assert_eq!((s.next(&g), s.due()), (None, Some(3)));
+        g.tick = 3;
+        assert_eq!(s.next(&g), Some(Action::Move(Move::Rotate)));
+        assert_eq!(s.next(&g), Some(Action::Move(Move::HardDrop)));
+        assert_eq!((s.next(&g), s.due()), (None, None));
+    }
+
+    #[test]
+    fn a_crowd_makes_the_move_most_voted_for_a_round() {
+        let (tx, rx) = mpsc::channel();
+        let mut c = Crowd::new(rx);
+        let mut g = Game::with_seed(&Config::default(), 1);
+        for (voter, m) in [("a", Move::Left), ("b", Move::Right), ("c", Move::Right)] {
+            tx.send((voter.to_string(), m)).unwrap();
+        }
+        tx.send(("a".to_string(), Move::Rotate)).unwrap(); // a changes their mind
+        assert_eq!(c.next(&g), None);
+        assert_eq!(c.due(), Some(VOTE_TICKS));
+        g.tick = VOTE_TICKS;
+        assert_eq!(c.next(&g), Some(Action::Move(Move::Right)));
+        assert!(c.tally().is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+// Items for party play: some incoming garbage rows carry an item block, and
+// clearing such a row earns an item, kept until used with the item key.
+
+pub const CHANCE: u32 = 3; // one garbage batch in CHANCE carries an item block
+pub const CLEAR_ROWS: u8 = 2; // rows ClearBottom takes away
+pub const SLOW_TICKS: u32 = 1000; // how long SlowGravity lasts - ten seconds
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Item {
+    ClearBottom, // remove the bottom rows of your own stack
+    Scramble,    // shuffle the opponent's rows when their piece locks
+    SlowGravity, // half gravity for a while
+}
+
+pub const ITEMS: [Item; 3] = [Item::ClearBottom, Item::Scramble, Item::SlowGravity];
+
+impl Item {
+    pub fn name(self) -> &'static str {
+        match self {
+            Item::ClearBottom => "Clear",
+            Item::Scramble => "Scramble",
+            Item::SlowGravity => "Slow",
+        }
+    }
+}
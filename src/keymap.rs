@@ -0,0 +1,125 @@
+use crate::game::Move;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::collections::BTreeMap;
+
+// What a key press asks for - a move in the game, or something about the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Move(Move),
+    Pause,
+    Quit,
+    Step,      // one tick on, in frame advance
+    Rewind,    // back a few seconds, in single-player games
+    Autopilot, // the bot takes over playing
+    TakeOver,  // ... and hands back
+}
+
+// names used for actions in the [keys] table of the config file
+const ACTIONS: [(&str, Action); 13] = [
+    ("left", Action::Move(Move::Left)),
+    ("right", Action::Move(Move::Right)),
+    ("rotate", Action::Move(Move::Rotate)),
+    ("hard_drop", Action::Move(Move::HardDrop)),
+    ("sonic_drop", Action::Move(Move::SonicDrop)),
+    ("item", Action::Move(Move::Item)),
+    ("hold", Action::Move(Move::Hold)),
+    ("pause", Action::Pause),
+    ("quit", Action::Quit),
+    ("step", Action::Step),
+    ("rewind", Action::Rewind),
+    ("autopilot", Action::Autopilot),
+    ("take_over", Action::TakeOver),
+];
+
+pub fn action(name: &str) -> Option<Action> {
+    ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+}
+
+// "left", "space", "f1", "x", ...
+pub fn key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match name.to_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "space" => KeyCode::Char(' '),
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "insert" => KeyCode::Insert,
+        "delete" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        f => KeyCode::F(f.strip_prefix('f')?.parse().ok()?),
+    };
+    Some(code)
+}
+
+pub struct Keymap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: vec![
+                (KeyCode::Left, Action::Move(Move::Left)),
+                (KeyCode::Right, Action::Move(Move::Right)),
+                (KeyCode::Up, Action::Move(Move::Rotate)),
+                (KeyCode::Down, Action::Move(Move::HardDrop)),
+                (KeyCode::Char(' '), Action::Move(Move::HardDrop)),
+                (KeyCode::Char('s'), Action::Move(Move::SonicDrop)),
+                (KeyCode::Char('i'), Action::Move(Move::Item)),
+                (KeyCode::Char('c'), Action::Move(Move::Hold)),
+                (KeyCode::Esc, Action::Pause),
+                (KeyCode::Char('p'), Action::Pause),
+                (KeyCode::Char('q'), Action::Quit),
+                (KeyCode::Char('.'), Action::Step),
+                (KeyCode::Backspace, Action::Rewind),
+                (KeyCode::Char('a'), Action::Autopilot),
+                (KeyCode::Char('t'), Action::TakeOver),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    // the defaults, with every action named in keys bound to exactly the keys listed
+    pub fn new(keys: &BTreeMap<String, Vec<String>>) -> Result<Keymap, String> {
+        let mut km = Keymap::default();
+        for (name, list) in keys {
+            let a = action(name).ok_or_else(|| format!("keys: unknown action '{}'", name))?;
+            km.unbind(a);
+            for k in list {
+                let code = key(k).ok_or_else(|| format!("keys.{}: unknown key '{}'", name, k))?;
+                km.bind(code, a);
+            }
+        }
+        Ok(km)
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key.code)
+            .map(|(_, a)| *a)
+    }
+
+    // bind key to a, replacing whatever it did before
+    pub fn bind(&mut self, key: KeyCode, a: Action) {
+        self.bindings.retain(|(k, _)| *k != key);
+        self.bindings.push((key, a));
+    }
+
+    // drop every binding for a
+    pub fn unbind(&mut self, a: Action) {
+        self.bindings.retain(|(_, b)| *b != a);
+    }
+}
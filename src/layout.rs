@@ -0,0 +1,151 @@
+use crossterm::terminal;
+use tetrisct::config::Config;
+use tetrisct::hud::Side;
+use tetrisct::palette::Theme;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Where things go on screen, worked out from the terminal size: the board
+// with the sidebar on the configured side and, in versus play, the
+// opponent's board on the sidebar's other side - or, against more than
+// one, their boards side by side there as mini boards, a cell a column
+// and two rows a line - centered when there is room to spare. In teams the
+// four boards sit two by two, the player's allies' minis by the player's
+// board and the sidebar between the two teams. Sidebar
+// panels that don't fit are left out, the title first, then the details.
+// On a terminal shorter than the board, only as many board rows as fit are
+// shown, scrolling to follow the falling piece.
+
+const SIDEBAR: u16 = 35; // widest the sidebar gets
+const TITLE: u16 = 34; // sidebar width the title needs
+const DETAILS: u16 = 16; // ... and the shape and drought lines
+const MIN_ROWS: u16 = 8; // fewest board rows worth playing with
+
+#[derive(Clone)]
+pub struct Layout {
+    pub x: u16,            // left edge of the player's board box
+    pub board: (u16, u16), // size of the box around a board
+    pub rows: u8,          // board rows shown, the bottom ones or around the falling piece
+    pub left: u16,         // sidebar columns, left..right
+    pub right: u16,
+    pub opponents: Vec<u16>, // left edge of each opponent's board box in versus play
+    pub mini: (u16, u16),    // ... and a mini board's box, as theirs are against more than one
+    pub meter: u16,          // column of the incoming garbage meter
+    pub hud: u16,            // first row of the sidebar widgets
+    pub status: u16,         // row for Paused/Game Over, with the popup and menus below
+    pub title: bool,         // panels shown in the sidebar, besides score, level and queue
+    pub details: bool,
+    pub ascii: bool, // boxes drawn in ASCII, for the ASCII theme
+}
+
+// the size of the box around a mini board of cfg's
+fn mini(cfg: &Config) -> (u16, u16) {
+    (cfg.width as u16 + 1, cfg.height.div_ceil(2) as u16 + 1)
+}
+
+// columns the opponents' n boards take, side by side - mini boards for more than one
+fn others(cfg: &Config, n: usize) -> u16 {
+    match n {
+        0 => 0,
+        1 => 2 * cfg.width as u16 + 2,
+        n => n as u16 * (mini(cfg).0 + 2) - 1,
+    }
+}
+
+impl Layout {
+    // the least room a cfg board takes, box and garbage meter, with n
+    // opponents' beside it, in columns and rows
+    pub fn needs(cfg: &Config, n: usize) -> (u16, u16) {
+        let rows = (cfg.height as u16).min(MIN_ROWS) + 2;
+        let minis = if n > 1 { mini(cfg).1 + 2 } else { 0 }; // and the names under them
+        (
+            2 * cfg.width as u16 + 2 + others(cfg, n) + 2,
+            rows.max(minis),
+        )
+    }
+
+    // for a cfg board, and n opponents' in versus play, the first allies of
+    // them on the player's team
+    pub fn new(cfg: &Config, n: usize, allies: usize) -> Layout {
+        let (cols, lines) = terminal::size().unwrap_or((80, 24));
+        let rows = (cfg.height as u16).min(lines.saturating_sub(2).max(MIN_ROWS));
+        let board = (2 * cfg.width as u16 + 1, rows + 1);
+        let other = others(cfg, n);
+        let spare = cols.saturating_sub(board.0 + 1 + other + 2);
+        let sidebar = spare.min(SIDEBAR);
+        let x0 = (spare - sidebar) / 2;
+        let mini = mini(cfg);
+        let step = mini.0 + 2;
+        let team = allies as u16 * step; // the allies' minis, beside the player's board
+        let (x, left, ours, theirs) = match (cfg.sidebar, n > 0) {
+            (Side::Right, false) => (x0, x0 + board.0 + 2, 0, 0),
+            (Side::Left, false) => (x0 + sidebar + 1, x0, 0, 0),
+            (Side::Right, true) => {
+                let at = x0 + board.0 + 2;
+                (x0, at + team, at, at + team + sidebar)
+            }
+            (Side::Left, true) => {
+                let left = x0 + other - team + 1;
+                (left + sidebar + team, left, left + sidebar, x0)
+            }
+        };
+        let at = |i: u16| match i < allies as u16 {
+            true => ours + i * step,
+            false => theirs + (i - allies as u16) * step,
+        };
+        Layout {
+            x,
+            board,
+            rows: rows as u8,
+            left,
+            right: left + sidebar,
+            opponents: (0..n as u16).map(at).collect(),
+            mini,
+            meter: x + board.0 + 1,
+            hud: 5,
+            status: 5 + cfg.hud.iter().map(|w| w.rows()).sum::<u16>(),
+            title: sidebar >= TITLE,
+            details: sidebar >= DETAILS,
+            ascii: cfg.theme == Theme::Ascii,
+        }
+    }
+
+    // everything drawn dx columns over, as the screen shakes
+    pub fn offset(&self, dx: i16) -> Layout {
+        let by = |x: u16| x.saturating_add_signed(dx);
+        Layout {
+            x: by(self.x),
+            left: by(self.left),
+            right: by(self.right),
+            opponents: self.opponents.iter().map(|&x| by(x)).collect(),
+            meter: by(self.meter),
+            ..self.clone()
+        }
+    }
+
+    // column to start s at for it to be centered in the sidebar
+    pub fn centered_x(&self, s: &str) -> u16 {
+        self.left + (self.right - self.left).saturating_sub(width(s)) / 2
+    }
+}
+
+// columns s takes on screen - two for wide glyphs, none for combining marks -
+// which is what to go by, not its length, when lining text up
+pub fn width(s: &str) -> u16 {
+    s.width() as u16
+}
+
+// as much of s as fits in n columns
+pub fn fit(s: &str, n: u16) -> String {
+    let mut used = 0;
+    s.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0) as u16;
+            used <= n
+        })
+        .collect()
+}
+
+// s with spaces after it to fill n columns
+pub fn pad(s: &str, n: u16) -> String {
+    format!("{}{}", s, " ".repeat(n.saturating_sub(width(s)) as usize))
+}
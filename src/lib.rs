@@ -0,0 +1,39 @@
+pub mod ai;
+pub mod analysis;
+pub mod archive;
+pub mod autosave;
+pub mod best;
+pub mod board;
+pub mod campaign;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod clock;
+pub mod config;
+pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod fever;
+pub mod frame;
+pub mod fumen;
+pub mod game;
+pub mod garbage;
+pub mod hud;
+pub mod input;
+pub mod items;
+pub mod keymap;
+pub mod mode;
+pub mod pack;
+pub mod palette;
+pub mod pieces;
+pub mod position;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod randomizer;
+pub mod replay;
+pub mod rules;
+pub mod session;
+pub mod shape;
+pub mod speed;
+pub mod stats;
+pub mod versus;
+pub mod view;
@@ -1,347 +1,1877 @@
 use crossterm::{
     cursor,
-    event::{poll, read, Event, KeyCode, KeyEvent},
-    style::{self, Stylize},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    style::{self, Color, Stylize},
     terminal, QueueableCommand, Result,
 };
-use rand::prelude::*;
-use std::io::{stdout, Write};
-use std::time;
-
-// Tetrominos - packed into 7 64 bit numbers.
-// Each tetromino is 4 squares - needs 4*(2+2)=16 bits to describe.
-// Hence 448 bits in total: 7 tetrominos * 4 orientations * 16 bits.
-static BLOCK: [u64; 7] = [
-    0x2154_9540_2154_9540,
-    0x6510_8451_6510_8451,
-    0x5140_5140_5140_5140,
-    0x9840_2140_9510_2654,
-    0x1654_5840_5210_4951,
-    0x3210_c840_3210_c840,
-    0x8951_6540_1840_6210,
-];
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tetrisct::ai::{self, Difficulty, Style};
+use tetrisct::analysis;
+use tetrisct::archive;
+use tetrisct::autosave;
+use tetrisct::best::Best;
+use tetrisct::board::{self, Board};
+use tetrisct::campaign::{Profile, Reward};
+use tetrisct::clock::{self, Ticker, Wall};
+use tetrisct::config::{self, Config};
+use tetrisct::error::Error;
+use tetrisct::fever::{self, Fever};
+use tetrisct::fumen::{self, Puzzle};
+use tetrisct::game::{Event as GameEvent, Game, Phase, TICK};
+use tetrisct::hud::Widget;
+use tetrisct::input::{Ai, Both, Crowd, InputSource, Keyboard};
+use tetrisct::keymap::Action;
+use tetrisct::mode::{self, Mode};
+use tetrisct::pack::{self, Pack, Progress};
+use tetrisct::palette::{Stack, Theme};
+use tetrisct::replay::Replay;
+use tetrisct::session::Session;
+use tetrisct::versus::{Setup, Target, Versus, MAX_OPPONENTS};
+use tetrisct::view::View;
+use tetrisct::{garbage, palette, pieces, position, stats};
 
-const TICK_LEVEL: u64 = 6000;
+mod broadcast;
+mod cast;
+mod cli;
+mod clipboard;
+mod commands;
+mod editor;
+mod events;
+#[cfg(feature = "http")]
+mod http;
+mod layout;
+mod mux;
+mod particles;
+mod profile;
+mod share;
 
-struct Game {
-    x: u8, // coor
-    y: u8,
-    r: u8,  // orientation
-    px: u8, // old coor
-    py: u8,
-    pr: u8,
-    p: u8, // tetromino
-    tick: u64,
-    score: u32,
-    board: [[u8; 10]; 20], // 20 rows x 10 cols
-    paused: bool,
+use cast::stdout; // the terminal, recorded with --record-cast
+use events::Wake;
+use layout::Layout;
+use particles::Particles;
+use profile::Stage;
+
+// wipe a row of the sidebar
+fn blank(lay: &Layout, row: u16) -> Result<()> {
+    let s = " ".repeat((lay.right - lay.left) as usize);
+    crossterm::queue!(stdout(), cursor::MoveTo(lay.left, row), style::Print(s))
 }
 
-// extract a bit packed number from a block
-fn num(p: u8, r: u8, i: u8) -> u8 {
-    (3 & BLOCK[p as usize] >> (r * 16 + i)) as u8
+// ticks as m:ss.cc
+fn clock(ticks: u64) -> String {
+    let cs = ticks * TICK.as_millis() as u64 / 10;
+    format!("{}:{:02}.{:02}", cs / 6000, cs / 100 % 60, cs % 100)
 }
 
-// calculate width-1 for tetromino
-fn width(p: u8, r: u8) -> u8 {
-    let p = (0..4).map(|i| num(p, r, i * 4 + 2)).fold((0, 9), |m, v| {
-        (std::cmp::max(m.0, v), std::cmp::min(m.1, v))
-    });
-    p.0 - p.1
+const DROUGHT: u32 = 13; // pieces without an I before the drought counter turns red
+
+fn render_game_info(g: &View, lay: &Layout) {
+    let s1: &str = "Unictris - Unicode-powered Tetris";
+    let s2 = "Rusty Glyph Edition 2023 ";
+
+    if lay.title {
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(lay.centered_x(s1), 2),
+            style::PrintStyledContent(s1.cyan()),
+            cursor::MoveTo(lay.centered_x(s2), 3),
+            style::PrintStyledContent(s2.yellow()),
+        )
+        .ok();
+    }
+    if let Some((goal, pieces)) = g.goal {
+        // the pieces left, counting the one in play
+        let left = (pieces + !g.over as u32).saturating_sub(g.dealt.iter().sum());
+        let s = format!(
+            "Goal: {}, {} piece{} left",
+            goal,
+            left,
+            if left == 1 { "" } else { "s" }
+        );
+        let s = layout::fit(&s, lay.right - lay.left);
+        blank(lay, 3).ok();
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(lay.centered_x(&s), 3),
+            style::PrintStyledContent(s.bold().yellow()),
+        )
+        .ok();
+    }
+
+    let i = lay.centered_x("Score : 123456"); /* get a pos base on av score digits */
+    let mut row = lay.hud;
+    for &w in &g.hud {
+        for k in 0..w.rows() {
+            blank(lay, row + k).ok();
+        }
+        let s = match w {
+            Widget::Score => Some(format!("Score : {}", g.score).bold().white()),
+            Widget::Level => Some(format!("Level : {}", g.level).bold().white()),
+            Widget::Time => {
+                let t = match g.mode {
+                    Mode::Ultra => mode::ULTRA_TICKS.saturating_sub(g.tick), // left
+                    _ => g.tick,
+                };
+                let secs = t * TICK.as_millis() as u64 / 1000;
+                Some(
+                    format!("Time  : {}:{:02}", secs / 60, secs % 60)
+                        .bold()
+                        .white(),
+                )
+            }
+            Widget::Split if g.mode == Mode::Sprint => Some(match g.splits.last() {
+                None => "Split : -".to_string().bold().white(),
+                Some(&t) => {
+                    let n = g.splits.len();
+                    let s = format!("Split : {} {}", n as u32 * mode::SPLIT, clock(t));
+                    match g.pace.get(n - 1) {
+                        Some(&p) if lay.details && t <= p => {
+                            format!("{} -{}", s, clock(p - t)).bold().green()
+                        }
+                        Some(&p) if lay.details => format!("{} +{}", s, clock(t - p)).bold().red(),
+                        _ => s.bold().white(),
+                    }
+                }
+            }),
+            Widget::Ghost if g.mode == Mode::Sprint && !g.ghost.is_empty() => {
+                // the best's progress as a bar a cell to every four lines, green while ahead of it
+                let n = Best::lines_at(&g.ghost, g.tick);
+                let cells = (n * 10 / mode::SPRINT_LINES) as usize;
+                let s = format!(
+                    "PB    : {}{} {}",
+                    "▰".repeat(cells),
+                    "▱".repeat(10 - cells),
+                    n
+                );
+                Some(if g.lines >= n {
+                    s.bold().green()
+                } else {
+                    s.bold().red()
+                })
+            }
+            Widget::Item => g
+                .item
+                .map(|item| format!("Item  : {}", item.name()).bold().magenta()),
+            Widget::Fever if g.fevers => Some(match g.fever {
+                Fever::On(n) => format!("FEVER x{} : {}s", fever::MULTIPLIER, n.div_ceil(100))
+                    .bold()
+                    .yellow(),
+                Fever::Charging { gauge, .. } => {
+                    let n = (gauge * 10 / fever::FULL) as usize;
+                    format!("Fever : {}{}", "■".repeat(n), "□".repeat(10 - n))
+                        .bold()
+                        .white()
+                }
+            }),
+            Widget::Shape if lay.details => {
+                draw_pieces(g, "Shape : ", &[(g.p, g.r)], (i, row), lay).ok();
+                None
+            }
+            Widget::Previews if !g.next.is_empty() => {
+                draw_pieces(g, "Next  : ", &g.next, (i, row), lay).ok();
+                None
+            }
+            Widget::Hold => {
+                draw_hold(g, (i, row), lay).ok();
+                None
+            }
+            Widget::Stats if lay.details => {
+                let drought = format!("Drought : {}", g.drought);
+                Some(if g.drought > DROUGHT {
+                    drought.bold().red()
+                } else {
+                    drought.bold().white()
+                })
+            }
+            Widget::Combo => {
+                draw_streaks(g, (i, row)).ok();
+                None
+            }
+            Widget::Bag => {
+                if let Some(left) = &g.bag {
+                    draw_bag(g, left, (i, row)).ok();
+                }
+                None
+            }
+            Widget::Heights if lay.details => {
+                let heights: Vec<u32> = g.heights.iter().map(|&h| h as u32).collect();
+                let bars = if lay.ascii {
+                    &stats::ASCII_BARS
+                } else {
+                    &stats::BARS
+                };
+                let chart = stats::chart(&heights, g.height as u32, heights.len(), 2, bars);
+                let label = "Stack : ";
+                for (k, line) in chart.iter().enumerate() {
+                    let margin = if k == 0 { label } else { "        " };
+                    let s = format!("{}{}", margin, line);
+                    crossterm::queue!(
+                        stdout(),
+                        cursor::MoveTo(i, row + k as u16),
+                        style::PrintStyledContent(s.bold().white())
+                    )
+                    .ok();
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(s) = s {
+            crossterm::queue!(
+                stdout(),
+                cursor::MoveTo(i, row),
+                style::PrintStyledContent(s)
+            )
+            .ok();
+        }
+        row += w.rows();
+    }
 }
 
-// calculate height-1 for tetromino
-fn height(p: u8, r: u8) -> u8 {
-    let p = (0..4).map(|i| num(p, r, i * 4)).fold((0, 9), |m, v| {
-        (std::cmp::max(m.0, v), std::cmp::min(m.1, v))
-    });
-    p.0 - p.1
+// a streak's colour, hotter the longer it goes on
+const STREAK: [Color; 4] = [Color::White, Color::Yellow, Color::Red, Color::Magenta];
+
+// the combo and the back to back streak from (x, y), each while it lasts - two in a row or more
+fn draw_streaks(g: &View, (x, y): (u16, u16)) -> Result<()> {
+    let mut stdout = stdout();
+    crossterm::queue!(stdout, cursor::MoveTo(x, y))?;
+    for (name, n) in [("Combo", g.combo), ("B2B", g.b2b)] {
+        if n >= 2 {
+            let c = STREAK[(n as usize - 2).min(STREAK.len() - 1)];
+            let s = format!("{} x{}  ", name, n);
+            crossterm::queue!(stdout, style::PrintStyledContent(s.bold().with(c)))?;
+        }
+    }
+    Ok(())
 }
 
-fn new_tetramino(g: &mut Game) {
-    g.p = random::<u8>() % 7; // tetromino
-    g.r = random::<u8>() % 4; // orientation
-    g.x = random::<u8>() % (10 - width(g.p, g.r));
-    g.y = 0;
-    g.py = 0;
-    g.pr = g.r;
-    g.px = g.x;
+// every piece by name from (x, y), in its colour while some of it is left
+// in the bag, greyed out once it has all come into play - all of them in colour
+// once the bag is empty, for the next one
+fn draw_bag(g: &View, left: &[u8], (x, y): (u16, u16)) -> Result<()> {
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        cursor::MoveTo(x, y),
+        style::PrintStyledContent("Bag   :".bold().white())
+    )?;
+    for (p, piece) in pieces::get().pieces.iter().enumerate() {
+        let p = p as u8;
+        let s = format!(" {}", piece.name);
+        let s = match left.is_empty() || left.contains(&p) {
+            true => s.bold().with(g.color(p)),
+            false => s.dim().dark_grey(),
+        };
+        crossterm::queue!(stdout, style::PrintStyledContent(s))?;
+    }
+    Ok(())
 }
 
-fn centered_x(s: &str) -> u16 {
-    let leftedge: u16 = 25;
-    let n: u16 = s.len().try_into().unwrap();
+// piece p in orientation r, two rows of its cells to a line of half blocks
+fn mini(p: u8, r: u8) -> [String; 2] {
+    let cells = pieces::shape(p).coor(r);
+    let filled = |i: u8, j: u8| cells.contains(&(i, j));
+    [0, 2].map(|i| {
+        (0..4)
+            .map(|j| match (filled(i, j), filled(i + 1, j)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect()
+    })
+}
 
-    match terminal::size() {
-        Ok((cols, _rows)) => {
-            if cols < leftedge + n {
-                leftedge
+// label, then the pieces drawn in their colours side by side over two
+// rows from (x, y) - as many as fit in the sidebar
+fn draw_pieces(
+    g: &View,
+    label: &str,
+    pieces: &[(u8, u8)],
+    (x, y): (u16, u16),
+    lay: &Layout,
+) -> Result<()> {
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        cursor::MoveTo(x, y),
+        style::PrintStyledContent(label.bold().white())
+    )?;
+    let mut x = x + layout::width(label);
+    for &(p, r) in pieces {
+        if x + 4 > lay.right {
+            break;
+        }
+        for (k, line) in mini(p, r).into_iter().enumerate() {
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(x, y + k as u16),
+                style::PrintStyledContent(line.with(g.color(p)))
+            )?;
+        }
+        x += 5;
+    }
+    Ok(())
+}
+
+// the piece held, greyed out once a hold has been used on this piece -
+// or with the swap preview, the two a hold would leave: the falling piece
+// put aside, and what comes into play for it
+fn draw_hold(g: &View, (x, y): (u16, u16), lay: &Layout) -> Result<()> {
+    let label = "Hold  : ";
+    match g.held {
+        _ if g.swap_preview && g.can_hold => {
+            let swap: Vec<_> = [(g.p, g.r)].into_iter().chain(g.swap).collect();
+            draw_pieces(g, label, &swap, (x, y), lay)
+        }
+        Some(held) if g.can_hold => draw_pieces(g, label, &[held], (x, y), lay),
+        held => {
+            draw_pieces(g, label, &[], (x, y), lay)?;
+            let Some((p, r)) = held else {
+                return Ok(());
+            };
+            let x = x + layout::width(label);
+            for (k, line) in mini(p, r).into_iter().enumerate() {
+                crossterm::queue!(
+                    stdout(),
+                    cursor::MoveTo(x, y + k as u16),
+                    style::PrintStyledContent(line.dim().dark_grey())
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// draw g's board inside a box whose left edge is column x, rows rows of it
+// with arrows on the box where more is hidden above or below
+fn draw_board(g: &View, x: u16, rows: u8) -> Result<()> {
+    let mut stdout = stdout();
+    let top = g.top(rows);
+    if rows < g.height {
+        let edge = 2 * g.width as u16 - 1;
+        let [more, none] = match g.theme {
+            Theme::Ascii => [["^", "v"], ["-", "-"]],
+            _ => [["▲", "▼"], ["━", "━"]],
+        };
+        let up = if top > 0 { more[0] } else { none[0] };
+        let down = if top + rows < g.height {
+            more[1]
+        } else {
+            none[1]
+        };
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(x + edge, 0),
+            style::PrintStyledContent(up.white()),
+            cursor::MoveTo(x + edge, rows as u16 + 1),
+            style::PrintStyledContent(down.white())
+        )?;
+    }
+    for i in top..top + rows {
+        crossterm::queue!(stdout, cursor::MoveTo(x + 1, (i - top) as u16 + 1))?;
+        let flash = g.clearing.contains(&i) && (g.tick / 5).is_multiple_of(2);
+        for j in 0..g.width {
+            let v = match g.sliding() && g.falling(j, i) {
+                true => 0, // drawn over once the row is done
+                false => g.cell(j, i),
+            };
+            let half = g.halfway().then(|| g.half_cell(j, i)).flatten();
+            if flash {
+                crossterm::queue!(stdout, style::PrintStyledContent("▓▓".white()))?;
+            } else if let Some(s) = half {
+                crossterm::queue!(stdout, style::PrintStyledContent(s.with(piece_color(g))))?;
+            } else if let Some(s) = (v == 0 && g.ghost_cell(j, i))
+                .then(|| palette::ghost(g.ghost(), g.color(g.p)))
+                .flatten()
+            {
+                crossterm::queue!(stdout, style::PrintStyledContent(s))?;
+            } else if v == 0 && g.hint_cell(j, i) {
+                crossterm::queue!(stdout, style::PrintStyledContent(palette::hint()))?;
+            } else if v != 0 {
+                let mut s = palette::block(g.theme, v);
+                let piece = v as usize <= pieces::get().pieces.len();
+                let c = match g.stack {
+                    Stack::Level => piece.then(|| g.color(v - 1)),
+                    _ => palette::stack(g.stack, i, g.height, g.tick)
+                        .filter(|_| piece && !g.falling(j, i)),
+                };
+                if let Some(c) = c {
+                    s.style_mut().background_color = Some(c);
+                }
+                let s = g.skin.dress(v, s);
+                crossterm::queue!(stdout, style::PrintStyledContent(s))?;
             } else {
-                (cols - leftedge - n) / 2 + leftedge
+                let s = g
+                    .skin
+                    .dress(0, palette::empty(g.background, j, i, g.height, g.level));
+                crossterm::queue!(stdout, style::PrintStyledContent(s))?;
             }
         }
-        Err(_) => leftedge,
+        if g.sliding() {
+            draw_sliding(g, x, i, (i - top) as u16 + 1)?;
+        }
     }
+    Ok(())
 }
 
-fn level(g: &Game) -> u64 {
-    1 + g.tick / TICK_LEVEL
+// the colour the falling piece is drawn in, when it takes half blocks
+fn piece_color(g: &View) -> Color {
+    if g.stack == Stack::Level {
+        return g.color(g.p);
+    }
+    let block = palette::block(g.theme, g.p + 1);
+    (block.style().background_color)
+        .or(block.style().foreground_color)
+        .unwrap_or(palette::color(g.p))
 }
 
-fn render_game_info(g: &Game) {
-    let s1: &str = "Unictris - Unicode-powered Tetris";
-    let s2 = "Rusty Glyph Edition 2023 ";
+// the falling piece's cells in board row i, drawn on screen row y g.slide
+// columns - half a cell - back towards where it was
+fn draw_sliding(g: &View, x: u16, i: u8, y: u16) -> Result<()> {
+    let mut j = 0;
+    while j < g.width {
+        let n = (j..g.width).take_while(|&k| g.falling(k, i)).count() as u8;
+        if n > 0 {
+            let col = (x + 1 + 2 * j as u16).saturating_add_signed(g.slide as i16);
+            let s = "█".repeat(2 * n as usize).with(piece_color(g));
+            crossterm::queue!(
+                stdout(),
+                cursor::MoveTo(col, y),
+                style::PrintStyledContent(s)
+            )?;
+        }
+        j += n.max(1);
+    }
+    Ok(())
+}
 
-    crossterm::queue!(
-        stdout(),
-        cursor::MoveTo(centered_x(s1), 2),
-        style::PrintStyledContent(s1.cyan()),
-        cursor::MoveTo(centered_x(s2), 3),
-        style::PrintStyledContent(s2.yellow()),
-    )
-    .ok();
+// incoming garbage as a column beside the board, filling from the bottom:
+// grey while on its way, yellow when about to rise, red once it will rise with the next lock
+fn draw_garbage_meter(g: &View, x: u16, rows: u8) -> Result<()> {
+    let mut stdout = stdout();
+    let mut cells = g.garbage.iter().flat_map(|b| {
+        let c = match b.delay {
+            0 => Color::Red,
+            d if d <= garbage::DELAY / 2 => Color::Yellow,
+            _ => Color::Grey,
+        };
+        (0..b.lines).map(move |_| c)
+    });
+    for i in (1..=rows as u16).rev() {
+        crossterm::queue!(stdout, cursor::MoveTo(x, i))?;
+        match cells.next() {
+            Some(c) => crossterm::queue!(stdout, style::PrintStyledContent("█".with(c)))?,
+            None => crossterm::queue!(stdout, style::Print(" "))?,
+        }
+    }
+    Ok(())
+}
 
-    let i = centered_x("Score : 123456"); /* get a pos base on av score digits */
+// garbage that rises with the next lock, as the bottom of the box flashing
+// red with the rows that are coming - a beat to get ready, or to clear
+fn draw_garbage_warning(g: &View, lay: &Layout) -> Result<()> {
+    let due: u32 = g
+        .garbage
+        .iter()
+        .filter(|b| b.delay == 0)
+        .map(|b| b.lines)
+        .sum();
+    if due == 0 || !(g.tick / 10).is_multiple_of(2) {
+        return Ok(());
+    }
+    let edge = if lay.ascii { "-" } else { "━" };
+    let s = format!(" {} INCOMING ", due);
+    let line = edge.repeat(lay.board.0 as usize - 1);
     crossterm::queue!(
         stdout(),
-        cursor::MoveTo(i, 5.try_into().unwrap()),
-        style::PrintStyledContent(format!("Score : {}", g.score).bold().white()),
-        cursor::MoveTo(i, 6.try_into().unwrap()),
-        style::PrintStyledContent(format!("Level : {}", level(&g)).bold().white()),
-        cursor::MoveTo(i, 8.try_into().unwrap()),
-        style::PrintStyledContent(format!("Shape : {}.{}", g.p, g.r).bold().white()),
+        cursor::MoveTo(lay.x + 1, lay.board.1),
+        style::PrintStyledContent(line.bold().red()),
+        cursor::MoveTo(
+            lay.x + (lay.board.0 + 1).saturating_sub(layout::width(&s)) / 2,
+            lay.board.1
+        ),
+        style::PrintStyledContent(s.bold().white().on_red()),
+    )?;
+    Ok(())
+}
+
+// the board box cycles through these in a fever
+const FEVER_COLORS: [Color; 6] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Green,
+    Color::Cyan,
+    Color::Blue,
+    Color::Magenta,
+];
+
+fn draw_screen(g: &View, lay: &Layout) -> Result<()> {
+    let border = match g.fever {
+        Fever::On(_) => FEVER_COLORS[(g.tick / 10 % FEVER_COLORS.len() as u64) as usize],
+        Fever::Charging { .. } => Color::White,
+    };
+    box_(lay.x, 0, lay.board.0, lay.board.1, border, lay.ascii)?;
+    draw_board(g, lay.x, lay.rows)?;
+    if !lay.opponents.is_empty() || g.mode == Mode::Survival {
+        draw_garbage_meter(g, lay.meter, lay.rows)?;
+        draw_garbage_warning(g, lay)?;
+    }
+    render_game_info(g, lay);
+    Ok(())
+}
+
+// a clean screen with the player's board box
+fn draw_frame(lay: &Layout) -> Result<()> {
+    crossterm::queue!(stdout(), terminal::Clear(terminal::ClearType::All))?;
+    box_(lay.x, 0, lay.board.0, lay.board.1, Color::White, lay.ascii)
+}
+
+// an opponent's board, and the name under it in its colour
+type Rival = (View, String, Color);
+
+// the opponents' boards, named - with their knockouts, in a room of more than two
+fn rivals(v: &Versus) -> Vec<Rival> {
+    let room = v.bots.len() > 1;
+    (v.bots.iter().enumerate())
+        .map(|(i, b)| {
+            let name = match v.room.kos[i + 1] {
+                n if room && n > 0 => format!("{} {}KO", b.name, n),
+                _ => b.name.clone(),
+            };
+            let c = match v.room.teams[i + 1] == v.room.teams[0] {
+                _ if b.game.over => Color::DarkGrey,
+                true => Color::Green, // an ally
+                false => Color::White,
+            };
+            (View::of(&b.game), name, c)
+        })
+        .collect()
+}
+
+// the opponents' boards, box and all, with their names: as big as the
+// player's against one, mini boards against more
+fn draw_opponents(rivals: &[Rival], lay: &Layout) -> Result<()> {
+    for ((v, name, c), &x) in rivals.iter().zip(&lay.opponents) {
+        let (s, y) = match lay.opponents.len() {
+            1 => {
+                box_(x, 0, lay.board.0, lay.board.1, Color::White, lay.ascii)?;
+                draw_board(v, x, lay.rows)?;
+                (format!("{}  Lines : {}", name, v.lines), lay.board.1 + 1)
+            }
+            _ => {
+                box_(x, 0, lay.mini.0, lay.mini.1, *c, lay.ascii)?;
+                draw_mini(v, (x + 1, 1))?;
+                (layout::fit(name, lay.mini.0 + 1), lay.mini.1 + 1)
+            }
+        };
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(x + 1, y),
+            style::PrintStyledContent(s.with(*c)),
+        )?;
+    }
+    Ok(())
+}
+
+// board v from (x, y), a cell a column and two rows a line, in half blocks
+fn draw_mini(v: &View, (x, y): (u16, u16)) -> Result<()> {
+    let color = |c: u8| match c {
+        0 => None,
+        board::GARBAGE => Some(palette::GARBAGE),
+        board::ITEM => Some(palette::ITEM),
+        board::FLOOR => Some(palette::FLOOR),
+        c => Some(v.color(c - 1)),
+    };
+    let [both, top, bottom] = match v.theme {
+        Theme::Ascii => [":", "'", "."],
+        _ => ["█", "▀", "▄"],
+    };
+    for i in 0..v.height.div_ceil(2) {
+        crossterm::queue!(stdout(), cursor::MoveTo(x, y + i as u16))?;
+        for j in 0..v.width {
+            let low = (2 * i + 1 < v.height).then(|| v.cell(j, 2 * i + 1));
+            let s = match (color(v.cell(j, 2 * i)), low.and_then(color)) {
+                (None, None) => " ".stylize(),
+                (Some(t), None) => top.with(t),
+                (None, Some(b)) => bottom.with(b),
+                (Some(t), Some(b)) if t == b || v.theme == Theme::Mono => both.with(t),
+                (Some(t), Some(b)) => top.with(t).on(b),
+            };
+            crossterm::queue!(stdout(), style::PrintStyledContent(s))?;
+        }
+    }
+    Ok(())
+}
+
+const POPUP_TICKS: u64 = 150; // how long a popup stays up
+
+// the latest game event in words, while it lasts
+fn draw_popup(popup: Option<&str>, lay: &Layout) -> Result<()> {
+    let mut stdout = stdout();
+    blank(lay, lay.status + 1)?;
+    if let Some(s) = popup {
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(lay.centered_x(s), lay.status + 1),
+            style::PrintStyledContent(s.bold().yellow()),
+        )?;
+    }
+    Ok(())
+}
+
+const REWIND: u64 = 300; // ticks the rewind key goes back - 3s
+const SHAKE: u64 = 12; // ticks the screen shakes for, after a tetris or heavy garbage
+const HEAVY: u32 = 4; // garbage rows rising at once that shake it
+const SLIDE: u64 = 2; // ticks a piece moved sideways is drawn sliding over - a frame or two
+
+fn is_ctrl_c(ev: &Event) -> bool {
+    matches!(
+        ev,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        })
     )
-    .ok();
 }
 
-fn draw_screen(g: &Game) -> Result<()> {
+// how a call to runloop ended
+enum Exit {
+    GameOver,
+    Restart,
+    Quit,
+    Killed, // by SIGINT/SIGTERM, the game kept to take up again
+}
+
+const PAUSE_MENU: [&str; 4] = ["Resume", "Restart", "Copy board", "Quit"];
+
+// the pause menu in the sidebar, or blank rows when not paused - but for
+// the autopilot saying it has the game
+// copied: the board was just copied, which the menu's title says
+fn draw_pause_menu(
+    (paused, sel, copied): (bool, usize, bool),
+    autopilot: bool,
+    lay: &Layout,
+) -> Result<()> {
+    let x = lay.centered_x("> Copy board <");
     let mut stdout = stdout();
+    blank(lay, lay.status)?;
+    if paused {
+        let s = if copied { "  Board copied" } else { "  Paused" };
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(x, lay.status),
+            style::PrintStyledContent(s.bold().yellow())
+        )?;
+    } else if autopilot {
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(x, lay.status),
+            style::PrintStyledContent("  Autopilot".bold().cyan())
+        )?;
+    }
+    for (i, item) in PAUSE_MENU.iter().enumerate() {
+        blank(lay, lay.status + 2 + i as u16)?;
+        crossterm::queue!(stdout, cursor::MoveTo(x, lay.status + 2 + i as u16))?;
+        if paused && i == sel {
+            let s = format!("> {} <", item);
+            crossterm::queue!(stdout, style::PrintStyledContent(s.bold().white()))?;
+        } else if paused {
+            let s = format!("  {}", item);
+            crossterm::queue!(stdout, style::PrintStyledContent(s.white()))?;
+        }
+    }
+    Ok(())
+}
 
-    for (i, row) in g.board.iter().enumerate() {
-        let i: u16 = (i.try_into()).unwrap();
-
-        crossterm::queue!(stdout, cursor::MoveTo(1, i + 1))?;
-        row.iter()
-            .enumerate()
-            .map(|(j, v)| {
-                let v = *v as u32;
-                let j: u16 = j.try_into().unwrap();
-                crossterm::queue!(stdout, cursor::MoveTo(j * 2 + 1, i + 1)).ok();
-                if v != 0 {
-                    let s = match v {
-                        // 1 => "\u{16A0}\u{16A0}".on_red(),
-                        // 2 => "\u{16A2}\u{16A2}".on_red(),
-                        // 3 => "\u{16A5}\u{16A5}".on_red(),
-                        // 4 => "\u{16A6}\u{16A6}".on_red(),
-                        // 5 => "\u{16BC}\u{16BC}".on_red(),
-                        // 6 => "\u{16AD}\u{16AD}".on_red(),
-                        // _ => "\u{16D2}\u{16D2}".on_red(),
-                        1 => "●●".on_blue(),
-                        2 => "◎◎".blue().on_yellow(),
-                        3 => "□□".on_green(),
-                        4 => "◦◦".on_magenta(),
-                        5 => "○○".on_dark_red(),
-                        6 => "◼◼".on_cyan(),
-                        _ => "◉◉".on_red(),
-                        // 1 => "  ".on_blue(),
-                        // 2 => "  ".on_yellow(),
-                        // 3 => "  ".on_green(),
-                        // 4 => "  ".on_magenta(),
-                        // 5 => "  ".on_dark_red(),
-                        // 6 => "  ".on_cyan(),
-                        // _ => "  ".on_red(),
-                    };
-                    crossterm::queue!(
-                        stdout,
-                        style::PrintStyledContent(s),
-                        cursor::MoveTo((j + 1) * 2 + 1, i + 1)
-                    )
-                    .ok();
-                } else {
-                    crossterm::queue!(stdout, style::PrintStyledContent("  ".white())).ok();
-                }
-            })
-            .for_each(drop);
+// the state of the game in full, for frame advance
+fn debug_lines(g: &Game) -> Vec<String> {
+    let phase = match &g.phase {
+        Phase::Falling => "falling".to_string(),
+        Phase::Clearing(rows, n) => format!("clearing {} rows, {} left", rows.len(), n),
+        Phase::Entry(n) => format!("entry, {} left", n),
+    };
+    vec![
+        format!("Tick {}  Level {}", g.tick, g.level()),
+        format!("Phase {}", phase),
+        format!(
+            "Piece {} at {},{} r{}",
+            pieces::get().pieces[g.p as usize].name,
+            g.x,
+            g.y,
+            g.r
+        ),
+        format!("Fall {:.2}  Lowest {}", g.fall, g.lowest),
+        format!(
+            "Lock {}/{}ms  Resets {}",
+            g.lock_ticks * TICK.as_millis() as u32,
+            g.timing().lock,
+            g.lock_resets
+        ),
+        format!("Grounded {}  Garbage {}", g.grounded(), g.garbage.pending()),
+        format!("Seed {}", g.seed),
+    ]
+}
+
+// what goes under the pause menu: the state in frame advance, and the
+// profile while F3 has it up
+fn debug(g: &Game, step: bool, profiling: Option<&mut profile::Report>) -> Option<Vec<String>> {
+    let mut lines = if step { debug_lines(g) } else { Vec::new() };
+    if let Some(p) = profiling {
+        p.update();
+        lines.extend(p.lines.iter().cloned());
+    }
+    (!lines.is_empty()).then_some(lines)
+}
+
+// ... drawn
+fn draw_debug(lines: &[String], lay: &Layout) -> Result<()> {
+    for (i, s) in lines.iter().enumerate() {
+        let row = lay.status + 6 + i as u16;
+        blank(lay, row)?;
+        let s = layout::fit(s, lay.right - lay.left);
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(lay.left + 1, row),
+            style::PrintStyledContent(s.dark_grey()),
+        )?;
     }
-    render_game_info(g);
-    stdout.flush()?;
     Ok(())
 }
 
-// place a tetramino on the board
-fn set_piece(g: &mut Game, x: u8, y: u8, r: u8, v: u8) {
-    for i in 0..4 {
-        g.board[(num(g.p, r, i * 4) + y) as usize][(num(g.p, r, i * 4 + 2) + x) as usize] = v;
+// A frame for the render thread: all it draws, as it was when sent.
+struct Scene {
+    view: View,
+    opponents: Vec<Rival>,
+    lay: Layout,
+    redraw: bool,              // clear the screen first, after a resize
+    menu: (bool, usize, bool), // the pause menu: up, the selection, and the board just copied
+    autopilot: bool,           // the bot playing
+    popup: Option<String>,
+    debug: Option<Vec<String>>, // the full state, in frame advance, and the profile
+    copy: Option<String>,       // text for the clipboard
+    sparks: Vec<u8>,            // rows cleared since the last scene drawn
+}
+
+fn draw_scene(s: &Scene, particles: &mut Particles) -> Result<()> {
+    let lay = &s.lay;
+    let span = profile::span(Stage::Draw);
+    write!(stdout(), "{}", mux::begin_frame())?;
+    if s.redraw {
+        draw_frame(lay)?;
+    }
+    draw_screen(&s.view, lay)?;
+    for &y in &s.sparks {
+        particles.burst(y, s.view.width);
+    }
+    particles.step();
+    particles.draw(&s.view, lay)?;
+    draw_pause_menu(s.menu, s.autopilot, lay)?;
+    draw_popup(s.popup.as_deref(), lay)?;
+    draw_opponents(&s.opponents, lay)?;
+    if let Some(lines) = &s.debug {
+        draw_debug(lines, lay)?;
+    }
+    write!(stdout(), "{}", mux::end_frame())?;
+    drop(span);
+    let span = profile::span(Stage::Flush);
+    stdout().flush()?;
+    drop(span);
+    broadcast::send(&s.view);
+    #[cfg(feature = "http")]
+    http::publish(&s.view);
+    if let Some(text) = &s.copy {
+        clipboard::copy(text)?;
     }
+    Ok(())
 }
 
-// move a piece from old (p*) coords to new
-fn update_piece(g: &mut Game) {
-    set_piece(g, g.px, g.py, g.pr, 0);
-    g.px = g.x;
-    g.py = g.y;
-    g.pr = g.r;
-    set_piece(g, g.x, g.y, g.r, g.p + 1);
+// Drawing on a thread of its own, so a slow terminal - over SSH, say -
+// never holds up the game or the keys: the loop sends a scene a frame and
+// goes on, and a scene sent while the last is still being drawn is dropped.
+// Nothing else may write to the terminal while it is running.
+struct Renderer {
+    scenes: Option<SyncSender<Box<Scene>>>,
+    thread: Option<JoinHandle<Result<()>>>,
 }
 
-fn wipe_filled_rows(g: &mut Game) {
-    for row in g.y..=g.y + height(g.p, g.r) {
-        if g.board[row as usize]
-            .iter()
-            .map(|v| *v as u32)
-            //.fold(1, |p, v| p * v)
-            .product::<u32>()
-            > 0
-        {
-            for i in (1..row).rev() {
-                let i = i as usize;
-                for j in 0..g.board[i + 1].len() {
-                    g.board[i + 1][j] = g.board[i][j];
-                }
-                for j in 0..g.board[0].len() {
-                    g.board[0][j] = 0;
-                }
-                g.score += 1;
-            }
+impl Renderer {
+    fn start() -> Renderer {
+        let (tx, rx) = mpsc::sync_channel::<Box<Scene>>(1);
+        let thread = std::thread::spawn(move || {
+            let mut particles = Particles::default();
+            rx.iter().try_for_each(|s| draw_scene(&s, &mut particles))
+        });
+        Renderer {
+            scenes: Some(tx),
+            thread: Some(thread),
+        }
+    }
+
+    // false if the scene was dropped, the last one not being drawn yet
+    fn send(&self, s: Scene) -> Result<bool> {
+        match self.scenes.as_ref().map(|tx| tx.try_send(Box::new(s))) {
+            Some(Ok(())) => Ok(true),
+            Some(Err(TrySendError::Full(_))) => Ok(false),
+            _ => Err(std::io::Error::other("the render thread stopped")),
         }
     }
 }
 
-// check if placing p at (x,y,r) will hit something
-fn check_hit(g: &mut Game, x: u8, y: u8, r: u8) -> bool {
-    let bottom: u8 = (g.board.len() - 1).try_into().unwrap();
-    if y + height(g.p, r) > bottom {
-        return true;
+// waits for the last scene to be drawn, so it is safe to draw again
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        self.scenes = None;
+        if let Some(t) = self.thread.take() {
+            t.join().ok();
+        }
     }
-    set_piece(g, g.px, g.py, g.pr, 0);
+}
 
-    let hits = (0..4)
-        .filter(|i| {
-            g.board[(y + num(g.p, r, i * 4)) as usize][(x + num(g.p, r, i * 4 + 2)) as usize] != 0
-        })
-        .count();
-    set_piece(g, g.px, g.py, g.pr, g.p + 1);
-    hits > 0
+// one tick for the game and the opponents; false once the game is over -
+// in versus play, the match
+fn advance(g: &mut Game, opp: &mut Option<Versus>) -> bool {
+    let going = g.do_tick();
+    match opp.as_mut().filter(|_| !g.paused) {
+        Some(v) => v.tick(g),
+        None => going,
+    }
 }
 
-fn do_tick(g: &mut Game) -> bool {
-    if g.paused {
-        return true;
+// the screen laid out for a cfg board, and the opponents' in versus play
+fn lay_out(cfg: &Config, opp: &Option<Versus>) -> Layout {
+    match opp {
+        Some(v) => Layout::new(cfg, v.bots.len(), v.allies()),
+        None => Layout::new(cfg, 0, 0),
     }
-    g.tick = (g.tick + 1) % std::u64::MAX;
-    if g.tick % 30 <= g.tick / TICK_LEVEL {
-        // only update some of the time...
-        if check_hit(g, g.x, g.y + 1, g.r) {
-            if g.y == 0 {
-                // overflow - game over
-                return false;
+}
+
+const SPARKS: u64 = 70; // ticks sparks fly for, at the longest
+
+// ticks the loop can sleep through, from g's own and what is drawn over
+// it - a popup, for one - for the screen to miss nothing; 1 while
+// something moves every tick: moving, what the loop draws moving or the
+// last scene not got through
+fn quiet(
+    g: &Game,
+    opp: &Option<Versus>,
+    input: &dyn InputSource,
+    popup: Option<u64>, // the tick it comes down
+    moving: bool,
+) -> u32 {
+    let cfg = g.cfg();
+    let hud = |w| cfg.hud.contains(&w);
+    let moving = moving
+        || opp.is_some()
+        || (cfg.smooth && !cfg.reduced_motion)
+        || cfg.stack == Stack::Rainbow
+        || (cfg.mode == Mode::Sprint && hud(Widget::Ghost));
+    if moving {
+        return 1;
+    }
+    let mut n = g.quiet();
+    if let Some(until) = popup {
+        n = n.min(until.saturating_sub(g.tick));
+    }
+    if hud(Widget::Time) {
+        n = n.min(100 - g.tick % 100); // seconds
+    }
+    if let Some(due) = input.due() {
+        n = n.min(due.saturating_sub(g.tick));
+    }
+    n.clamp(1, u32::MAX as u64) as u32
+}
+
+// play until the game is over or the player leaves it
+// input: where the moves come from, given the keys pressed
+// rec: the moves so far, kept in single-player games for rewinding and --record
+// step: frame advance - a tick per press of the step key, not in real time
+// quit: set by SIGINT/SIGTERM so a kill leaves by the same way as 'q', but the game kept
+fn runloop(
+    g: &mut Game,
+    opp: &mut Option<Versus>,
+    rec: &mut Option<Replay>,
+    input: &mut dyn InputSource,
+    lay: &mut Layout,
+    step: bool,
+    quit: &AtomicBool,
+) -> Result<Exit> {
+    let wall = Wall::default();
+    let mut ticker = Ticker::new(&wall);
+    let renderer = Renderer::start();
+    let mut sel = 0; // pause menu selection
+    let mut copied = false; // ... and whether it was Copy board
+    let mut popup = None; // (text, tick it comes down)
+    let mut redraw = false; // for the renderer, kept until a scene gets through
+    let mut copy = None;
+    let mut saved = g.tick; // when the game was last autosaved
+    let mut slide = (0, 0); // columns the piece is drawn off by, and the tick that stops
+    let mut shake = 0; // the tick the screen stops shaking
+    let mut shaken = 0; // ... and the columns it is over by
+    let mut sparks = Vec::new(); // rows cleared, for the renderer to throw sparks out of
+    let mut drawn = false; // the last scene got through to the renderer
+    let mut sparked = 0; // the tick sparks last flew
+    let mut profiling: Option<profile::Report> = None; // F3's overlay, when up
+    let mut hint = ai::Search::default(); // where the bot would put the piece
+    let mut searching = false; // ... and it isn't done working that out
+    let mut autopilot: Option<Ai> = None; // the bot, while it has the game
+    let mut kos = 0; // knockouts the player has had, in a room
+    let mut out = false; // the player topped out, in teams
+    loop {
+        if quit.load(Ordering::Relaxed) {
+            return Ok(Exit::Killed);
+        }
+        let due = if step { 0 } else { ticker.due(g.cfg().tick()) };
+        let span = profile::span(Stage::Sim);
+        for _ in 0..due {
+            if !advance(g, opp) {
+                return Ok(Exit::GameOver);
             }
-            wipe_filled_rows(g);
-            new_tetramino(g);
+        }
+        drop(span);
+        if let Some(v) = opp.as_ref().filter(|v| v.room.kos[0] > kos) {
+            kos = v.room.kos[0];
+            popup = Some(("KNOCKOUT!".to_string(), g.tick + POPUP_TICKS));
+        }
+        // topped out with the team still in: watched to the end, the
+        // game's clock stopped with it - over by a move otherwise, over now
+        let team_in = |v: &Versus| v.allies() > 0 && v.result(g).is_none();
+        if g.over && !out {
+            if !opp.as_ref().is_some_and(team_in) {
+                return Ok(Exit::GameOver);
+            }
+            out = true;
+            popup = Some(("ALLY PLAYS ON".to_string(), u64::MAX));
+        }
+        // between ticks and the moves made after them, so none is left out
+        if let Some(r) = rec
+            .as_mut()
+            .filter(|_| g.tick.abs_diff(saved) >= autosave::EVERY)
+        {
+            r.finish(g);
+            autosave::save(r).ok(); // tried again next time: the game matters more
+            saved = g.tick;
+        }
+        let still = g.cfg().reduced_motion;
+        for e in g.events.drain(..) {
+            let heavy = match e {
+                GameEvent::Clear(c) => c.lines >= 4,
+                GameEvent::Garbage(n) => n >= HEAVY,
+                _ => false,
+            };
+            if heavy && !still {
+                shake = g.tick + SHAKE;
+            }
+            let s = match e {
+                GameEvent::Chain(n) => format!("CHAIN x{}!", n),
+                GameEvent::Fever => "FEVER!".to_string(),
+                GameEvent::Clear(c) => match c.name() {
+                    Some(name) => format!("{}!", name),
+                    None => continue,
+                },
+                GameEvent::PerfectClear => "PERFECT CLEAR!".to_string(),
+                GameEvent::Row(y) if !still => {
+                    sparks.push(y);
+                    sparked = g.tick;
+                    continue;
+                }
+                GameEvent::Lock(..) | GameEvent::Garbage(_) | GameEvent::Row(_) => continue,
+            };
+            popup = Some((s, g.tick + POPUP_TICKS));
+        }
+        // paused, nothing moves until a key comes, once the pause menu has
+        // got to the renderer
+        let wait = match step {
+            true => Duration::from_millis(100),
+            false if g.paused && drawn => events::IDLE,
+            false => {
+                let moving = !drawn || g.tick < slide.1 || g.tick < shake;
+                let moving = moving || g.tick < sparked + SPARKS || searching;
+                let moving = moving || autopilot.is_some();
+                let n = quiet(g, opp, &*input, popup.as_ref().map(|p| p.1), moving);
+                ticker.wait_for(n, g.cfg().tick()).min(clock::MAX_LAG / 2)
+            }
+        };
+        let wait = match profiling {
+            Some(_) => wait.min(profile::EVERY),
+            None => wait,
+        };
+        let woken = events::next(wait);
+        let span = profile::span(Stage::Input);
+        if let Some(Wake::Term(ev)) = woken {
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(Exit::Quit),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Resize(w, h) => {
+                    cast::resize((w, h))?;
+                    *lay = lay_out(g.cfg(), opp);
+                    redraw = true;
+                }
+                Event::Key(KeyEvent {
+                    code: KeyCode::F(3),
+                    ..
+                }) => {
+                    profiling = match profiling {
+                        Some(_) => None,
+                        None => Some(profile::Report::default()),
+                    };
+                    redraw = true; // not to leave its lines behind
+                }
+                Event::Key(key) if g.paused => match key.code {
+                    KeyCode::Up => sel = (sel + PAUSE_MENU.len() - 1) % PAUSE_MENU.len(),
+                    KeyCode::Down => sel = (sel + 1) % PAUSE_MENU.len(),
+                    KeyCode::Enter => match sel {
+                        0 => g.paused = false,
+                        1 => return Ok(Exit::Restart),
+                        2 => {
+                            copy = Some(position::to_text(&g.board));
+                            copied = true;
+                        }
+                        _ => return Ok(Exit::Quit),
+                    },
+                    _ => input.key(&key),
+                },
+                Event::Key(key) => input.key(&key),
+                _ => (),
+            }
+        }
+        // the player's, and then the bot's moves if it has the game
+        let bot = |g: &Game, autopilot: &mut Option<Ai>| {
+            let ai = autopilot.as_mut().filter(|_| !g.paused)?;
+            ai.next(g).map(|a| (a, true))
+        };
+        while let Some((a, by_bot)) = input
+            .next(g)
+            .map(|a| (a, false))
+            .or_else(|| bot(g, &mut autopilot))
+        {
+            match a {
+                Action::Pause if g.paused => g.paused = false,
+                Action::Quit => return Ok(Exit::Quit),
+                _ if g.paused => (), // only the pause menu, until it is left
+                Action::Move(_) if autopilot.is_some() && !by_bot => (), // the bot has it
+                Action::Autopilot if autopilot.is_none() => {
+                    autopilot = Some(Ai::new(Difficulty::Hard, Style::Balanced));
+                }
+                Action::TakeOver => autopilot = None,
+                Action::Move(m) => {
+                    if let Some(r) = rec.as_mut() {
+                        r.record(g, m);
+                    }
+                    let x = g.x;
+                    g.apply(m);
+                    if g.x != x && !g.cfg().reduced_motion {
+                        slide = ((x as i16 - g.x as i16).signum() as i8, g.tick + SLIDE);
+                    }
+                }
+                Action::Pause => {
+                    g.paused = true;
+                    sel = 0;
+                    copied = false;
+                }
+                Action::Step if step && !advance(g, opp) => return Ok(Exit::GameOver),
+                Action::Rewind if opp.is_none() => {
+                    if let Some(r) = rec.as_mut() {
+                        *g = r.rewind(g.tick.saturating_sub(REWIND));
+                        popup = None;
+                        slide = (0, 0);
+                    }
+                }
+                Action::Step | Action::Rewind | Action::Autopilot => (),
+            }
+        }
+        drop(span);
+        let span = profile::span(Stage::Scene);
+        let mut view = View::of(g);
+        if g.tick < slide.1 {
+            view.slide = slide.0;
+        }
+        searching = false;
+        if g.cfg().hint && g.phase == Phase::Falling {
+            match hint.step(g, &ai::WEIGHTS) {
+                Some(p) => view.hint(p.r, p.x, p.y),
+                None => searching = true,
+            }
+        }
+        // a column to one side and then the other, the screen cleared and
+        // drawn afresh each time it moves to leave nothing behind
+        let dx = match shake {
+            t if g.tick < t => [1, -1][(g.tick / 2 % 2) as usize],
+            _ => 0,
+        };
+        if dx != shaken {
+            shaken = dx;
+            redraw = true;
+        }
+        let scene = Scene {
+            view,
+            opponents: opp.as_ref().map(rivals).unwrap_or_default(),
+            lay: lay.offset(dx),
+            redraw,
+            menu: (g.paused, sel, copied),
+            autopilot: autopilot.is_some(),
+            popup: popup
+                .as_ref()
+                .filter(|(_, until)| g.tick < *until && !g.paused)
+                .map(|(s, _)| s.clone()),
+            debug: debug(g, step, profiling.as_mut()),
+            copy: copy.clone(),
+            sparks: sparks.clone(),
+        };
+        drawn = renderer.send(scene)?;
+        if drawn {
+            redraw = false;
+            copy = None;
+            sparks.clear();
         } else {
-            g.y += 1;
-            update_piece(g);
+            profile::dropped();
         }
+        drop(span);
     }
-    true
 }
 
-fn runloop(g: &mut Game) -> Result<()> {
-    while do_tick(g) {
-        if let Ok(true) = poll(time::Duration::from_millis(10)) {
-            match read() {
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Char('q'),
+// play a replay back, until it ends and the viewer leaves
+fn watch(replay: &Replay, quit: &AtomicBool) -> Result<()> {
+    let mut play = replay.playback();
+    let mut lay = Layout::new(&replay.config, 0, 0);
+    draw_frame(&lay)?;
+    let wall = Wall::default();
+    let mut ticker = Ticker::new(&wall);
+    let mut playing = true;
+    while !quit.load(Ordering::Relaxed) {
+        for _ in 0..ticker.due(replay.config.tick()) {
+            playing = playing && play.step();
+        }
+        draw_screen(&View::of(&play.game), &lay)?;
+        blank(&lay, lay.status)?;
+        let s = if playing {
+            "Replay - [Q]uit"
+        } else {
+            "Replay over - [Q]uit"
+        };
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(lay.centered_x(s), lay.status),
+            style::PrintStyledContent(s.bold().yellow()),
+        )?;
+        stdout().flush()?;
+        let wait = match playing {
+            true => ticker.wait(),
+            false => events::IDLE,
+        };
+        if let Some(Wake::Term(ev)) = events::next(wait) {
+            match ev {
+                ev if is_ctrl_c(&ev) => break,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('q') | KeyCode::Esc,
                     ..
-                })) => return Ok(()),
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Char(' '),
+                }) => break,
+                Event::Resize(w, h) => {
+                    cast::resize((w, h))?;
+                    lay = Layout::new(&replay.config, 0, 0);
+                    draw_frame(&lay)?;
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+// leave the final board up and ask whether to go again
+fn game_over(
+    g: &Game,
+    opp: Option<&Versus>,
+    session: &Session,
+    lay: &Layout,
+    quit: &AtomicBool,
+) -> Result<bool> {
+    let s = match opp {
+        None if g.solved => {
+            let n = g.stats.pieces();
+            let s = if n == 1 { "" } else { "s" };
+            format!("Solved with {} piece{} - [R]etry [Q]uit", n, s)
+        }
+        None if g.goal.is_some() => "Not solved - [R]etry [Q]uit".to_string(),
+        None if g.cfg().mode == Mode::Sprint && g.lines >= mode::SPRINT_LINES => {
+            format!("{} - [R]etry [Q]uit", clock(g.tick))
+        }
+        None if g.cfg().mode == Mode::Ultra && g.tick >= mode::ULTRA_TICKS => {
+            format!("Time's up - {} points - [R]etry [Q]uit", g.score)
+        }
+        None => "Game Over - [R]etry [Q]uit".to_string(),
+        Some(v) if v.result(g) == Some(true) => "You Win! - [R]etry [Q]uit".to_string(),
+        Some(_) => "You Lose - [R]etry [Q]uit".to_string(),
+    };
+    let s2 = format!(
+        "Games : {}  Best : {}  Lines : {}",
+        session.games, session.best, session.lines
+    );
+    draw_screen(&View::of(g), lay)?;
+    crossterm::queue!(
+        stdout(),
+        cursor::MoveTo(lay.centered_x(&s), lay.status),
+        style::PrintStyledContent(s.as_str().bold().red()),
+        cursor::MoveTo(lay.centered_x(&s2), lay.status + 2),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    draw_heatmap(g, lay)?;
+    draw_score_graph(g, lay)?;
+    draw_opponents(&opp.map(rivals).unwrap_or_default(), lay)?;
+    stdout().flush()?;
+
+    while !quit.load(Ordering::Relaxed) {
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('r'),
                     ..
-                })) => g.paused = !g.paused,
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Left,
+                }) => return Ok(true),
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('q') | KeyCode::Esc,
                     ..
-                })) => {
-                    if g.x > 0 && !check_hit(g, g.x - 1, g.y, g.r) {
-                        g.x -= 1;
+                }) => return Ok(false),
+                ev if is_ctrl_c(&ev) => return Ok(false),
+                _ => (),
+            }
+        }
+    }
+    Ok(false)
+}
+
+// a heatmap's colours, from the coolest
+const HEAT: [Color; 4] = [Color::DarkBlue, Color::Green, Color::Yellow, Color::Red];
+
+// where the game's pieces locked: how many blocks went in each column along
+// the bottom of the box, and in each row beside it, with how far it leaned
+fn draw_heatmap(g: &Game, lay: &Layout) -> Result<()> {
+    let (shades, none) = match lay.ascii {
+        true => ([".", ":", "+", "#"], "-"),
+        false => (["░", "▒", "▓", "█"], "━"),
+    };
+    let mut stdout = stdout();
+    for (x, h) in stats::heat(&g.stats.columns).into_iter().enumerate() {
+        let s = match h {
+            0 => none.repeat(2).white(),
+            h => shades[h - 1].repeat(2).with(HEAT[h - 1]),
+        };
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(lay.x + 1 + 2 * x as u16, lay.board.1),
+            style::PrintStyledContent(s)
+        )?;
+    }
+    let heat = stats::heat(&g.stats.rows);
+    let top = View::of(g).top(lay.rows);
+    for i in top..top + lay.rows {
+        let s = match heat.get(i as usize) {
+            Some(&h) if h > 0 => shades[h - 1].with(HEAT[h - 1]),
+            _ => " ".stylize(),
+        };
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(lay.meter, (i - top) as u16 + 1),
+            style::PrintStyledContent(s)
+        )?;
+    }
+    if !g.stats.columns.is_empty() {
+        let (left, right) = g.stats.lean();
+        let s = format!("Placed : left {}%  right {}%", left, right);
+        crossterm::queue!(
+            stdout,
+            cursor::MoveTo(lay.centered_x(&s), lay.status + 3),
+            style::PrintStyledContent(s.white()),
+        )?;
+    }
+    Ok(())
+}
+
+const GRAPH_ROWS: u16 = 4; // tallest the score graph gets
+
+// how the score went over the game, under the game over lines where there is room
+fn draw_score_graph(g: &Game, lay: &Layout) -> Result<()> {
+    let lines = terminal::size().map_or(24, |(_, l)| l);
+    let top = lay.status + 5;
+    let rows = lines.saturating_sub(top + 1).min(GRAPH_ROWS);
+    let cols = lay.right - lay.left;
+    if rows == 0 || cols < 10 || g.score == 0 {
+        return Ok(());
+    }
+    let mut samples = g.stats.scores.clone();
+    samples.push(g.score);
+    let bars = if lay.ascii {
+        &stats::ASCII_BARS
+    } else {
+        &stats::BARS
+    };
+    let title = format!("Score over {}", clock(g.tick));
+    crossterm::queue!(
+        stdout(),
+        cursor::MoveTo(lay.centered_x(&title), top),
+        style::PrintStyledContent(title.as_str().white()),
+    )?;
+    let chart = stats::chart(&samples, g.score, cols as usize, rows as usize, bars);
+    for (i, row) in chart.iter().enumerate() {
+        crossterm::queue!(
+            stdout(),
+            cursor::MoveTo(lay.centered_x(row), top + 1 + i as u16),
+            style::PrintStyledContent(row.as_str().green()),
+        )?;
+    }
+    Ok(())
+}
+
+// centered on the whole screen
+fn screen_x(s: &str) -> u16 {
+    let cols = terminal::size().map_or(80, |(c, _)| c);
+    cols.saturating_sub(layout::width(s)) / 2
+}
+
+// take up g, the game the last run died in? None to quit instead
+fn resume_menu(g: &Game, quit: &AtomicBool) -> Result<Option<bool>> {
+    let s1 = "There is a game that was cut short";
+    let s2 = format!(
+        "{:?}, level {}, {} points, {} lines",
+        g.cfg().mode,
+        g.level(),
+        g.score,
+        g.lines
+    );
+    let s3 = "[Enter] Resume  [N]ew game  [Q]uit";
+    crossterm::queue!(
+        stdout(),
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 3),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(&s2), 5),
+        style::PrintStyledContent(s2.as_str().bold().white()),
+        cursor::MoveTo(screen_x(s3), 7),
+        style::PrintStyledContent(s3.white()),
+    )?;
+    stdout().flush()?;
+    while !quit.load(Ordering::Relaxed) {
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(None),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) => match key.code {
+                    KeyCode::Enter | KeyCode::Char('y') => return Ok(Some(true)),
+                    KeyCode::Char('n') => return Ok(Some(false)),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(None)
+}
+
+// pick a board size before the first game; false to quit instead
+fn title_menu(cfg: &mut Config, quit: &AtomicBool) -> Result<bool> {
+    let mut sizes: Vec<(&str, u8, u8)> = config::PRESETS.to_vec();
+    if !sizes
+        .iter()
+        .any(|&(_, w, h)| (w, h) == (cfg.width, cfg.height))
+    {
+        sizes.insert(0, ("Custom", cfg.width, cfg.height));
+    }
+    let mut sel = sizes
+        .iter()
+        .position(|&(_, w, h)| (w, h) == (cfg.width, cfg.height))
+        .unwrap_or(0);
+    let s1 = "Unictris - Unicode-powered Tetris";
+    let s2 = "[Enter] Play  [Q]uit";
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 3),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(s2), 7 + sizes.len() as u16),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    while !quit.load(Ordering::Relaxed) {
+        for (i, (name, w, h)) in sizes.iter().enumerate() {
+            let s = match i == sel {
+                true => format!("> {}{:>2}x{:<2} <", layout::pad(name, 8), w, h)
+                    .bold()
+                    .white(),
+                false => format!("  {}{:>2}x{:<2}  ", layout::pad(name, 8), w, h).white(),
+            };
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(screen_x(" Classic  10x20  "), 5 + i as u16),
+                style::PrintStyledContent(s),
+            )?;
+        }
+        stdout.flush()?;
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(false),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) => match key.code {
+                    KeyCode::Up => sel = (sel + sizes.len() - 1) % sizes.len(),
+                    KeyCode::Down => sel = (sel + 1) % sizes.len(),
+                    KeyCode::Enter | KeyCode::Char(' ') => {
+                        (_, cfg.width, cfg.height) = sizes[sel];
+                        return Ok(true);
                     }
-                }
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Right,
-                    ..
-                })) => {
-                    if g.x + width(g.p, g.r) < 9 && !check_hit(g, g.x + 1, g.y, g.r) {
-                        g.x += 1;
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(false)
+}
+
+const LEVEL_ROW: u64 = 5; // levels to a row on the level select screen
+
+// pick the starting level, for marathon; false to quit instead
+fn level_menu(cfg: &mut Config, quit: &AtomicBool) -> Result<bool> {
+    let s1 = "Starting level";
+    let s2 = "[Enter] Play  [Q]uit";
+    let rows = config::MAX_LEVEL.div_ceil(LEVEL_ROW);
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 3),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(s2), 7 + rows as u16),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    let x = screen_x(&" ".repeat(4 * LEVEL_ROW as usize));
+    let mut sel = cfg.level;
+    while !quit.load(Ordering::Relaxed) {
+        for level in 1..=config::MAX_LEVEL {
+            let (row, col) = ((level - 1) / LEVEL_ROW, (level - 1) % LEVEL_ROW);
+            let s = match level == sel {
+                true => format!("[{:>2}]", level).bold().white(),
+                false => format!(" {:>2} ", level).white(),
+            };
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(x + 4 * col as u16, 5 + row as u16),
+                style::PrintStyledContent(s),
+            )?;
+        }
+        stdout.flush()?;
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(false),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) => match key.code {
+                    KeyCode::Left if sel > 1 => sel -= 1,
+                    KeyCode::Right if sel < config::MAX_LEVEL => sel += 1,
+                    KeyCode::Up if sel > LEVEL_ROW => sel -= LEVEL_ROW,
+                    KeyCode::Down => sel = (sel + LEVEL_ROW).min(config::MAX_LEVEL),
+                    KeyCode::Enter | KeyCode::Char(' ') => {
+                        cfg.level = sel;
+                        return Ok(true);
                     }
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(false)
+}
+
+// what the computer's difficulty and style come to, for the setup screen
+fn describe(d: Difficulty, s: Style) -> [String; 2] {
+    let ahead = match d.depth() {
+        1 => "",
+        _ => ", looking a piece ahead",
+    };
+    let wrong = match (d.mistakes() * 100.0) as u32 {
+        0 => "never misplaces a piece".to_string(),
+        n => format!("misplaces {}% of its pieces", n),
+    };
+    let style = match s {
+        Style::Balanced => "clears lines as it goes",
+        Style::Attacker => "builds up for tetrises, to send garbage",
+        Style::Safe => "keeps its stack as low as it can",
+    };
+    [
+        format!("Moves every {} ticks{}", d.think(), ahead),
+        format!("{}, {}", wrong, style),
+    ]
+}
+
+// x's neighbour in all, the next one or, back, the one before
+fn turn<T: Copy + PartialEq>(all: &[T], x: T, back: bool) -> T {
+    let i = all.iter().position(|&y| y == x).unwrap_or(0);
+    let by = if back { all.len() - 1 } else { 1 };
+    all[(i + by) % all.len()]
+}
+
+// where garbage goes with target t, in words
+fn aims(t: Target) -> &'static str {
+    match t {
+        Target::Random => "Garbage goes to one of them at random",
+        Target::Leader => "Garbage goes to whoever has the highest score",
+        Target::All => "Garbage goes to every one of them, in full",
+        Target::Badges => "Garbage goes to whoever has knocked out the most",
+    }
+}
+
+// pick the computer's difficulty and style, how many to play and where
+// garbage goes among them, before a versus match; false to quit instead
+fn versus_menu(versus: &mut Setup, quit: &AtomicBool) -> Result<bool> {
+    let s1 = "Versus the computer";
+    let s2 = "[Enter] Play  [Q]uit";
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 3),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(s2), 15),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    // how many to play, and whether that's 2v2
+    let mut counts: Vec<(usize, bool)> = (1..=MAX_OPPONENTS).map(|n| (n, false)).collect();
+    counts.push((3, true));
+    let mut sel = 0; // the row: difficulty, style, opponents or target
+    while !quit.load(Ordering::Relaxed) {
+        let v = *versus;
+        let rows = [
+            ("Difficulty", format!("{:?}", v.difficulty)),
+            ("Style", format!("{:?}", v.style)),
+            (
+                "Opponents",
+                match v.teams {
+                    true => "2v2".to_string(),
+                    false => v.opponents.to_string(),
+                },
+            ),
+            ("Target", format!("{:?}", v.target)),
+        ];
+        for (i, (name, value)) in rows.iter().enumerate() {
+            let s = match i == sel {
+                true => format!("{} < {:<8} >", layout::pad(name, 11), value)
+                    .bold()
+                    .white(),
+                false => format!("{}   {:<8}  ", layout::pad(name, 11), value).white(),
+            };
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(screen_x(&" ".repeat(24)), 5 + i as u16),
+                style::PrintStyledContent(s),
+            )?;
+        }
+        let [d1, d2] = describe(v.difficulty, v.style);
+        let d3 = match v.opponents {
+            _ if v.teams => "You and an ally against two, garbage shared".to_string(),
+            1 => String::new(),
+            _ => aims(v.target).to_string(),
+        };
+        for (i, s) in [d1, d2, d3].iter().enumerate() {
+            let row = 10 + i as u16;
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(0, row),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                cursor::MoveTo(screen_x(s), row),
+                style::PrintStyledContent(s.as_str().dark_grey()),
+            )?;
+        }
+        stdout.flush()?;
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            // the row's choice turned one on, or back
+            let turn = |back| {
+                let mut v = v;
+                match sel {
+                    0 => v.difficulty = turn(&Difficulty::ALL, v.difficulty, back),
+                    1 => v.style = turn(&Style::ALL, v.style, back),
+                    2 => (v.opponents, v.teams) = turn(&counts, (v.opponents, v.teams), back),
+                    _ => v.target = turn(&Target::ALL, v.target, back),
                 }
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Down,
-                    ..
-                })) => {
-                    while !check_hit(g, g.x, g.y + 1, g.r) {
-                        g.y += 1;
-                        update_piece(g);
-                    }
-                    wipe_filled_rows(g);
-                    new_tetramino(g);
+                v
+            };
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(false),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) => match key.code {
+                    KeyCode::Up => sel = (sel + rows.len() - 1) % rows.len(),
+                    KeyCode::Down => sel = (sel + 1) % rows.len(),
+                    KeyCode::Left => *versus = turn(true),
+                    KeyCode::Right => *versus = turn(false),
+                    KeyCode::Enter | KeyCode::Char(' ') => return Ok(true),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(false)
+}
+
+// pick a puzzle, (pack, puzzle), from packs - those solved ticked, with the
+// fewest pieces they took - starting from and leaving sel on the one picked;
+// None to quit instead
+fn pack_menu(
+    packs: &[Pack],
+    progress: &Progress,
+    sel: &mut (usize, usize),
+    ascii: bool,
+    quit: &AtomicBool,
+) -> Result<Option<(usize, usize)>> {
+    let s1 = "Puzzles";
+    let s2 = "[Enter] Play  [Q]uit";
+    let tick = if ascii { "*" } else { "✓" };
+    // a line per pack and per puzzle, the puzzles' to pick
+    let mut lines = Vec::new();
+    for (i, pack) in packs.iter().enumerate() {
+        let n = progress.count(pack);
+        lines.push((
+            format!("{}  {}/{}", pack.title, n, pack.puzzles.len()),
+            None,
+        ));
+        for (j, p) in pack.puzzles.iter().enumerate() {
+            let done = match progress.best(&pack.title, &p.title) {
+                Some(b) => format!("{} {}/{}", tick, b, p.par),
+                None => String::new(),
+            };
+            let s = format!(
+                "{}{} {}",
+                layout::pad(&p.title, 16),
+                layout::pad(&p.goal.to_string(), 18),
+                done
+            );
+            lines.push((s, Some((i, j))));
+        }
+    }
+    let width = lines
+        .iter()
+        .map(|(s, _)| layout::width(s))
+        .max()
+        .unwrap_or(0)
+        + 4;
+    let (_, rows) = terminal::size().unwrap_or((80, 24));
+    let shown = (rows.saturating_sub(9) as usize).max(1);
+    let mut stdout = stdout();
+    crossterm::queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 2),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(s2), 6 + shown.min(lines.len()) as u16),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    let x = screen_x(&" ".repeat(width as usize));
+    let picks: Vec<(usize, usize)> = lines.iter().filter_map(|&(_, p)| p).collect();
+    let mut at = picks.iter().position(|&p| p == *sel).unwrap_or(0);
+    while !quit.load(Ordering::Relaxed) {
+        let line = lines
+            .iter()
+            .position(|&(_, p)| p == Some(picks[at]))
+            .unwrap_or(0);
+        // from the line before it, for its pack's title to show over the first
+        let top = line
+            .saturating_sub(1)
+            .min(lines.len().saturating_sub(shown));
+        for (k, (s, p)) in lines.iter().enumerate().skip(top).take(shown) {
+            let s = match p {
+                None => layout::pad(s, width).bold().yellow(),
+                Some(p) if *p == picks[at] => {
+                    format!("> {}", layout::pad(s, width - 2)).bold().white()
                 }
-                Ok(Event::Key(KeyEvent {
-                    code: KeyCode::Up, ..
-                })) => {
-                    g.r = (g.r + 1) % 4;
-                    while g.x + width(g.p, g.r) > 9 {
-                        g.x -= 1;
-                    }
-                    if check_hit(g, g.x, g.y, g.r) {
-                        g.x = g.px;
-                        g.r = g.pr;
+                Some(_) => format!("  {}", layout::pad(s, width - 2)).white(),
+            };
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(x, 4 + (k - top) as u16),
+                style::PrintStyledContent(s),
+            )?;
+        }
+        stdout.flush()?;
+        if let Some(Wake::Term(ev)) = events::next(events::IDLE) {
+            match ev {
+                ev if is_ctrl_c(&ev) => return Ok(None),
+                Event::Key(key) if key.kind == KeyEventKind::Release => (),
+                Event::Key(key) => match key.code {
+                    KeyCode::Up => at = (at + picks.len() - 1) % picks.len(),
+                    KeyCode::Down => at = (at + 1) % picks.len(),
+                    KeyCode::Enter | KeyCode::Char(' ') => {
+                        *sel = picks[at];
+                        return Ok(Some(*sel));
                     }
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(None)
+}
+
+// puzzles picked from packs and played, until the player quits
+// - going on through the campaign, with the rewards that unlocked
+fn puzzles(
+    packs: &[Pack],
+    progress: &mut Progress,
+    profile: &mut Profile,
+    cfg: &Config,
+    input: &mut dyn InputSource,
+    step: bool,
+    quit: &AtomicBool,
+) -> Result<Vec<Reward>> {
+    let mut unlocked = Vec::new();
+    let mut session = Session::default();
+    let mut sel = (0, 0);
+    while let Some((i, j)) = pack_menu(packs, progress, &mut sel, cfg.theme == Theme::Ascii, quit)?
+    {
+        let (pack, entry) = (&packs[i], &packs[i].puzzles[j]);
+        loop {
+            let mut game = Game::new(cfg);
+            entry.start(&mut game);
+            let mut lay = Layout::new(cfg, 0, 0);
+            draw_frame(&lay)?;
+            // no rewinding, which would start over without the goal
+            let exit = runloop(&mut game, &mut None, &mut None, input, &mut lay, step, quit)?;
+            session.record(&game);
+            if game.solved && progress.record(&pack.title, &entry.title, game.stats.pieces()) {
+                progress.save()?;
+                let rewards = profile.advance(None, progress);
+                if !rewards.is_empty() {
+                    profile.save()?;
+                    unlocked.extend(rewards);
                 }
+            }
+            match exit {
+                Exit::Quit | Exit::Killed => break,
+                Exit::GameOver if !game_over(&game, None, &session, &lay, quit)? => break,
                 _ => (),
             }
         }
-        update_piece(g);
-        draw_screen(g)?;
     }
-    Ok(())
+    Ok(unlocked)
 }
 
-fn box_(x: u16, y: u16, width: u16, height: u16) -> Result<()> {
-    const TOP_LEFT: &str = "\u{250f}";
-    const TOP_RIGHT: &str = "\u{2513}";
-    const BOTTOM_LEFT: &str = "\u{2517}";
-    const BOTTOM_RIGHT: &str = "\u{251b}";
-    const VERTICAL: &str = "\u{2503}";
-    const HORIZONTAL: &str = "\u{2501}";
+// who is playing: one of profiles, Some(None) for the default, or a new one
+// named here; None to quit instead
+fn profile_menu(profiles: &[String], quit: &AtomicBool) -> Result<Option<Option<String>>> {
+    let s1 = "Who is playing?";
+    let s2 = "[Enter] Play  [Esc] Quit";
+    let mut names: Vec<String> = vec!["Default".to_string()];
+    names.extend(profiles.iter().cloned());
+    let mut new = String::new(); // the last line's name, typed in
+    let mut sel = 0;
+    let mut stdout = stdout();
+    let rows = names.len() as u16 + 1;
+    crossterm::queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(screen_x(s1), 3),
+        style::PrintStyledContent(s1.cyan()),
+        cursor::MoveTo(screen_x(s2), 7 + rows),
+        style::PrintStyledContent(s2.white()),
+    )?;
+    while !quit.load(Ordering::Relaxed) {
+        let x = screen_x(&" ".repeat(24));
+        for (i, name) in names.iter().chain([&format!("New: {}_", new)]).enumerate() {
+            let s = match i == sel {
+                true => format!("> {}", layout::pad(name, 22)).bold().white(),
+                false => format!("  {}", layout::pad(name, 22)).white(),
+            };
+            crossterm::queue!(
+                stdout,
+                cursor::MoveTo(x, 5 + i as u16),
+                style::PrintStyledContent(s),
+            )?;
+        }
+        stdout.flush()?;
+        let Some(Wake::Term(ev)) = events::next(events::IDLE) else {
+            continue;
+        };
+        let typing = sel == names.len(); // in the new name, every key a character
+        match ev {
+            ev if is_ctrl_c(&ev) => return Ok(None),
+            Event::Key(key) if key.kind == KeyEventKind::Release => (),
+            Event::Key(key) => match key.code {
+                KeyCode::Up => sel = (sel + names.len()) % (names.len() + 1),
+                KeyCode::Down => sel = (sel + 1) % (names.len() + 1),
+                KeyCode::Enter if sel == 0 => return Ok(Some(None)),
+                KeyCode::Enter if !typing => return Ok(Some(Some(names[sel].clone()))),
+                KeyCode::Enter if !new.is_empty() => return Ok(Some(Some(new))),
+                KeyCode::Backspace if typing => {
+                    new.pop();
+                }
+                KeyCode::Char(c) if typing && new.chars().count() < 20 => new.push(c),
+                KeyCode::Char(_) if typing => (), // full
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    Ok(None)
+}
+
+// SIGINT and SIGTERM setting the flag returned rather than killing the
+// program, and waking the loop waiting, for it to leave the screen it is on
+// by the same way as 'q'
+fn catch_signals() -> io::Result<Arc<AtomicBool>> {
+    let quit = Arc::new(AtomicBool::new(false));
+    events::on_signals(&[SIGINT, SIGTERM], Arc::clone(&quit))?;
+    Ok(quit)
+}
+
+// a box with its top left corner at (x, y), in line drawing characters or plain ASCII
+fn box_(x: u16, y: u16, width: u16, height: u16, c: Color, ascii: bool) -> Result<()> {
+    let [top_left, top_right, bottom_left, bottom_right, vertical, horizontal] = match ascii {
+        false => [
+            "\u{250f}", "\u{2513}", "\u{2517}", "\u{251b}", "\u{2503}", "\u{2501}",
+        ],
+        true => ["+", "+", "+", "+", "|", "-"],
+    };
     let mut stdout = stdout();
 
     stdout
-        .queue(terminal::Clear(terminal::ClearType::All))?
         .queue(cursor::MoveTo(x, y))?
-        .queue(style::PrintStyledContent(TOP_LEFT.white()))?
+        .queue(style::PrintStyledContent(top_left.with(c)))?
         .queue(cursor::MoveTo(x + width, y))?
-        .queue(style::PrintStyledContent(TOP_RIGHT.white()))?
+        .queue(style::PrintStyledContent(top_right.with(c)))?
         .queue(cursor::MoveTo(x, y + height))?
-        .queue(style::PrintStyledContent(BOTTOM_LEFT.white()))?
+        .queue(style::PrintStyledContent(bottom_left.with(c)))?
         .queue(cursor::MoveTo(x + width, y + height))?
-        .queue(style::PrintStyledContent(BOTTOM_RIGHT.white()))?;
+        .queue(style::PrintStyledContent(bottom_right.with(c)))?;
 
     for i in 1..width {
         crossterm::queue!(
             stdout,
             cursor::MoveTo(x + i, y),
-            style::PrintStyledContent(HORIZONTAL.white()),
+            style::PrintStyledContent(horizontal.with(c)),
             cursor::MoveTo(x + i, y + height),
-            style::PrintStyledContent(HORIZONTAL.white())
+            style::PrintStyledContent(horizontal.with(c))
         )?;
     }
     for i in 1..height {
         crossterm::queue!(
             stdout,
             cursor::MoveTo(x, y + i),
-            style::PrintStyledContent(VERTICAL.white()),
+            style::PrintStyledContent(vertical.with(c)),
             cursor::MoveTo(x + width, y + i),
-            style::PrintStyledContent(VERTICAL.white())
+            style::PrintStyledContent(vertical.with(c))
         )?;
     }
     crossterm::queue!(
@@ -349,48 +1879,465 @@ fn box_(x: u16, y: u16, width: u16, height: u16) -> Result<()> {
         cursor::Hide,
         cursor::MoveTo(x + width + 2, y + height + 2)
     )?;
+    Ok(())
+}
+
+// the stack and pieces of fumen src, on a board of cfg's size
+fn puzzle(src: &str, cfg: &Config) -> Result<Puzzle> {
+    if cfg.width != fumen::WIDTH {
+        let msg = format!("--fumen: fumen boards are {} wide", fumen::WIDTH);
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg));
+    }
+    fumen::parse(src, cfg.height)
+}
+
+#[cfg(feature = "export")]
+fn export(replay: &Path, out: &Path, cfg: &Config) -> tetrisct::error::Result<()> {
+    Ok(tetrisct::export::export(&Replay::load(replay, cfg)?, out)?)
+}
+
+#[cfg(not(feature = "export"))]
+fn export(_: &Path, _: &Path, _: &Config) -> tetrisct::error::Result<()> {
+    let msg = "--export: built without the export feature (cargo build --features export)";
+    Err(Error::Usage(msg.to_string()))
+}
 
-    stdout.flush()?;
+#[cfg(feature = "http")]
+fn serve(addr: &str) -> tetrisct::error::Result<()> {
+    Ok(http::start(addr)?)
+}
+
+#[cfg(not(feature = "http"))]
+fn serve(_: &str) -> tetrisct::error::Result<()> {
+    let msg = "--serve: built without the http feature (cargo build --features http)";
+    Err(Error::Usage(msg.to_string()))
+}
+
+#[cfg(feature = "trace")]
+fn trace(path: &Path) -> tetrisct::error::Result<()> {
+    Ok(profile::trace(path)?)
+}
+
+#[cfg(not(feature = "trace"))]
+fn trace(_: &Path) -> tetrisct::error::Result<()> {
+    let msg = "--trace: built without the trace feature (cargo build --features trace)";
+    Err(Error::Usage(msg.to_string()))
+}
+
+// the terminal given over to the game until this is dropped - on the way
+// out, error or not, so whatever stopped the game can be read
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Screen> {
+        crossterm::queue!(
+            stdout(),
+            style::ResetColor,
+            terminal::Clear(terminal::ClearType::All),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            cursor::MoveTo(0, 0)
+        )?;
+        terminal::enable_raw_mode()?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        crossterm::execute!(
+            stdout(),
+            terminal::Clear(terminal::ClearType::All),
+            terminal::LeaveAlternateScreen,
+            cursor::Show,
+            cursor::MoveTo(0, 0)
+        )
+        .ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+// the console of old Windows, as cmd.exe opens in - not Windows Terminal,
+// ConEmu, mintty or others that go by what terminals elsewhere do
+fn legacy_console() -> bool {
+    cfg!(windows)
+        && ["WT_SESSION", "TERM_PROGRAM", "TERM", "ConEmuANSI"]
+            .iter()
+            .all(|v| std::env::var_os(v).is_none())
+}
+
+// does the terminal draw an emoji square two columns wide, as the emoji theme needs?
+fn emoji_wide() -> bool {
+    crossterm::execute!(stdout(), cursor::MoveTo(0, 0), style::Print("🟥")).is_ok()
+        && cursor::position().is_ok_and(|p| p == (2, 0))
+}
+
+// is the terminal big enough for a cfg board, and n opponents' in versus play?
+fn fits(cfg: &Config, n: usize) -> tetrisct::error::Result<()> {
+    let need = Layout::needs(cfg, n);
+    let have = terminal::size()?;
+    if have.0 < need.0 || have.1 < need.1 {
+        return Err(Error::TerminalTooSmall { need, have });
+    }
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let mut game = Game {
-        x: 0,
-        y: 0,
-        r: 0,
-        pr: 0,
-        px: 0,
-        py: 0,
-        p: 0,
-        tick: 0,
-        score: 0,
-        board: [[0; 10]; 20],
-        paused: false,
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e @ Error::Usage(_)) => {
+            eprintln!("{}", e);
+            ExitCode::from(2) // as for any command used wrongly
+        }
+        Err(e) => {
+            eprintln!("tetrisct: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> tetrisct::error::Result<()> {
+    if let Ok(path) = std::env::var("UNICTRIS_PIECES") {
+        pieces::init(pieces::PieceSet::from_file(&path)?);
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let parse = |cfg: &mut Config| match cli::parse(args.iter().cloned(), cfg) {
+        Ok(Some(opts)) => Ok(opts),
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            std::process::exit(0);
+        }
+        Err(msg) => Err(Error::Usage(format!("{}\n\n{}", msg, cli::USAGE))),
+    };
+    // who is playing, asked if there are profiles and it isn't said, for
+    // the settings to come from theirs - and then the options over those
+    let opts = parse(&mut Config::default())?;
+    let profiles = config::profiles();
+    match &opts.profile {
+        Some(name) => config::set_profile(name)?,
+        None if opts.plays() && !profiles.is_empty() && std::io::stdout().is_terminal() => {
+            let quit = catch_signals()?;
+            let screen = Screen::enter()?;
+            let name = profile_menu(&profiles, &quit)?;
+            drop(screen);
+            match name {
+                Some(Some(name)) => config::set_profile(&name)?,
+                Some(None) => (), // the default
+                None => return Ok(()),
+            }
+        }
+        None => (),
+    }
+    // before the config is read, for an imported one to be used
+    if let Some(path) = &opts.import_profile {
+        let n = archive::import(path)?;
+        println!("Imported {} file(s) from {}", n, path.display());
+        return Ok(());
+    }
+    if let Some(path) = &opts.export_profile {
+        let n = archive::export(path)?;
+        println!("Exported {} file(s) to {}", n, path.display());
+        return Ok(());
+    }
+    let mut cfg = Config::load()?;
+    let opts = parse(&mut cfg)?;
+    // the options over the file held to the same as the file alone
+    let mut cfg = cfg.validate()?;
+    if let Some((replay, out)) = &opts.export {
+        return export(replay, out, &cfg);
+    }
+    if let Some((replay, out)) = &opts.tas {
+        return Ok(std::fs::write(out, Replay::load(replay, &cfg)?.to_bare())?);
+    }
+    let mut profile = Profile::load()?;
+    if opts.campaign {
+        print!("{}", profile);
+        return Ok(());
+    }
+    if let Some(replay) = &opts.analyze {
+        print!("{}", analysis::analyze(&Replay::load(replay, &cfg)?));
+        return Ok(());
+    }
+    if let Some(replay) = &opts.to_fumen {
+        println!("{}", fumen::from_replay(&Replay::load(replay, &cfg)?)?);
+        return Ok(());
+    }
+    if let Some(replay) = &opts.share {
+        Replay::load(replay, &cfg)?; // not sent if it won't play
+        println!("{}", share::upload(replay, &cfg.share)?);
+        return Ok(());
+    }
+    let mut watching = match (&opts.play, &opts.fetch) {
+        (Some(path), _) => Some(Replay::load(path, &cfg)?),
+        (_, Some(url)) => Some(
+            Replay::parse(&share::download(url)?, &cfg)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", url, e)))?,
+        ),
+        _ => None,
+    };
+    if opts.record.is_some() && opts.versus.is_some() {
+        let msg = "--record: replays are of single-player games only";
+        return Err(Error::Usage(msg.to_string()));
+    }
+    if watching.is_none() && opts.edit.is_none() {
+        profile.check(&cfg)?;
+    }
+    let mut best = Best::load()?;
+    if let Some(path) = &opts.board {
+        position::load(path, cfg.width, cfg.height)?; // any mistakes, before the screen changes
+    }
+    if let Some(src) = &opts.fumen {
+        puzzle(src, &cfg)?;
+    }
+    let packs = match opts.puzzles {
+        true => {
+            let mut packs = pack::bundled(cfg.height)?;
+            for path in &opts.packs {
+                packs.push(pack::load(path, cfg.height)?);
+            }
+            Some(packs)
+        }
+        false => None,
     };
-    new_tetramino(&mut game);
+    let mut progress = Progress::load()?;
+    let editing = match &opts.edit {
+        Some(path) if path.exists() => Some(position::load(path, cfg.width, cfg.height)?),
+        Some(_) => Some(Puzzle {
+            board: Board::new(cfg.width, cfg.height),
+            queue: Vec::new(),
+        }),
+        None => None,
+    };
+    if let Some(addr) = &opts.broadcast {
+        broadcast::start(addr)?;
+    }
+    if let Some(addr) = &opts.serve {
+        serve(addr)?;
+    }
+    if let Some(path) = &opts.trace {
+        trace(path)?;
+    }
+    let votes = opts.commands.as_deref().map(commands::start).transpose()?;
+    if let Some(path) = &opts.record_cast {
+        cast::start(path, terminal::size()?)?;
+    }
 
-    crossterm::queue!(
-        stdout(),
-        style::ResetColor,
-        terminal::Clear(terminal::ClearType::All),
-        terminal::EnterAlternateScreen,
-        cursor::Hide,
-        cursor::MoveTo(0, 0)
-    )?;
-    terminal::enable_raw_mode()?;
-    box_(0, 0, 21, 21)?;
-    runloop(&mut game)?;
+    let keymap = cfg.keymap()?;
+    let cramped = mux::size_warning(terminal::size()?);
+    let screen = Screen::enter()?;
+    let legacy = cfg.legacy || legacy_console();
+    cfg.theme = match cfg.color.colors(std::io::stdout().is_terminal()) {
+        true if legacy => Theme::Ascii,
+        true => cfg.theme.in_month(palette::month()), // the season's, for seasonal
+        false => Theme::Mono,
+    };
+    if legacy {
+        cast::basic();
+    }
+    if cfg.theme == Theme::Mono {
+        cast::plain();
+    }
+    // the theme given up on, for want of emoji - which a multiplexer may
+    // count a column wide whatever the terminal does
+    let inside = mux::detect();
+    let narrow = (cfg.theme.emoji() && (inside.is_some() || !emoji_wide())).then_some(cfg.theme);
+    if narrow.is_some() {
+        cfg.theme = Theme::Glyphs;
+    }
+    if let Some(replay) = watching.as_mut() {
+        replay.config.theme = cfg.theme; // the look is the watcher's
+    }
+    #[cfg(feature = "debug-invariants")]
+    {
+        // the report of a broken invariant, on the terminal as it was
+        let report = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            crossterm::execute!(stdout(), terminal::LeaveAlternateScreen, cursor::Show).ok();
+            terminal::disable_raw_mode().ok();
+            report(info);
+        }));
+    }
+    let quit = catch_signals()?;
+    let mut versus = opts.versus.map(|difficulty| Setup {
+        difficulty,
+        style: opts.style.unwrap_or(Style::Balanced),
+        opponents: opts.opponents.unwrap_or(if opts.teams { 3 } else { 1 }),
+        target: opts.target.unwrap_or(Target::Random),
+        teams: opts.teams,
+    });
+    // a game the last run died in, to take up again if the player wants to
+    let resumed = match autosave::load(&cfg) {
+        Some(mut r) if opts.plays() && packs.is_none() && opts.versus.is_none() => {
+            r.config.theme = cfg.theme;
+            let g = r.rewind(r.end);
+            match resume_menu(&g, &quit)? {
+                Some(true) => Some((r, g)),
+                Some(false) => {
+                    autosave::clear()?;
+                    None
+                }
+                None => {
+                    quit.store(true, Ordering::Relaxed); // kept for next time
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let mut input: Box<dyn InputSource> = match votes {
+        Some(votes) => Box::new(Both(Keyboard::new(&keymap), Crowd::new(votes))),
+        None => Box::new(Keyboard::new(&keymap)),
+    };
+    let mut session = Session::default();
+    let mut last = None; // the final game, for the summary
+    let mut unlocked = Vec::new();
+    if let Some(replay) = &watching {
+        fits(&replay.config, 0)?;
+        watch(replay, &quit)?;
+    } else if let (Some(puzzle), Some(path)) = (editing, &opts.edit) {
+        fits(&cfg, 0)?;
+        editor::edit(puzzle, path, &cfg, &quit)?;
+    } else if let Some(packs) = &packs {
+        cfg.width = pack::WIDTH;
+        fits(&cfg, 0)?;
+        let profile = &mut profile;
+        unlocked = puzzles(
+            packs,
+            &mut progress,
+            profile,
+            &cfg,
+            &mut *input,
+            opts.step,
+            &quit,
+        )?;
+    } else if resumed.is_some()
+        || title_menu(&mut cfg, &quit)?
+            && (cfg.mode != Mode::Marathon || level_menu(&mut cfg, &quit)?)
+            && versus
+                .as_mut()
+                .map_or(Ok(true), |v| versus_menu(v, &quit))?
+    {
+        if let Some((r, _)) = &resumed {
+            cfg = Config {
+                theme: cfg.theme,
+                ..r.config.clone()
+            };
+        }
+        fits(&cfg, versus.map_or(0, |v| v.opponents))?;
+        // the starting stack and pieces, for the board size picked
+        let start = match (&opts.board, &opts.fumen) {
+            (Some(path), _) => Some(position::load(path, cfg.width, cfg.height)?),
+            (_, Some(src)) => Some(puzzle(src, &cfg)?),
+            _ => None,
+        };
+        let new_game = |best: &Best| {
+            let mut g = Game::new(&cfg);
+            g.pace = best.pace();
+            g.ghost = best.ghost();
+            if let Some(p) = &start {
+                g.set_board(p.board.clone());
+                g.set_queue(&p.queue);
+            }
+            g
+        };
+        let new_opponent = || versus.map(|v| Versus::new(&cfg, &v));
+        let mut opponent = new_opponent();
+        let new_replay = |g: &Game| opts.versus.is_none().then(|| Replay::new(g));
+        let (mut game, mut replay) = match resumed {
+            Some((r, mut g)) => {
+                g.pace = best.pace();
+                g.ghost = best.ghost();
+                (g, Some(r))
+            }
+            None => {
+                let g = new_game(&best);
+                let r = new_replay(&g);
+                (g, r)
+            }
+        };
+        let mut lay = lay_out(&cfg, &opponent);
+        loop {
+            draw_frame(&lay)?;
+            let exit = runloop(
+                &mut game,
+                &mut opponent,
+                &mut replay,
+                &mut *input,
+                &mut lay,
+                opts.step,
+                &quit,
+            )?;
+            // a game killed is kept, up to the last move, for next time -
+            // any other way out of it is the end of it; versus games aren't
+            // kept at all, nor do they touch the one that is
+            match (&exit, replay.as_mut()) {
+                (Exit::Killed, Some(r)) => {
+                    r.finish(&game);
+                    autosave::save(r)?;
+                }
+                (_, Some(_)) => autosave::clear()?,
+                (_, None) => (),
+            }
+            session.record(&game);
+            if best.record(&game) {
+                best.save()?;
+            }
+            let rewards = profile.advance(Some(&game), &progress);
+            if !rewards.is_empty() {
+                profile.save()?;
+                unlocked.extend(rewards);
+            }
+            if let (Some(r), Some(path)) = (replay.as_mut(), &opts.record) {
+                r.finish(&game);
+                r.save(path)?;
+            }
+            match exit {
+                Exit::Quit | Exit::Killed => break,
+                Exit::GameOver if !game_over(&game, opponent.as_ref(), &session, &lay, &quit)? => {
+                    break
+                }
+                _ => {
+                    game = new_game(&best);
+                    opponent = new_opponent();
+                    replay = new_replay(&game);
+                }
+            }
+        }
+        last = Some(game);
+    }
 
-    crossterm::queue!(
-        stdout(),
-        terminal::Clear(terminal::ClearType::All),
-        terminal::LeaveAlternateScreen,
-        cursor::Show,
-        cursor::MoveTo(0, 0)
-    )?;
-    terminal::disable_raw_mode()?;
+    drop(screen);
+    cast::finish()?;
+    if let Some(theme) = narrow {
+        let theme = format!("{:?}", theme).to_lowercase();
+        match inside {
+            Some(mux) => eprintln!(
+                "theme {}: emoji can't be counted on to be two columns wide in {}, so the blocks were glyphs",
+                theme,
+                format!("{:?}", mux).to_lowercase()
+            ),
+            None => eprintln!(
+                "theme {}: this terminal doesn't draw emoji two columns wide, so the blocks were glyphs",
+                theme
+            ),
+        }
+    }
+    if let Some(warning) = cramped {
+        eprintln!("{}", warning);
+    }
+    for reward in &unlocked {
+        println!("Unlocked {}!", reward);
+    }
+    if let Some(board) = clipboard::last() {
+        print!("Board:\n{}", board);
+    }
 
-    println!("Score: {}; Level: {}", game.score, level(&game));
+    let Some(game) = last else {
+        return Ok(());
+    };
+    println!("Score: {}; Level: {}", game.score, game.level());
+    if session.games > 1 {
+        println!("{}", session.summary().join("\n"));
+    }
     Ok(())
 }
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+// What the game is played for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Marathon, // points, as the levels go by
+    Survival, // garbage rises ever faster; the score is seconds survived
+    Rising,   // the bottom row turns to solid floor every so often
+    Sprint,   // SPRINT_LINES lines, as fast as possible
+    Ultra,    // points, in ULTRA_TICKS
+}
+
+pub const SPRINT_LINES: u32 = 40;
+pub const ULTRA_TICKS: u64 = 12000; // two minutes
+pub const SPLIT: u32 = 10; // lines between split times in sprint
+
+impl std::str::FromStr for Mode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Mode, ()> {
+        match s {
+            "marathon" => Ok(Mode::Marathon),
+            "survival" => Ok(Mode::Survival),
+            "rising" => Ok(Mode::Rising),
+            "sprint" => Ok(Mode::Sprint),
+            "ultra" => Ok(Mode::Ultra),
+            _ => Err(()),
+        }
+    }
+}
+
+// Survival's difficulty curve: a garbage row every ten seconds to begin with,
+// coming 10% sooner every half minute, down to one a second.
+const START: f64 = 1000.0; // ticks between rows at the start
+const SHRINK: f64 = 0.9;
+const STEP: u64 = 3000; // ticks between shrinks
+const FASTEST: u64 = 100;
+
+const FLOOR: u64 = 3000; // ticks between floor rows in rising - half a minute
+
+// ticks from a row rising at tick until the next - garbage in survival, floor in rising
+pub fn rising_interval(mode: Mode, tick: u64) -> u64 {
+    match mode {
+        Mode::Rising => FLOOR,
+        _ => {
+            let n = (tick / STEP).min(100) as i32;
+            ((START * SHRINK.powi(n)) as u64).max(FASTEST)
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::process::Command;
+use std::sync::OnceLock;
+
+// Playing inside tmux or GNU screen, which stand between the game and the
+// terminal: sequences for the terminal itself must be wrapped to get past
+// them, what they report about widths and sizes is theirs and not always
+// the terminal's, so the game goes more carefully there.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mux {
+    Tmux,
+    Screen,
+}
+
+static MUX: OnceLock<Option<Mux>> = OnceLock::new();
+
+// the multiplexer the game runs in, if any - as TMUX, STY and TERM tell
+pub fn detect() -> Option<Mux> {
+    *MUX.get_or_init(|| {
+        let var = |v| std::env::var_os(v).is_some();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if var("TMUX") || term.starts_with("tmux") {
+            Some(Mux::Tmux)
+        } else if var("STY") || term.starts_with("screen") {
+            Some(Mux::Screen)
+        } else {
+            None
+        }
+    })
+}
+
+// seq as it gets to the terminal outside the multiplexer - which tmux lets
+// through only with its allow-passthrough option on
+fn passthrough(seq: &str) -> String {
+    match detect() {
+        Some(Mux::Tmux) => format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b")),
+        Some(Mux::Screen) => format!("\x1bP{}\x1b\\", seq),
+        None => seq.to_string(),
+    }
+}
+
+// the marks around a frame that have the terminal show it all at once,
+// not half drawn - terminals without synchronized updates ignore them
+pub fn begin_frame() -> &'static str {
+    static BEGIN: OnceLock<String> = OnceLock::new();
+    BEGIN.get_or_init(|| passthrough("\x1b[?2026h"))
+}
+
+pub fn end_frame() -> &'static str {
+    static END: OnceLock<String> = OnceLock::new();
+    END.get_or_init(|| passthrough("\x1b[?2026l"))
+}
+
+// the size of the terminal tmux is showing the game on, where it says
+fn outer_size() -> Option<(u16, u16)> {
+    let out = Command::new("tmux")
+        .args(["display-message", "-p", "#{client_width} #{client_height}"])
+        .output()
+        .ok()?;
+    let out = String::from_utf8(out.stdout).ok()?;
+    let (w, h) = out.trim().split_once(' ')?;
+    Some((w.parse().ok()?, h.parse().ok()?))
+}
+
+// what is wrong with the size the game was given, a pane of have: bigger
+// than the terminal it is shown on, when the window is sized for another
+// client, parts of the board are cut off
+pub fn size_warning(have: (u16, u16)) -> Option<String> {
+    if detect() != Some(Mux::Tmux) {
+        return None;
+    }
+    let (w, h) = outer_size()?;
+    (have.0 > w || have.1 > h).then(|| {
+        format!(
+            "tmux: the window is {}x{} and the terminal showing it {}x{}, so some of it was cut off - try tmux's aggressive-resize option, or detach the other clients",
+            have.0, have.1, w, h
+        )
+    })
+}
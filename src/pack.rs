@@ -0,0 +1,241 @@
+use crate::config;
+use crate::error::{Error, Result};
+use crate::fumen::Puzzle;
+use crate::game::{Clear, Game, Spin};
+use crate::position;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Puzzle packs: puzzles one after another in a text file, the pack's title
+// first as "pack <title>", then each puzzle headed by "puzzle <title>" and
+// followed by what it asks for as "goal lines 4", "goal tspin 2" or
+// "goal perfect", its par as "par 1" - the fewest pieces it takes - and its
+// stack and queue as --board-file has them. A puzzle is played with the
+// pieces on its queue, or par pieces with none; running out before the
+// goal is met fails it. Packs are 10 wide, as fumen boards are, and the
+// ones built in are in assets/packs.
+
+pub const WIDTH: u8 = 10;
+
+const BUNDLED: [&str; 2] = [
+    include_str!("../assets/packs/clear-in-one.txt"),
+    include_str!("../assets/packs/t-spins.txt"),
+];
+
+// what a puzzle asks for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Lines(u32), // clear this many lines
+    Spin(u32),  // ... with a T-spin, not a mini
+    Perfect,    // leave the board empty
+}
+
+impl std::str::FromStr for Goal {
+    type Err = ();
+    fn from_str(s: &str) -> std::result::Result<Goal, ()> {
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            ["lines", n] => Ok(Goal::Lines(n.parse().map_err(|_| ())?)),
+            ["tspin", n] => Ok(Goal::Spin(n.parse().map_err(|_| ())?)),
+            ["perfect"] => Ok(Goal::Perfect),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = ["", " single", " double", " triple"];
+        match self {
+            Goal::Lines(n) => write!(f, "clear {} line{}", n, if *n == 1 { "" } else { "s" }),
+            Goal::Spin(n) => write!(f, "a T-spin{}", rows[(*n).min(3) as usize]),
+            Goal::Perfect => write!(f, "a perfect clear"),
+        }
+    }
+}
+
+impl Goal {
+    // is it met by clear, with lines cleared so far - that one's among them?
+    pub fn met(&self, clear: &Clear, lines: u32) -> bool {
+        match *self {
+            Goal::Lines(n) => lines >= n,
+            Goal::Spin(n) => clear.spin == Spin::Full && clear.lines >= n,
+            Goal::Perfect => false, // seen once the rows are gone
+        }
+    }
+}
+
+pub struct Entry {
+    pub title: String,
+    pub puzzle: Puzzle,
+    pub goal: Goal,
+    pub par: u32,
+}
+
+impl Entry {
+    // the pieces there are to meet the goal with
+    pub fn pieces(&self) -> u32 {
+        match self.puzzle.queue.len() {
+            0 => self.par,
+            n => n as u32,
+        }
+    }
+
+    // set game g up to play it
+    pub fn start(&self, g: &mut Game) {
+        g.set_board(self.puzzle.board.clone());
+        g.set_queue(&self.puzzle.queue);
+        g.goal = Some((self.goal, self.pieces()));
+    }
+}
+
+pub struct Pack {
+    pub title: String,
+    pub puzzles: Vec<Entry>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// the puzzle of title, from its goal, par and the lines of its stack and queue
+fn entry(
+    title: String,
+    goal: Option<&str>,
+    par: Option<&str>,
+    rows: &str,
+    height: u8,
+) -> io::Result<Entry> {
+    let err = |msg: String| invalid(format!("puzzle {}: {}", title, msg));
+    let goal = goal.ok_or_else(|| err("no goal".to_string()))?;
+    let goal = goal
+        .parse()
+        .map_err(|_| err(format!("what goal is '{}'?", goal)))?;
+    let puzzle = position::parse_puzzle(rows, WIDTH, height).map_err(|e| err(e.to_string()))?;
+    let par = match par {
+        Some(p) => p.parse().map_err(|_| err(format!("bad par '{}'", p)))?,
+        None if !puzzle.queue.is_empty() => puzzle.queue.len() as u32,
+        None => return Err(err("no queue or par to say how many pieces".to_string())),
+    };
+    Ok(Entry {
+        title: title.clone(),
+        puzzle,
+        goal,
+        par,
+    })
+}
+
+pub fn parse(src: &str, height: u8) -> io::Result<Pack> {
+    let mut lines = src.lines().filter(|l| !l.trim().is_empty());
+    let title = match lines.next().and_then(|l| l.trim().strip_prefix("pack ")) {
+        Some(t) => t.trim().to_string(),
+        None => {
+            return Err(invalid(
+                "the first line should be \"pack <title>\"".to_string(),
+            ))
+        }
+    };
+    let mut puzzles = Vec::new();
+    // the puzzle being read: title, goal, par and rows
+    let mut at: Option<(String, Option<&str>, Option<&str>, String)> = None;
+    for line in lines {
+        let l = line.trim();
+        if let Some(t) = l.strip_prefix("puzzle ") {
+            if let Some((title, goal, par, rows)) = at.take() {
+                puzzles.push(entry(title, goal, par, &rows, height)?);
+            }
+            at = Some((t.trim().to_string(), None, None, String::new()));
+            continue;
+        }
+        let Some((_, goal, par, rows)) = at.as_mut() else {
+            return Err(invalid(format!("'{}' before the first puzzle", l)));
+        };
+        match l.split_once(' ') {
+            Some(("goal", g)) => *goal = Some(g.trim()),
+            Some(("par", p)) => *par = Some(p.trim()),
+            _ => {
+                *rows += line;
+                rows.push('\n');
+            }
+        }
+    }
+    if let Some((title, goal, par, rows)) = at {
+        puzzles.push(entry(title, goal, par, &rows, height)?);
+    }
+    if puzzles.is_empty() {
+        return Err(invalid(format!("pack {}: no puzzles", title)));
+    }
+    Ok(Pack { title, puzzles })
+}
+
+pub fn load(path: &Path, height: u8) -> io::Result<Pack> {
+    let src = std::fs::read_to_string(path)?;
+    parse(&src, height).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+// the packs that come with the game, on boards height high
+pub fn bundled(height: u8) -> io::Result<Vec<Pack>> {
+    BUNDLED.iter().map(|src| parse(src, height)).collect()
+}
+
+// The puzzles solved, by pack and puzzle title, with the fewest pieces
+// each took - kept in puzzles.toml in the config directory.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Progress {
+    pub solved: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+fn path() -> Option<PathBuf> {
+    config::dir().map(|d| d.join("puzzles.toml"))
+}
+
+impl Progress {
+    pub fn load() -> Result<Progress> {
+        let Some(path) = path() else {
+            return Ok(Progress::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(src) => toml::from_str(&src).map_err(|e| Error::Corrupt {
+                msg: e.to_string(),
+                path,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let src = toml::to_string(self).map_err(|e| io::Error::other(e.to_string()))?;
+        std::fs::write(path, src)
+    }
+
+    // the fewest pieces puzzle in pack was solved with, if it was
+    pub fn best(&self, pack: &str, puzzle: &str) -> Option<u32> {
+        self.solved.get(pack)?.get(puzzle).copied()
+    }
+
+    // take note of puzzle in pack solved with pieces; true if it did better than before
+    pub fn record(&mut self, pack: &str, puzzle: &str, pieces: u32) -> bool {
+        if self.best(pack, puzzle).is_some_and(|b| b <= pieces) {
+            return false;
+        }
+        (self.solved.entry(pack.to_string()).or_default()).insert(puzzle.to_string(), pieces);
+        true
+    }
+
+    // puzzles of pack solved
+    pub fn count(&self, pack: &Pack) -> usize {
+        (pack.puzzles.iter())
+            .filter(|p| self.best(&pack.title, &p.title).is_some())
+            .count()
+    }
+}
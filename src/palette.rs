@@ -0,0 +1,590 @@
+use crate::board;
+use crate::config::Glyph;
+use crate::pieces::{self, parse_color};
+use crossterm::style::{Color, StyledContent, Stylize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
+
+// colour of tetromino p - guideline colours unless the piece set says otherwise
+pub fn color(p: u8) -> Color {
+    match pieces::get().pieces.get(p as usize) {
+        Some(piece) => piece.color,
+        None => GARBAGE,
+    }
+}
+
+pub const GARBAGE: Color = Color::DarkGrey;
+pub const ITEM: Color = Color::Magenta;
+pub const FLOOR: Color = Color::Grey;
+
+// the 16 ANSI colours, as xterm shows them
+const ANSI: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+// which of the 16 basic colours is nearest to rgb
+pub fn basic(rgb: [u8; 3]) -> u8 {
+    let far = |c: &[u8; 3]| {
+        (0..3)
+            .map(|i| (c[i] as i32 - rgb[i] as i32).pow(2))
+            .sum::<i32>()
+    };
+    (0..16).min_by_key(|&i| far(&ANSI[i])).unwrap_or(0) as u8
+}
+
+// c as red, green and blue, for drawing outside the terminal
+pub fn rgb(c: Color) -> [u8; 3] {
+    let ansi = match c {
+        Color::Rgb { r, g, b } => return [r, g, b],
+        Color::AnsiValue(n) => n,
+        Color::Reset | Color::Black => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White => 15,
+    };
+    match ansi {
+        0..=15 => ANSI[ansi as usize],
+        16..=231 => {
+            // 6x6x6 colour cube
+            let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+            let n = ansi - 16;
+            [level(n / 36), level(n / 6 % 6), level(n % 6)]
+        }
+        _ => {
+            let v = 8 + 10 * (ansi - 232);
+            [v, v, v]
+        }
+    }
+}
+
+// What shows behind the empty cells of the board - kept dark, so the stack stands out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    Plain,    // the terminal's own
+    Gradient, // night blue, deepening towards the floor
+    Stars,    // a sprinkling of dim dots
+    Tint,     // a dark shade that changes with the level
+}
+
+impl std::str::FromStr for Background {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Background, ()> {
+        match s {
+            "plain" => Ok(Background::Plain),
+            "gradient" => Ok(Background::Gradient),
+            "stars" => Ok(Background::Stars),
+            "tint" => Ok(Background::Tint),
+            _ => Err(()),
+        }
+    }
+}
+
+// How the locked blocks are coloured - the falling piece has its own colour,
+// but for by level, which colours every piece as the NES did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stack {
+    Piece,    // each in the colour of the piece it came from
+    Gradient, // by height, violet at the top shading to teal at the floor
+    Rainbow,  // hues running down the stack, cycling as time goes by
+    Level,    // two colours a level, from a table going round every ten levels
+}
+
+impl std::str::FromStr for Stack {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Stack, ()> {
+        match s {
+            "piece" => Ok(Stack::Piece),
+            "gradient" => Ok(Stack::Gradient),
+            "rainbow" => Ok(Stack::Rainbow),
+            "level" => Ok(Stack::Level),
+            _ => Err(()),
+        }
+    }
+}
+
+// the colour for a locked block in row y of a board h rows high at tick, None for the piece's own
+pub fn stack(style: Stack, y: u8, h: u8, tick: u64) -> Option<Color> {
+    let hue = match style {
+        Stack::Piece | Stack::Level => return None,
+        Stack::Gradient => 280.0 - 110.0 * y as f64 / (h.max(2) - 1) as f64,
+        Stack::Rainbow => ((y as u64 * 15 + tick / 2) % 360) as f64,
+    };
+    Some(hsv(hue, 0.7, 0.85))
+}
+
+// the two colours of each level, near enough the NES's, from level 1
+const LEVELS: [[(u8, u8, u8); 2]; 10] = [
+    [(0, 88, 248), (60, 188, 252)],
+    [(0, 168, 0), (184, 248, 24)],
+    [(216, 0, 204), (248, 120, 248)],
+    [(0, 88, 248), (88, 216, 84)],
+    [(228, 0, 88), (88, 248, 152)],
+    [(88, 248, 152), (104, 136, 252)],
+    [(248, 56, 0), (124, 124, 124)],
+    [(104, 68, 252), (168, 0, 32)],
+    [(0, 88, 248), (248, 56, 0)],
+    [(248, 56, 0), (252, 160, 68)],
+];
+
+// colour of piece p at level, by the level's two colours: J and Z take the
+// first, L and S the second and T, O and I the first, paled - other pieces
+// going round the three
+pub fn level(p: u8, level: u64) -> Color {
+    let [first, second] = LEVELS[(level.max(1) as usize - 1) % LEVELS.len()];
+    let name = pieces::get()
+        .pieces
+        .get(p as usize)
+        .map(|p| p.name.as_str());
+    let ((r, g, b), pale) = match name {
+        Some("J" | "Z") => (first, false),
+        Some("L" | "S") => (second, false),
+        Some("T" | "O" | "I") => (first, true),
+        _ => ([first, second, first][p as usize % 3], p % 3 == 2),
+    };
+    let pale = |v: u8| if pale { v / 2 + 128 } else { v };
+    Color::Rgb {
+        r: pale(r),
+        g: pale(g),
+        b: pale(b),
+    }
+}
+
+// a true colour from hue (degrees), saturation and value (0 to 1)
+fn hsv(hue: f64, s: f64, v: f64) -> Color {
+    let c = v * s;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue.rem_euclid(360.0) / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    let byte = |f: f64| ((f + m) * 255.0).round() as u8;
+    Color::Rgb {
+        r: byte(r),
+        g: byte(g),
+        b: byte(b),
+    }
+}
+
+// How the ghost piece - a shadow of where the falling piece will land - is
+// drawn, as what reads best differs between terminals and fonts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ghost {
+    Dim,     // the piece's colour, faint
+    Outline, // bracketed cells in the piece's colour
+    Glyph,   // a glyph of its own, in grey
+    Off,
+}
+
+impl std::str::FromStr for Ghost {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Ghost, ()> {
+        match s {
+            "dim" => Ok(Ghost::Dim),
+            "outline" => Ok(Ghost::Outline),
+            "glyph" => Ok(Ghost::Glyph),
+            "off" => Ok(Ghost::Off),
+            _ => Err(()),
+        }
+    }
+}
+
+// the two characters for a cell of the hint's outline, fainter than any ghost
+pub fn hint() -> StyledContent<&'static str> {
+    "[]".dark_grey()
+}
+
+// the two characters for a ghost cell of a piece coloured c, None for no ghost
+pub fn ghost(style: Ghost, c: Color) -> Option<StyledContent<&'static str>> {
+    match style {
+        Ghost::Dim => Some("██".with(c).dim()),
+        Ghost::Outline => Some("[]".with(c)),
+        Ghost::Glyph => Some("◌◌".dark_grey()),
+        Ghost::Off => None,
+    }
+}
+
+const TINTS: [(u8, u8, u8); 6] = [
+    (0, 0, 40),
+    (0, 30, 30),
+    (0, 35, 0),
+    (35, 30, 0),
+    (40, 0, 0),
+    (30, 0, 35),
+];
+
+// the two characters for empty cell (x, y) of a board h rows high, at the given level
+pub fn empty(bg: Background, x: u8, y: u8, h: u8, level: u64) -> StyledContent<&'static str> {
+    match bg {
+        Background::Plain => "  ".white(),
+        Background::Gradient => {
+            let b = 20 + (40 * y as u16 / h.max(1) as u16) as u8;
+            "  ".on(Color::Rgb { r: 0, g: 0, b })
+        }
+        Background::Stars => {
+            let h = (x as u32) << 8 | y as u32;
+            let h = h.wrapping_mul(0x9e37_79b1);
+            match (h ^ h >> 15) % 19 {
+                0 => "· ".dark_grey(),
+                5 => " .".dark_grey(),
+                _ => "  ".white(),
+            }
+        }
+        Background::Tint => {
+            let (r, g, b) = TINTS[(level as usize - 1) % TINTS.len()];
+            "  ".on(Color::Rgb { r, g, b })
+        }
+    }
+}
+
+// How the blocks of the stack and the falling piece are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Glyphs,    // a glyph of its own for each piece, on the piece's colour
+    Emoji,     // coloured emoji squares, for terminals that draw them two columns wide
+    Winter,    // snowflakes on icy colours, with snowdrifts for garbage
+    Halloween, // autumn colours, with jack-o'-lanterns for garbage
+    Seasonal,  // winter in December, halloween in October, glyphs otherwise
+    Neon,      // the pieces' colours lit up on black, as outlines
+    Pastel,    // soft pastel colours, with clouds for garbage
+    Mono,      // no colour at all, every piece told apart by its glyph alone
+    Ascii,     // plain ASCII on the 16 basic colours, for old consoles
+}
+
+impl std::str::FromStr for Theme {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Theme, ()> {
+        match s {
+            "glyphs" => Ok(Theme::Glyphs),
+            "emoji" => Ok(Theme::Emoji),
+            "winter" => Ok(Theme::Winter),
+            "halloween" => Ok(Theme::Halloween),
+            "seasonal" => Ok(Theme::Seasonal),
+            "neon" => Ok(Theme::Neon),
+            "pastel" => Ok(Theme::Pastel),
+            "mono" => Ok(Theme::Mono),
+            "ascii" => Ok(Theme::Ascii),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Theme {
+    // the theme to draw with in month (1 to 12): the season's for Seasonal
+    pub fn in_month(self, month: u32) -> Theme {
+        match (self, month) {
+            (Theme::Seasonal, 10) => Theme::Halloween,
+            (Theme::Seasonal, 12) => Theme::Winter,
+            (Theme::Seasonal, _) => Theme::Glyphs,
+            (theme, _) => theme,
+        }
+    }
+
+    // does it take emoji, drawn two columns wide?
+    pub fn emoji(self) -> bool {
+        matches!(self, Theme::Emoji | Theme::Halloween)
+    }
+}
+
+// this month, 1 to 12, going by the system clock in UTC
+pub fn month() -> u32 {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // days since 1970 to a month, by way of years starting in March
+    let days = secs / 86400 + 719468;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153; // 0 for March
+    if m < 10 {
+        m as u32 + 3
+    } else {
+        m as u32 - 9
+    }
+}
+
+const ICE: [Color; 4] = [
+    Color::Rgb {
+        r: 170,
+        g: 220,
+        b: 255,
+    },
+    Color::Rgb {
+        r: 120,
+        g: 180,
+        b: 240,
+    },
+    Color::Rgb {
+        r: 200,
+        g: 235,
+        b: 245,
+    },
+    Color::Rgb {
+        r: 90,
+        g: 140,
+        b: 210,
+    },
+];
+
+const AUTUMN: [Color; 4] = [
+    Color::AnsiValue(208), // pumpkin
+    Color::AnsiValue(93),  // purple
+    Color::AnsiValue(70),  // moss
+    Color::AnsiValue(130), // rust
+];
+
+const PASTEL: [Color; 5] = [
+    Color::AnsiValue(217), // pink
+    Color::AnsiValue(158), // mint
+    Color::AnsiValue(153), // sky
+    Color::AnsiValue(229), // lemon
+    Color::AnsiValue(183), // lilac
+];
+
+// Whether to draw in colour at all - without, the mono theme is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    Auto, // unless NO_COLOR is set or the output isn't a terminal
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = ();
+    fn from_str(s: &str) -> Result<ColorChoice, ()> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ColorChoice {
+    // colour or not, for output going to a terminal or not
+    pub fn colors(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                terminal && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+// the pieces' glyphs in the mono theme, one each for as many as there are
+pub const MONO: [&str; 10] = ["▓▓", "()", "██", "░░", "<>", "[]", "▒▒", "{}", "%%", "$$"];
+
+// the emoji squares, and about what colour each is drawn in
+const SQUARES: [(&str, [u8; 3]); 8] = [
+    ("🟥", [255, 0, 0]),
+    ("🟧", [255, 135, 0]),
+    ("🟨", [255, 255, 0]),
+    ("🟩", [0, 205, 0]),
+    ("🟦", [0, 205, 255]), // light blue, as most fonts have it
+    ("🔵", [0, 0, 238]),   // no dark blue square, so a circle
+    ("🟪", [205, 0, 205]),
+    ("🟫", [150, 75, 0]),
+];
+
+// the square nearest colour c
+fn square(c: Color) -> &'static str {
+    let [r, g, b] = rgb(c).map(i32::from);
+    let far = |&(_, [r2, g2, b2]): &(&str, [u8; 3])| {
+        let d = [r - r2 as i32, g - g2 as i32, b - b2 as i32];
+        d.iter().map(|d| d * d).sum::<i32>()
+    };
+    SQUARES.iter().min_by_key(|s| far(s)).map_or("🟥", |s| s.0)
+}
+
+// the two columns for a filled cell with colour layer value v, in theme as
+// in_month has it for the date
+pub fn block(theme: Theme, v: u8) -> StyledContent<&'static str> {
+    match theme {
+        Theme::Glyphs => {
+            let glyph = match v {
+                1 => "●●",
+                2 => "◎◎",
+                3 => "□□",
+                4 => "◦◦",
+                5 => "○○",
+                6 => "◼◼",
+                board::GARBAGE => "▒▒",
+                board::ITEM => "◆◆",
+                board::FLOOR => "██",
+                _ => "◉◉",
+            };
+            match v {
+                board::ITEM => glyph.white().on(ITEM),
+                board::FLOOR => glyph.with(FLOOR),
+                _ => glyph.black().on(color(v - 1)),
+            }
+        }
+        Theme::Emoji => match v {
+            board::GARBAGE => "⬜".stylize(),
+            board::ITEM => "💎".stylize(),
+            board::FLOOR => "⬛".stylize(),
+            _ => square(color(v - 1)).stylize(),
+        },
+        Theme::Winter => match v {
+            board::GARBAGE => "░░".white(),
+            board::ITEM => "✶✶".white().on(ITEM),
+            board::FLOOR => "▓▓".white(),
+            _ => ["❄ ", "❅ ", "❆ "][v as usize % 3]
+                .white()
+                .on(ICE[(v as usize - 1) % ICE.len()]),
+        },
+        Theme::Halloween => match v {
+            board::GARBAGE => "🎃".stylize(),
+            board::ITEM => "👻".stylize(),
+            board::FLOOR => "██".with(Color::AnsiValue(52)),
+            _ => "▚▚".black().on(AUTUMN[(v as usize - 1) % AUTUMN.len()]),
+        },
+        // the season's theme is worked out once, by in_month, for the
+        // whole game: here it is only the theme for the rest of the year
+        Theme::Seasonal => block(Theme::Glyphs, v),
+        Theme::Neon => match v {
+            board::GARBAGE => "▒▒".with(GARBAGE).on(Color::Black),
+            board::ITEM => "◆◆".with(ITEM).on(Color::Black),
+            board::FLOOR => "▔▔".with(FLOOR).on(Color::Black),
+            _ => "▣▣".with(color(v - 1)).on(Color::Black),
+        },
+        Theme::Pastel => match v {
+            board::GARBAGE => "☁ ".white().on(Color::AnsiValue(250)),
+            board::ITEM => "✿ ".white().on(ITEM),
+            board::FLOOR => "██".with(Color::AnsiValue(252)),
+            _ => "◖◗".dark_grey().on(PASTEL[(v as usize - 1) % PASTEL.len()]),
+        },
+        Theme::Mono => match v {
+            board::GARBAGE => "##".stylize(),
+            board::ITEM => "**".stylize(),
+            board::FLOOR => "==".stylize(),
+            _ => MONO.get(v as usize - 1).unwrap_or(&"??").stylize(),
+        },
+        Theme::Ascii => match v {
+            board::GARBAGE => "##".white().on(GARBAGE),
+            board::ITEM => "**".white().on(ITEM),
+            board::FLOOR => "==".with(FLOOR),
+            _ => "[]".black().on(color(v - 1)),
+        },
+    }
+}
+
+// The config's [glyphs], worked out: for each kind of cell by colour layer
+// value, 0 for empty, what is drawn over what the theme has.
+#[derive(Clone, Default)]
+pub struct Skin {
+    looks: BTreeMap<u8, Look>,
+}
+
+#[derive(Clone)]
+struct Look {
+    text: Option<String>,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl Skin {
+    // glyphs is keyed by piece name, "garbage", "item", "floor" or "empty"
+    pub fn new(glyphs: &BTreeMap<String, Glyph>) -> Result<Skin, String> {
+        let set = pieces::get();
+        let mut looks = BTreeMap::new();
+        for (name, g) in glyphs {
+            let v = match name.as_str() {
+                "empty" => 0,
+                "garbage" => board::GARBAGE,
+                "item" => board::ITEM,
+                "floor" => board::FLOOR,
+                name => match set.pieces.iter().position(|p| p.name == name) {
+                    Some(p) => p as u8 + 1,
+                    None => return Err(format!("glyphs: no piece or cell called '{}'", name)),
+                },
+            };
+            if let Some(text) = g.text.as_ref().filter(|t| t.width() != 2) {
+                return Err(format!(
+                    "glyphs: {}: '{}' isn't two columns wide",
+                    name, text
+                ));
+            }
+            let color = |c: &Option<_>| match c {
+                Some(c) => parse_color(c)
+                    .map(Some)
+                    .ok_or_else(|| format!("glyphs: {}: not a colour", name)),
+                None => Ok(None),
+            };
+            let look = Look {
+                text: g.text.clone(),
+                fg: color(&g.fg)?,
+                bg: color(&g.bg)?,
+            };
+            looks.insert(v, look);
+        }
+        Ok(Skin { looks })
+    }
+
+    // s, the theme's look for a cell with colour layer value v, as the config would have it
+    pub fn dress<'a>(&'a self, v: u8, s: StyledContent<&'a str>) -> StyledContent<&'a str> {
+        let Some(look) = self.looks.get(&v) else {
+            return s;
+        };
+        let mut style = *s.style();
+        style.foreground_color = look.fg.or(style.foreground_color);
+        style.background_color = look.bg.or(style.background_color);
+        StyledContent::new(style, look.text.as_deref().unwrap_or(s.content()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seasonal_is_the_seasons_theme() {
+        assert_eq!(Theme::Seasonal.in_month(10), Theme::Halloween);
+        assert_eq!(Theme::Seasonal.in_month(12), Theme::Winter);
+        assert_eq!(Theme::Seasonal.in_month(5), Theme::Glyphs);
+        assert_eq!(Theme::Winter.in_month(10), Theme::Winter);
+        assert!(Theme::Seasonal.in_month(10).emoji());
+    }
+}
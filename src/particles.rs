@@ -0,0 +1,109 @@
+use crate::cast::stdout;
+use crate::layout::Layout;
+use crossterm::style::{self, Color, Stylize};
+use crossterm::{cursor, Result};
+use rand::prelude::*;
+use std::time::Instant;
+use tetrisct::view::View;
+
+// Sparks thrown out of cleared rows, kept by the render thread: each burst
+// a few particles flying off from along the row, slowing as they go and
+// dimming from ✦ to ･ to ﾟ before they die. They stay inside the board -
+// which is drawn afresh every frame, so one gone leaves nothing behind -
+// and there are never more than MAX, the oldest giving way, for the
+// frame's drawing to stay about as cheap as it was.
+
+const MAX: usize = 64;
+const PER_ROW: usize = 8;
+const LIFE: (f32, f32) = (0.3, 0.7); // seconds, shortest and longest
+const SPEED: (f32, f32) = (3.0, 9.0); // cells a second
+const DRAG: f32 = 3.0; // ... lost each second, as a share of it
+const GLYPHS: [char; 3] = ['✦', '･', 'ﾟ'];
+const ASCII: [char; 3] = ['*', '.', '\''];
+
+struct Particle {
+    x: f32, // where, in board cells
+    y: f32,
+    dx: f32, // cells a second
+    dy: f32,
+    age: f32, // seconds
+    life: f32,
+}
+
+pub struct Particles {
+    list: Vec<Particle>,
+    last: Instant, // when they last moved
+}
+
+impl Default for Particles {
+    fn default() -> Particles {
+        Particles {
+            list: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+}
+
+impl Particles {
+    // a burst from board row y, width cells wide
+    pub fn burst(&mut self, y: u8, width: u8) {
+        let mut rng = thread_rng();
+        for _ in 0..PER_ROW {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(SPEED.0..SPEED.1);
+            self.list.push(Particle {
+                x: rng.gen_range(0.0..width as f32),
+                y: y as f32 + 0.5,
+                dx: speed * angle.cos(),
+                dy: speed * angle.sin(),
+                age: 0.0,
+                life: rng.gen_range(LIFE.0..LIFE.1),
+            });
+        }
+        let over = self.list.len().saturating_sub(MAX);
+        self.list.drain(..over);
+    }
+
+    // on by the time since they last moved, the dead ones gone
+    pub fn step(&mut self) {
+        let dt = self.last.elapsed().as_secs_f32();
+        self.last = Instant::now();
+        let slow = (1.0 - DRAG * dt).max(0.0);
+        for p in &mut self.list {
+            p.x += p.dx * dt;
+            p.y += p.dy * dt;
+            p.dx *= slow;
+            p.dy *= slow;
+            p.age += dt;
+        }
+        self.list.retain(|p| p.age < p.life);
+    }
+
+    // on board v, drawn in lay, those over empty cells of the rows shown -
+    // not to cut a wide glyph in two
+    pub fn draw(&self, v: &View, lay: &Layout) -> Result<()> {
+        let top = v.top(lay.rows) as f32;
+        let glyphs = if lay.ascii { ASCII } else { GLYPHS };
+        for p in &self.list {
+            let inside =
+                (0.0..v.width as f32).contains(&p.x) && (top..top + lay.rows as f32).contains(&p.y);
+            if !inside || v.cell(p.x as u8, p.y as u8) != 0 {
+                continue;
+            }
+            let glyph = glyphs[(3.0 * p.age / p.life) as usize % 3];
+            let c = match p.age < p.life / 2.0 {
+                true => Color::Yellow,
+                false => Color::White,
+            };
+            // half a cell at a time, a cell being two columns
+            let col = lay.x + 1 + (2.0 * p.x) as u16;
+            let row = (p.y - top) as u16 + 1;
+            crossterm::queue!(
+                stdout(),
+                cursor::MoveTo(col, row),
+                style::PrintStyledContent(glyph.with(c))
+            )?;
+        }
+        Ok(())
+    }
+}
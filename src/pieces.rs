@@ -0,0 +1,143 @@
+use crate::shape::Shape;
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::OnceLock;
+
+// The default piece set - see the file for a description of the format.
+const DEFAULT: &str = include_str!("../assets/pieces.toml");
+
+static SET: OnceLock<PieceSet> = OnceLock::new();
+
+pub struct Piece {
+    pub name: String,
+    pub color: Color,
+    pub shape: Shape,
+}
+
+pub struct PieceSet {
+    pub pieces: Vec<Piece>,
+    pub kicks: Vec<(i8, i8)>,
+}
+
+#[derive(Deserialize)]
+struct RawSet {
+    kicks: Vec<(i8, i8)>,
+    piece: Vec<RawPiece>,
+}
+
+#[derive(Deserialize)]
+struct RawPiece {
+    name: String,
+    color: RawColor,
+    orientations: Vec<Vec<String>>,
+}
+
+// a colour as files give it: an ANSI number, a name or "#rrggbb"
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RawColor {
+    Ansi(u8),
+    Named(String),
+}
+
+pub fn parse_color(c: &RawColor) -> Option<Color> {
+    match c {
+        RawColor::Ansi(v) => Some(Color::AnsiValue(*v)),
+        RawColor::Named(s) => match s.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => {
+                let v = u32::from_str_radix(hex, 16).ok()?;
+                Some(Color::Rgb {
+                    r: (v >> 16) as u8,
+                    g: (v >> 8) as u8,
+                    b: v as u8,
+                })
+            }
+            Some(_) => None,
+            None => Color::try_from(s.as_str()).ok(),
+        },
+    }
+}
+
+fn parse_orientation(rows: &[String]) -> Option<[(u8, u8); 4]> {
+    let cells: Vec<(u8, u8)> = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(i, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(j, _)| (i, j))
+        })
+        .filter(|&(i, j)| i < 4 && j < 4)
+        .map(|(i, j)| (i as u8, j as u8))
+        .collect();
+    // anchor at the top left corner, whatever the padding in the file
+    let top = cells.iter().map(|c| c.0).min()?;
+    let left = cells.iter().map(|c| c.1).min()?;
+    let cells: Vec<(u8, u8)> = cells.iter().map(|c| (c.0 - top, c.1 - left)).collect();
+    cells.try_into().ok()
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl PieceSet {
+    pub fn parse(src: &str) -> io::Result<PieceSet> {
+        let raw: RawSet = toml::from_str(src).map_err(|e| invalid(e.to_string()))?;
+        if raw.piece.is_empty() {
+            return Err(invalid("piece set is empty".to_string()));
+        }
+        let mut pieces = Vec::new();
+        for p in raw.piece {
+            let color = parse_color(&p.color)
+                .ok_or_else(|| invalid(format!("piece {}: unknown colour", p.name)))?;
+            if p.orientations.len() != 4 {
+                return Err(invalid(format!("piece {}: needs 4 orientations", p.name)));
+            }
+            let mut cells = [[(0, 0); 4]; 4];
+            for (r, o) in p.orientations.iter().enumerate() {
+                cells[r] = parse_orientation(o).ok_or_else(|| {
+                    invalid(format!(
+                        "piece {}: orientation {} is not 4 squares",
+                        p.name, r
+                    ))
+                })?;
+            }
+            pieces.push(Piece {
+                name: p.name,
+                color,
+                shape: Shape::new(cells),
+            });
+        }
+        Ok(PieceSet {
+            pieces,
+            kicks: raw.kicks,
+        })
+    }
+
+    pub fn from_file(path: &str) -> io::Result<PieceSet> {
+        let src = std::fs::read_to_string(path)?;
+        PieceSet::parse(&src).map_err(|e| invalid(format!("{}: {}", path, e)))
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        PieceSet::parse(DEFAULT).expect("built-in piece set")
+    }
+}
+
+// install the piece set used for the rest of the run - first call wins
+pub fn init(set: PieceSet) {
+    SET.set(set).ok();
+}
+
+pub fn get() -> &'static PieceSet {
+    SET.get_or_init(PieceSet::default)
+}
+
+pub fn shape(p: u8) -> &'static Shape {
+    &get().pieces[p as usize].shape
+}
@@ -0,0 +1,133 @@
+use crate::board::{Board, GARBAGE, ITEM};
+use crate::fumen::Puzzle;
+use crate::pieces;
+use std::io;
+
+// Board positions as text, for practising a stacking situation again and
+// again and for sharing it. One line per row, top to bottom - rows left
+// out at the top are empty - and one character per cell: '.' (or a space)
+// for an empty cell, the first letter of a piece's name for its blocks,
+// '#' for garbage and '*' for an item block. Rows can also be run
+// together on one line, split by '/'. A puzzle adds the pieces to play on
+// it, in order, on a line of their own as "queue TSZ".
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// what goes in cell c, if it means anything
+fn cell(c: char) -> Option<u8> {
+    match c {
+        '.' | ' ' => Some(0),
+        '#' => Some(GARBAGE),
+        '*' => Some(ITEM),
+        c => pieces::get()
+            .pieces
+            .iter()
+            .position(|p| p.name.starts_with(c.to_ascii_uppercase()))
+            .map(|p| p as u8 + 1),
+    }
+}
+
+pub fn parse(src: &str, width: u8, height: u8) -> io::Result<Board> {
+    let rows: Vec<&str> = src
+        .lines()
+        .flat_map(|l| l.split('/'))
+        .map(|l| l.trim_end())
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    if rows.len() > height as usize {
+        return Err(invalid(format!(
+            "{} rows, the board has {}",
+            rows.len(),
+            height
+        )));
+    }
+    let mut b = Board::new(width, height);
+    let top = height - rows.len() as u8;
+    for (i, row) in rows.iter().enumerate() {
+        let y = top + i as u8;
+        if row.chars().count() > width as usize {
+            let msg = format!("row {}: wider than the board's {} cells", i + 1, width);
+            return Err(invalid(msg));
+        }
+        for (x, c) in row.chars().enumerate() {
+            let v = cell(c).ok_or_else(|| invalid(format!("row {}: what is '{}'?", i + 1, c)))?;
+            b.set(x as u8, y, v);
+        }
+        if b.is_filled(y) {
+            return Err(invalid(format!(
+                "row {}: full rows would clear at once",
+                i + 1
+            )));
+        }
+    }
+    Ok(b)
+}
+
+// a stack and, if there is a queue line, its pieces
+pub fn parse_puzzle(src: &str, width: u8, height: u8) -> io::Result<Puzzle> {
+    let mut rows = String::new();
+    let mut pieces = Vec::new();
+    for line in src.lines() {
+        match line.trim().strip_prefix("queue") {
+            Some(q) => pieces = queue(q.trim())?,
+            None => {
+                rows += line;
+                rows.push('\n');
+            }
+        }
+    }
+    Ok(Puzzle {
+        board: parse(&rows, width, height)?,
+        queue: pieces,
+    })
+}
+
+pub fn load(path: &std::path::Path, width: u8, height: u8) -> io::Result<Puzzle> {
+    let src = std::fs::read_to_string(path)?;
+    parse_puzzle(&src, width, height)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+// b in the same form, from its highest filled row down
+pub fn to_text(b: &Board) -> String {
+    let mut s = String::new();
+    for row in b
+        .to_ascii()
+        .lines()
+        .skip_while(|r| r.chars().all(|c| c == '.'))
+    {
+        s += &row.replace('=', "#"); // the floor, as garbage
+        s.push('\n');
+    }
+    s
+}
+
+// puzzle p in the same form, the queue after the stack
+pub fn puzzle_text(p: &Puzzle) -> String {
+    let mut s = to_text(&p.board);
+    if !p.queue.is_empty() {
+        s += &format!("queue {}\n", letters(&p.queue));
+    }
+    s
+}
+
+// a queue of pieces as their letters, "TSZ"
+pub fn letters(queue: &[u8]) -> String {
+    let names = &pieces::get().pieces;
+    queue
+        .iter()
+        .filter_map(|&p| names.get(p as usize)?.name.chars().next())
+        .collect()
+}
+
+// ... and back
+pub fn queue(s: &str) -> io::Result<Vec<u8>> {
+    s.chars()
+        .map(|c| match cell(c) {
+            Some(v) if v >= 1 && v as usize <= pieces::get().pieces.len() => Ok(v - 1),
+            _ => Err(invalid(format!("what piece is '{}'?", c))),
+        })
+        .collect()
+}
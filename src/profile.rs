@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::span::EnteredSpan;
+
+// Where a frame's time goes, for the overlay F3 brings up to find what
+// loses frames over a slow link: the game loop and the render thread each
+// time their phases with a span, and once a second the totals become the
+// milliseconds each took a frame, on average and at worst, with the scenes
+// the renderer was too busy to take. Timing is always on - an Instant
+// either end of a phase costs next to nothing - so the overlay has the
+// last second to show the moment it comes up.
+
+#[derive(Clone, Copy)]
+pub enum Stage {
+    Sim,   // the game's and the opponent's ticks
+    Input, // keys and moves
+    Scene, // the frame put together for the renderer
+    Draw,  // ... drawn, on the render thread
+    Flush, // ... and written to the terminal
+}
+
+const PHASES: usize = 5;
+const NAMES: [&str; PHASES] = ["Sim", "Input", "Scene", "Draw", "Flush"];
+pub const EVERY: Duration = Duration::from_secs(1); // between reports
+
+// nanoseconds spent in each phase since the last report, the longest, and
+// the spans timed
+static TOTAL: [AtomicU64; PHASES] = [const { AtomicU64::new(0) }; PHASES];
+static WORST: [AtomicU64; PHASES] = [const { AtomicU64::new(0) }; PHASES];
+static SPANS: [AtomicU64; PHASES] = [const { AtomicU64::new(0) }; PHASES];
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+// a phase being timed, until it goes out of scope
+pub struct Span {
+    stage: Stage,
+    start: Instant,
+    _traced: EnteredSpan, // ... and seen as one by any subscriber
+}
+
+pub fn span(p: Stage) -> Span {
+    let span = match p {
+        Stage::Sim => tracing::trace_span!("sim"),
+        Stage::Input => tracing::trace_span!("input"),
+        Stage::Scene => tracing::trace_span!("scene"),
+        Stage::Draw => tracing::trace_span!("draw"),
+        Stage::Flush => tracing::trace_span!("flush"),
+    };
+    Span {
+        stage: p,
+        start: Instant::now(),
+        _traced: span.entered(),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let i = self.stage as usize;
+        let ns = self.start.elapsed().as_nanos() as u64;
+        TOTAL[i].fetch_add(ns, Ordering::Relaxed);
+        WORST[i].fetch_max(ns, Ordering::Relaxed);
+        SPANS[i].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// a scene the renderer didn't take
+pub fn dropped() {
+    DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+// The overlay's lines, worked out afresh every second from the spans
+// since the last time.
+pub struct Report {
+    since: Instant,
+    pub lines: Vec<String>,
+}
+
+impl Default for Report {
+    fn default() -> Report {
+        take(); // what went before it came up isn't a second's worth
+        Report {
+            since: Instant::now(),
+            lines: vec!["Profile ...".to_string()],
+        }
+    }
+}
+
+impl Report {
+    // the lines brought up to date, if a second has gone by
+    pub fn update(&mut self) {
+        let secs = self.since.elapsed().as_secs_f64();
+        if secs < EVERY.as_secs_f64() {
+            return;
+        }
+        self.since = Instant::now();
+        let (spans, dropped) = take();
+        let drawn = spans[Stage::Draw as usize].2;
+        self.lines = vec![format!(
+            "Profile {:.0} fps  {} dropped",
+            drawn as f64 / secs,
+            dropped
+        )];
+        for (name, (total, worst, n)) in NAMES.iter().zip(spans) {
+            let ms = |ns: u64| ns as f64 / 1e6;
+            let avg = total.checked_div(n).unwrap_or(0);
+            let s = format!("{:<5} {:6.2}ms  max {:6.2}ms", name, ms(avg), ms(worst));
+            self.lines.push(s);
+        }
+    }
+}
+
+// the totals, longest and spans of each phase, and the scenes dropped,
+// since the last time - starting again from nothing
+fn take() -> ([(u64, u64, u64); PHASES], u64) {
+    let mut spans = [(0, 0, 0); PHASES];
+    for (i, s) in spans.iter_mut().enumerate() {
+        *s = (
+            TOTAL[i].swap(0, Ordering::Relaxed),
+            WORST[i].swap(0, Ordering::Relaxed),
+            SPANS[i].swap(0, Ordering::Relaxed),
+        );
+    }
+    (spans, DROPPED.swap(0, Ordering::Relaxed))
+}
+
+// every span from here on written to path as it closes, with its time
+#[cfg(feature = "trace")]
+pub fn trace(path: &std::path::Path) -> std::io::Result<()> {
+    use tracing_subscriber::fmt::format::FmtSpan;
+    let file = std::fs::File::create(path)?;
+    tracing_subscriber::fmt()
+        .with_writer(std::sync::Mutex::new(file))
+        .with_span_events(FmtSpan::CLOSE)
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .init();
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use crate::config::Config;
+use crate::game::{Game, MOVES};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+// The engine as a Python module, headless: the real rules for training
+// agents against, reset/step/observe in the manner of gym. Built with the
+// python feature by maturin (pip install . does it), as the module tetrisct:
+//
+//     from tetrisct import Env
+//     env = Env()
+//     obs = env.reset(seed=1)
+//     obs, reward, done, info = env.step(Env.HARD_DROP)
+//
+// An observation is the board as rows of cells, counting the falling piece:
+// 0 empty, 1 to 7 a tetromino's, 253 floor, 254 garbage holding an item,
+// 255 garbage. The reward is the points scored by the step. The moves are
+// numbered as in the C API, NOOP making none.
+
+const NOOP: i32 = -1;
+
+type Cells = Vec<Vec<u8>>; // an observation, rows of cells
+
+// what a step gives: (observation, reward, done, info)
+type Step<'py> = (Cells, u32, bool, Bound<'py, PyDict>);
+
+// A game, each step making the move given, if any, then letting ticks ticks
+// of 10ms go by. Like the games it wraps, an Env belongs to the thread that
+// made it.
+#[pyclass(unsendable)]
+struct Env {
+    cfg: Config,
+    ticks: u32,
+    game: Option<Game>,
+}
+
+#[pymethods]
+impl Env {
+    #[classattr]
+    const NOOP: i32 = NOOP;
+    #[classattr]
+    const LEFT: i32 = 0;
+    #[classattr]
+    const RIGHT: i32 = 1;
+    #[classattr]
+    const ROTATE: i32 = 2;
+    #[classattr]
+    const HARD_DROP: i32 = 3;
+    #[classattr]
+    const SONIC_DROP: i32 = 4;
+    #[classattr]
+    const ITEM: i32 = 5;
+    #[classattr]
+    const HOLD: i32 = 6;
+
+    #[classattr]
+    #[pyo3(name = "ACTIONS")]
+    fn actions() -> Vec<i32> {
+        [NOOP].into_iter().chain(0..MOVES.len() as i32).collect()
+    }
+
+    // on a width x height board, 0 for the default, 10 x 20
+    #[new]
+    #[pyo3(signature = (width=0, height=0, ticks=1))]
+    fn new(width: u8, height: u8, ticks: u32) -> PyResult<Env> {
+        let mut cfg = Config::default();
+        if width > 0 {
+            cfg.width = width;
+        }
+        if height > 0 {
+            cfg.height = height;
+        }
+        let cfg = cfg
+            .validate()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Env {
+            cfg,
+            ticks,
+            game: None,
+        })
+    }
+
+    // a new game dealt from seed, and its first observation
+    #[pyo3(signature = (seed=0))]
+    fn reset(&mut self, seed: u64) -> Cells {
+        let g = self.game.insert(Game::with_seed(&self.cfg, seed));
+        observe(g)
+    }
+
+    // (observation, reward, done, info) after action, one of ACTIONS
+    fn step<'py>(&mut self, py: Python<'py>, action: i32) -> PyResult<Step<'py>> {
+        let ticks = self.ticks;
+        let g = self.game()?;
+        let m = match action {
+            NOOP => None,
+            _ => match usize::try_from(action).ok().and_then(|m| MOVES.get(m)) {
+                Some(&m) => Some(m),
+                None => return Err(PyValueError::new_err(format!("no such action: {}", action))),
+            },
+        };
+        let score = g.score;
+        if let Some(m) = m.filter(|_| !g.over) {
+            g.apply(m);
+        }
+        let done = !(0..ticks).all(|_| g.do_tick());
+        let reward = g.score - score;
+        Ok((observe(g), reward, done, info(py, g)?))
+    }
+
+    // the board as rows of cells, top row first
+    fn observe(&mut self) -> PyResult<Cells> {
+        Ok(observe(self.game()?))
+    }
+
+    // the score and lines so far, the tick and the falling piece
+    fn info<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        info(py, self.game()?)
+    }
+
+    fn close(&mut self) {
+        self.game = None;
+    }
+}
+
+impl Env {
+    fn game(&mut self) -> PyResult<&mut Game> {
+        (self.game.as_mut()).ok_or_else(|| PyRuntimeError::new_err("step before reset"))
+    }
+}
+
+fn observe(g: &Game) -> Cells {
+    let (w, h) = (g.board.width(), g.board.height());
+    (0..h)
+        .map(|y| (0..w).map(|x| g.cell(x, y)).collect())
+        .collect()
+}
+
+fn info<'py>(py: Python<'py>, g: &Game) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new(py);
+    d.set_item("score", g.score)?;
+    d.set_item("lines", g.lines)?;
+    d.set_item("tick", g.tick)?;
+    d.set_item("piece", g.p)?;
+    Ok(d)
+}
+
+#[pymodule]
+fn tetrisct(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Env>()
+}
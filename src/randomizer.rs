@@ -0,0 +1,231 @@
+use crate::pieces;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+// Where the sequence of pieces comes from.
+pub trait Randomizer {
+    // the next tetromino, 0..n for a piece set of n
+    fn next(&mut self, rng: &mut dyn RngCore) -> u8;
+
+    // the pieces still to be dealt from the current bag, and how many a
+    // whole bag holds, for randomizers that deal from one
+    fn bag(&self) -> Option<(&[u8], usize)> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Kind {
+    #[serde(rename = "random")]
+    Random,
+    #[serde(rename = "7-bag")]
+    Bag7,
+    #[serde(rename = "14-bag")]
+    Bag14,
+    #[serde(rename = "tgm")]
+    Tgm,
+    #[serde(rename = "nes")]
+    Nes,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Kind, ()> {
+        match s {
+            "random" => Ok(Kind::Random),
+            "7-bag" => Ok(Kind::Bag7),
+            "14-bag" => Ok(Kind::Bag14),
+            "tgm" => Ok(Kind::Tgm),
+            "nes" => Ok(Kind::Nes),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn new(kind: Kind) -> Box<dyn Randomizer> {
+    let n = pieces::get().pieces.len() as u8;
+    match kind {
+        Kind::Random => Box::new(Random { n }),
+        Kind::Bag7 => Box::new(Bag::new(n, 1)),
+        Kind::Bag14 => Box::new(Bag::new(n, 2)),
+        Kind::Tgm => Box::new(History::new(n)),
+        Kind::Nes => Box::new(Reroll { n, last: None }),
+    }
+}
+
+// every piece equally likely every time
+pub struct Random {
+    n: u8,
+}
+
+impl Randomizer for Random {
+    fn next(&mut self, rng: &mut dyn RngCore) -> u8 {
+        (rng.next_u32() % self.n as u32) as u8
+    }
+}
+
+// deal out shuffled bags holding `copies` of every piece
+pub struct Bag {
+    n: u8,
+    copies: u8,
+    bag: Vec<u8>,
+}
+
+impl Bag {
+    pub fn new(n: u8, copies: u8) -> Bag {
+        Bag {
+            n,
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self, mut rng: &mut dyn RngCore) -> u8 {
+        if self.bag.is_empty() {
+            self.bag = (0..self.n)
+                .cycle()
+                .take((self.n * self.copies) as usize)
+                .collect();
+            self.bag.shuffle(&mut rng);
+        }
+        self.bag.pop().unwrap()
+    }
+
+    fn bag(&self) -> Option<(&[u8], usize)> {
+        Some((&self.bag, (self.n * self.copies) as usize))
+    }
+}
+
+// NES style: one roll among the pieces and a dummy, and one more among the
+// pieces alone if that came up the dummy or the piece just dealt - making a
+// repeat rarer, 1 in 28 rather than 1 in 7, but forgetting everything
+// before, so a piece can go missing for as long as chance has it
+pub struct Reroll {
+    n: u8,
+    last: Option<u8>,
+}
+
+impl Randomizer for Reroll {
+    fn next(&mut self, rng: &mut dyn RngCore) -> u8 {
+        let mut p = (rng.next_u32() % (self.n as u32 + 1)) as u8;
+        if p == self.n || Some(p) == self.last {
+            p = (rng.next_u32() % self.n as u32) as u8;
+        }
+        self.last = Some(p);
+        p
+    }
+}
+
+// TGM style: reroll up to ROLLS times while the piece is among the last 4 dealt
+pub struct History {
+    n: u8,
+    history: [u8; 4],
+    first: bool,
+}
+
+const ROLLS: usize = 6;
+
+fn find(name: &str) -> u8 {
+    let set = &pieces::get().pieces;
+    set.iter().position(|p| p.name == name).unwrap_or(0) as u8
+}
+
+impl History {
+    pub fn new(n: u8) -> History {
+        let (s, z) = (find("S"), find("Z"));
+        History {
+            n,
+            history: [z, s, z, s],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for History {
+    fn next(&mut self, rng: &mut dyn RngCore) -> u8 {
+        let mut p = 0;
+        for _ in 0..ROLLS {
+            p = (rng.next_u32() % self.n as u32) as u8;
+            // nor does the game open with an O
+            let banned = self.first && p == find("O");
+            if !self.history.contains(&p) && !banned {
+                break;
+            }
+        }
+        self.first = false;
+        self.history.rotate_left(1);
+        self.history[3] = p;
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn deal(kind: Kind, seed: u64, n: usize) -> Vec<u8> {
+        let mut r = new(kind);
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| r.next(&mut rng)).collect()
+    }
+
+    #[test]
+    fn a_bag_deals_every_piece_once_a_round() {
+        for (kind, copies) in [(Kind::Bag7, 1), (Kind::Bag14, 2)] {
+            let round = 7 * copies;
+            for seed in 0..20 {
+                for bag in deal(kind, seed, round * 10).chunks(round) {
+                    let mut bag = bag.to_vec();
+                    bag.sort();
+                    let all: Vec<u8> = (0..7)
+                        .flat_map(|p| std::iter::repeat_n(p, copies))
+                        .collect();
+                    assert_eq!(bag, all, "{:?} seed {}", kind, seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_bag_left_shrinks_as_it_deals() {
+        let mut r = new(Kind::Bag7);
+        let mut rng = StdRng::seed_from_u64(1);
+        let p = r.next(&mut rng);
+        let (left, round) = r.bag().unwrap();
+        assert_eq!((left.len(), round), (6, 7));
+        assert!(!left.contains(&p));
+    }
+
+    #[test]
+    fn a_seed_deals_the_same_pieces() {
+        for kind in [Kind::Random, Kind::Bag7, Kind::Bag14, Kind::Tgm, Kind::Nes] {
+            assert_eq!(deal(kind, 7, 200), deal(kind, 7, 200), "{:?}", kind);
+            assert_ne!(deal(kind, 7, 200), deal(kind, 8, 200), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn tgm_seldom_opens_with_s_z_or_o() {
+        let bad = [find("S"), find("Z"), find("O")];
+        let n = (0..1000)
+            .filter(|&seed| bad.contains(&deal(Kind::Tgm, seed, 1)[0]))
+            .count();
+        assert!(n < 30, "{} in 1000", n); // (3/7)^6 of the time, six rolls all failing
+    }
+
+    #[test]
+    fn history_and_reroll_make_repeats_rarer() {
+        let repeats = |kind| {
+            let d = deal(kind, 3, 7000);
+            d.windows(2).filter(|w| w[0] == w[1]).count()
+        };
+        let random = repeats(Kind::Random); // about 1 in 7
+        assert!(repeats(Kind::Nes) < random / 2); // about 1 in 28
+        assert!(repeats(Kind::Tgm) < random / 10);
+    }
+}
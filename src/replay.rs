@@ -0,0 +1,286 @@
+use crate::board::Board;
+use crate::config::Config;
+use crate::game::{Game, Move};
+use crate::input::{InputSource, Script};
+use crate::keymap::Action;
+use crate::position;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// A game as its seed, its settings, the stack it started from if not an
+// empty board and the puzzle's pieces if any, and the moves made - enough
+// to play it again tick for tick. On disk: all but the moves as TOML, a
+// line of dashes, then a "tick:move" line for every move.
+//
+// For tool-assisted runs there is also a bare form, easy to write and edit
+// by hand or by script: a "seed N" line, optionally "end N", a "board"
+// line with the starting stack's rows split by '/' and a "queue" line of
+// piece letters, and the "tick:move" lines. It plays under whatever settings are in effect, and
+// without an end it plays until the game is over.
+
+pub struct Replay {
+    pub seed: u64,
+    pub config: Config,
+    pub end: u64,                // tick the recording stopped at
+    pub board: Option<Board>,    // the starting stack, if any
+    pub queue: Vec<u8>,          // pieces dealt first, for a puzzle
+    pub moves: Vec<(u64, Move)>, // each move, with the tick it was made after
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Header {
+    seed: u64,
+    end: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    board: Option<String>, // as text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queue: Option<String>, // piece letters
+    config: Config,
+}
+
+const SEPARATOR: &str = "---";
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Replay {
+    // start recording g
+    pub fn new(g: &Game) -> Replay {
+        Replay {
+            seed: g.seed,
+            config: g.cfg().clone(),
+            end: 0,
+            board: (!g.board.is_empty()).then(|| g.board.clone()),
+            queue: g.puzzle.clone(),
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, g: &Game, m: Move) {
+        self.moves.push((g.tick, m));
+    }
+
+    pub fn finish(&mut self, g: &Game) {
+        self.end = g.tick;
+    }
+
+    // the game as it was at tick, forgetting the moves made since
+    pub fn rewind(&mut self, tick: u64) -> Game {
+        self.moves.retain(|&(t, _)| t < tick);
+        self.end = tick;
+        let mut play = self.playback();
+        while play.step() {}
+        play.game.events.clear();
+        play.game
+    }
+
+    // either form; cfg is for bare replays, which don't carry settings
+    pub fn parse(src: &str, cfg: &Config) -> io::Result<Replay> {
+        let Some((head, body)) = src.split_once(&format!("\n{}\n", SEPARATOR)) else {
+            return Replay::parse_bare(src, cfg);
+        };
+        let h: Header = toml::from_str(head).map_err(|e| invalid(e.to_string()))?;
+        let config = h.config.validate().map_err(|e| invalid(e.to_string()))?;
+        let board = match h.board {
+            Some(s) => Some(position::parse(&s, config.width, config.height)?),
+            None => None,
+        };
+        let mut r = Replay {
+            seed: h.seed,
+            config,
+            end: h.end,
+            board,
+            queue: position::queue(h.queue.as_deref().unwrap_or(""))?,
+            moves: Vec::new(),
+        };
+        let skip = head.lines().count() + 1;
+        for (n, line) in body.lines().enumerate() {
+            r.parse_move(skip + n, line)?;
+        }
+        r.moves.sort_by_key(|&(tick, _)| tick); // hand edits may be out of order
+        Ok(r)
+    }
+
+    fn parse_bare(src: &str, cfg: &Config) -> io::Result<Replay> {
+        let mut r = Replay {
+            seed: 0,
+            config: cfg.clone(),
+            end: u64::MAX,
+            board: None,
+            queue: Vec::new(),
+            moves: Vec::new(),
+        };
+        let mut seed = None;
+        for (n, line) in src.lines().enumerate() {
+            let bad = || invalid(format!("line {}: bad number in '{}'", n + 1, line));
+            match line.trim().split_once(' ') {
+                Some(("seed", v)) => seed = Some(v.trim().parse().map_err(|_| bad())?),
+                Some(("end", v)) => r.end = v.trim().parse().map_err(|_| bad())?,
+                Some(("queue", v)) => {
+                    r.queue = position::queue(v.trim())
+                        .map_err(|e| invalid(format!("line {}: {}", n + 1, e)))?
+                }
+                Some(("board", v)) => {
+                    r.board = Some(
+                        position::parse(v, cfg.width, cfg.height)
+                            .map_err(|e| invalid(format!("line {}: {}", n + 1, e)))?,
+                    )
+                }
+                _ => r.parse_move(n, line)?,
+            }
+        }
+        r.seed = seed.ok_or_else(|| invalid("no 'seed' line".to_string()))?;
+        r.moves.sort_by_key(|&(tick, _)| tick);
+        Ok(r)
+    }
+
+    // a "tick:move" line (n counting from 0), or a blank or comment
+    fn parse_move(&mut self, n: usize, line: &str) -> io::Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let bad = || invalid(format!("line {}: bad move '{}'", n + 1, line));
+        let (tick, m) = line.split_once(':').ok_or_else(bad)?;
+        let tick = tick.trim().parse().map_err(|_| bad())?;
+        let m = m.trim().parse().map_err(|_| bad())?;
+        self.moves.push((tick, m));
+        Ok(())
+    }
+
+    // the bare form
+    pub fn to_bare(&self) -> String {
+        let mut s = format!("seed {}\nend {}\n", self.seed, self.end);
+        if let Some(b) = &self.board {
+            s += &format!(
+                "board {}\n",
+                position::to_text(b).trim_end().replace('\n', "/")
+            );
+        }
+        if !self.queue.is_empty() {
+            s += &format!("queue {}\n", position::letters(&self.queue));
+        }
+        for (tick, m) in &self.moves {
+            s += &format!("{}:{}\n", tick, m.name());
+        }
+        s
+    }
+
+    pub fn load(path: &Path, cfg: &Config) -> io::Result<Replay> {
+        let src = std::fs::read_to_string(path)?;
+        Replay::parse(&src, cfg)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn playback(&self) -> Playback<'_> {
+        let mut game = Game::with_seed(&self.config, self.seed);
+        if let Some(b) = &self.board {
+            game.set_board(b.clone());
+        }
+        game.set_queue(&self.queue);
+        Playback {
+            replay: self,
+            game,
+            moves: Script::new(&self.moves),
+        }
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let h = Header {
+            seed: self.seed,
+            end: self.end,
+            board: self.board.as_ref().map(position::to_text),
+            queue: (!self.queue.is_empty()).then(|| position::letters(&self.queue)),
+            config: self.config.clone(),
+        };
+        let head = toml::to_string(&h).map_err(|_| fmt::Error)?;
+        writeln!(f, "{}{}", head, SEPARATOR)?;
+        for (tick, m) in &self.moves {
+            writeln!(f, "{}:{}", tick, m.name())?;
+        }
+        Ok(())
+    }
+}
+
+// A replay being played: the game as it was, a tick at a time.
+pub struct Playback<'a> {
+    replay: &'a Replay,
+    pub game: Game,
+    moves: Script<'a>,
+}
+
+impl Playback<'_> {
+    // make the moves due, then advance a tick; false once the replay is over
+    pub fn step(&mut self) -> bool {
+        while !self.game.over {
+            match self.moves.next(&self.game) {
+                Some(Action::Move(m)) => self.game.apply(m),
+                Some(_) => (),
+                None => break,
+            }
+        }
+        self.game.tick < self.replay.end && self.game.do_tick()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{Difficulty, Style};
+    use crate::clock::{Ticker, Virtual};
+    use crate::game::TICK;
+    use crate::input::Ai;
+    use std::time::Duration;
+
+    #[test]
+    fn a_game_on_a_virtual_clock_replays_the_same() {
+        let cfg = Config {
+            level: 8,
+            ..Config::default()
+        };
+        let clock = Virtual::default();
+        let mut ticker = Ticker::new(&clock);
+        let mut g = Game::with_seed(&cfg, 42);
+        let mut rec = Replay::new(&g);
+        let mut bot = Ai::new(Difficulty::Hard, Style::Balanced);
+        // frames coming unevenly, as they do, the loop's order of things
+        // kept: the ticks due, then the moves
+        let frames = [13, 37, 7, 16, 90, 3, 250, 1];
+        for ms in frames.iter().cycle().take(4000) {
+            clock.advance(Duration::from_millis(*ms));
+            for _ in 0..ticker.due(TICK) {
+                g.do_tick();
+            }
+            while let Some(Action::Move(m)) = bot.next(&g) {
+                rec.record(&g, m);
+                g.apply(m);
+            }
+            if g.over {
+                break;
+            }
+        }
+        rec.finish(&g);
+        assert!(g.lines > 0);
+        let mut play = rec.playback();
+        while play.step() {}
+        assert_eq!(play.game.tick, g.tick);
+        assert_eq!(play.game.score, g.score);
+        assert_eq!(play.game.lines, g.lines);
+        assert_eq!(play.game.board.to_ascii(), g.board.to_ascii());
+        // and once more from the text form, as it is saved
+        let again = Replay::parse(&rec.to_string(), &cfg).unwrap();
+        let mut play = again.playback();
+        while play.step() {}
+        assert_eq!((play.game.score, play.game.lines), (g.score, g.lines));
+    }
+}
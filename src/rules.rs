@@ -0,0 +1,87 @@
+use crate::config::Config;
+use crate::palette::Stack;
+use crate::randomizer::Kind;
+use crate::speed::Curve;
+use serde::{Deserialize, Serialize};
+
+// Rulesets: the settings that make up a way of playing taken together, for
+// one option to set them all - each can still be set on its own, after.
+// Classic is the NES game as near as the engine comes to it: its speed
+// curve, its scoring, how it dealt the pieces and the colours they changed
+// to every level.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ruleset {
+    Modern, // the settings' defaults
+    Classic,
+}
+
+impl std::str::FromStr for Ruleset {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Ruleset, ()> {
+        match s {
+            "modern" => Ok(Ruleset::Modern),
+            "classic" => Ok(Ruleset::Classic),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Ruleset {
+    // cfg playing by these rules
+    pub fn apply(self, cfg: &mut Config) {
+        let (curve, scoring, randomizer, stack) = match self {
+            Ruleset::Modern => (
+                Curve::Unictris,
+                Scoring::Unictris,
+                Kind::Random,
+                Stack::Piece,
+            ),
+            Ruleset::Classic => (Curve::Nes, Scoring::Nes, Kind::Nes, Stack::Level),
+        };
+        cfg.curve = curve;
+        cfg.scoring = scoring;
+        cfg.randomizer = randomizer;
+        cfg.stack = stack;
+    }
+}
+
+// What a clear is worth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scoring {
+    Unictris, // by how far down the rows were, times the chain, level and fever
+    Nes,      // 40/100/300/1200 times the level, a point a row dropped; no combos
+}
+
+impl std::str::FromStr for Scoring {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Scoring, ()> {
+        match s {
+            "unictris" => Ok(Scoring::Unictris),
+            "nes" => Ok(Scoring::Nes),
+            _ => Err(()),
+        }
+    }
+}
+
+const NES: [u32; 5] = [0, 40, 100, 300, 1200]; // by rows cleared at once
+
+impl Scoring {
+    // points for clearing rows, the rows they were at, at level - the
+    // chain, starting level and fever multiplier being n
+    pub fn clear(self, rows: &[u8], level: u64, n: u32) -> u32 {
+        match self {
+            Scoring::Unictris => rows.iter().map(|&row| row as u32 * n).sum(),
+            Scoring::Nes => NES[rows.len().min(4)] * level as u32,
+        }
+    }
+
+    // ... and for dropping a piece rows rows
+    pub fn drop(self, rows: u8) -> u32 {
+        match self {
+            Scoring::Unictris => 0,
+            Scoring::Nes => rows as u32,
+        }
+    }
+}
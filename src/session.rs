@@ -0,0 +1,56 @@
+use crate::game::{Game, TICK};
+
+// Running totals over all the games played in one run of the program.
+#[derive(Default)]
+pub struct Session {
+    pub games: u32,
+    pub best: u32,
+    pub lines: u32,
+    pub score: u64,  // all the games' scores added up
+    pub pieces: u32, // played
+    pub ticks: u64,  // game time played
+}
+
+impl Session {
+    pub fn record(&mut self, g: &Game) {
+        self.games += 1;
+        self.best = self.best.max(g.score);
+        self.lines += g.lines;
+        self.score += g.score as u64;
+        self.pieces += g.stats.pieces();
+        self.ticks += g.tick;
+    }
+
+    pub fn average(&self) -> u64 {
+        self.score / self.games.max(1) as u64
+    }
+
+    // pieces per second, over all the time played
+    pub fn pps(&self) -> f64 {
+        match self.ticks {
+            0 => 0.0,
+            t => self.pieces as f64 / (t as f64 * TICK.as_secs_f64()),
+        }
+    }
+
+    // the report once the player is done, a line each
+    pub fn summary(&self) -> Vec<String> {
+        let s = self.ticks * TICK.as_millis() as u64 / 1000;
+        vec![
+            format!(
+                "Session: {} games in {}:{:02}:{:02}",
+                self.games,
+                s / 3600,
+                s / 60 % 60,
+                s % 60
+            ),
+            format!("Score: best {}, average {}", self.best, self.average()),
+            format!(
+                "Lines: {}; Pieces: {} ({:.2} a second)",
+                self.lines,
+                self.pieces,
+                self.pps()
+            ),
+        ]
+    }
+}
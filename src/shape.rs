@@ -0,0 +1,37 @@
+// The geometry of one tetromino in its four orientations.
+// Everything the collision and rendering code asks for is worked out once,
+// when the piece set is loaded, so lookups are plain table reads.
+
+pub struct Shape {
+    coor: [[(u8, u8); 4]; 4], // (row, col) squares, anchored at the top left
+    dim: [(u8, u8); 4],       // (width, height)
+    mask: [[u16; 4]; 4],      // one bitmask per row, column 0 in bit 0
+}
+
+impl Shape {
+    pub fn new(coor: [[(u8, u8); 4]; 4]) -> Shape {
+        let mut dim = [(0, 0); 4];
+        let mut mask = [[0; 4]; 4];
+        for r in 0..4 {
+            for &(i, j) in &coor[r] {
+                dim[r].0 = dim[r].0.max(j + 1);
+                dim[r].1 = dim[r].1.max(i + 1);
+                mask[r][i as usize] |= 1 << j;
+            }
+        }
+        Shape { coor, dim, mask }
+    }
+
+    pub fn coor(&self, r: u8) -> &[(u8, u8); 4] {
+        &self.coor[r as usize]
+    }
+
+    // (width, height) in orientation r
+    pub fn dim(&self, r: u8) -> (u8, u8) {
+        self.dim[r as usize]
+    }
+
+    pub fn mask(&self, r: u8) -> &[u16; 4] {
+        &self.mask[r as usize]
+    }
+}
@@ -0,0 +1,67 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+
+// Replays from one player to another in a command each: --share uploads
+// one to a paste service, the config's share endpoint, and prints the
+// address it answers with; --fetch takes that address and plays the replay
+// found there. The transfers are curl's, which speaks every protocol and
+// proxy the endpoint could want. The service is sent the file as the form
+// field "file", as 0x0.st and the like take it, and must answer with its
+// address and nothing else.
+
+fn curl(args: &[&str]) -> io::Result<Output> {
+    let out = Command::new("curl")
+        .args(["-fsSL"])
+        .args(args)
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("curl, which sharing needs: {}", e)))?;
+    if !out.status.success() {
+        let msg = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        return Err(io::Error::other(msg));
+    }
+    Ok(out)
+}
+
+// the form field "file" holding the file at path, the name quoted for curl
+// not to take a ; or , in it for more of the field
+fn field(path: &Path) -> String {
+    let name = path.display().to_string();
+    format!(
+        "file=@\"{}\"",
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+// upload the replay at path to endpoint; its address there
+pub fn upload(path: &Path, endpoint: &str) -> io::Result<String> {
+    let out = curl(&["-F", &field(path), "--url", endpoint])
+        .map_err(|e| io::Error::new(e.kind(), format!("--share {}: {}", endpoint, e)))?;
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+// the text at url
+pub fn download(url: &str) -> io::Result<String> {
+    let out = curl(&["--url", url])
+        .map_err(|e| io::Error::new(e.kind(), format!("--fetch {}: {}", url, e)))?;
+    String::from_utf8(out.stdout).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("--fetch {}: not a replay", url),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_file_name_is_quoted_for_curl() {
+        assert_eq!(field(Path::new("a;b,c.ttr")), r#"file=@"a;b,c.ttr""#);
+        assert_eq!(
+            field(Path::new(r#"say "hi"\.ttr"#)),
+            r#"file=@"say \"hi\"\\.ttr""#
+        );
+    }
+}
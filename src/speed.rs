@@ -0,0 +1,132 @@
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+
+// How fast a game gets as the levels go up: gravity, and how long a landed
+// piece waits before locking, the next piece before it comes in (ARE) and
+// full rows before they go - all from one curve picked for the game. The
+// game's own keeps the delays set in the config; the others are taken from
+// the games they are named for, level 1 here being the first level there.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    Unictris,  // a row every 0.3s, one more each level
+    Nes,       // frames a row from the NES game's table, no lock delay to speak of
+    Tgm,       // finer steps, then up to 20G within a few levels
+    Guideline, // the modern games' formula, with half a second to lock
+}
+
+impl std::str::FromStr for Curve {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Curve, ()> {
+        match s {
+            "unictris" => Ok(Curve::Unictris),
+            "nes" => Ok(Curve::Nes),
+            "tgm" => Ok(Curve::Tgm),
+            "guideline" => Ok(Curve::Guideline),
+            _ => Err(()),
+        }
+    }
+}
+
+// A level's speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    pub gravity: f64, // cells a second
+    pub lock: u32,    // ms a landed piece waits before locking
+    pub entry: u32,   // ms from locking to the next piece (ARE)
+    pub clear: u32,   // ms full rows stay up before they are cleared
+}
+
+const FPS: f64 = 60.0988; // the NES's and near enough the arcade's frame rate
+
+// frames a row on the NES, from its level 0
+const NES: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    1,
+];
+
+// TGM's gravity in 1/256ths of a row a frame, from the internal level it
+// starts at - a level here being a hundred there
+const TGM: [(u32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+// ms for n frames
+fn frames(n: u32) -> u32 {
+    (n as f64 * 1000.0 / FPS).round() as u32
+}
+
+impl Curve {
+    // the timing at level, the game's own taking the delays from cfg
+    pub fn timing(self, level: u64, cfg: &Config) -> Timing {
+        let l = level.saturating_sub(1); // from the first level
+        match self {
+            Curve::Unictris => Timing {
+                gravity: level as f64 / 0.3,
+                lock: cfg.lock_delay,
+                entry: cfg.spawn_delay,
+                clear: cfg.clear_delay,
+            },
+            Curve::Nes => {
+                let n = NES[(l as usize).min(NES.len() - 1)];
+                Timing {
+                    gravity: FPS / n as f64,
+                    lock: frames(n), // it locks when it next would fall
+                    entry: frames(10),
+                    clear: frames(17),
+                }
+            }
+            Curve::Tgm => {
+                let internal = 100 * l.min(9) as u32;
+                let g = (TGM.iter().rev())
+                    .find(|&&(from, _)| from <= internal)
+                    .map_or(TGM[0].1, |&(_, g)| g);
+                Timing {
+                    gravity: g as f64 / 256.0 * FPS,
+                    lock: frames(30),
+                    entry: frames(30),
+                    clear: frames(41),
+                }
+            }
+            Curve::Guideline => {
+                let l = l.min(19) as f64;
+                Timing {
+                    gravity: 1.0 / (0.8 - l * 0.007).powf(l),
+                    lock: 500,
+                    entry: frames(6),
+                    clear: frames(20),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::pieces;
+
+// Counts kept over the course of one game.
+#[derive(Default)]
+pub struct Stats {
+    pub dealt: Vec<u32>,   // pieces played, per tetromino
+    pub drought: u32,      // pieces since the last I
+    pub scores: Vec<u32>,  // the score every SAMPLE ticks, for the graph at the end
+    pub columns: Vec<u32>, // blocks locked in each column, for the heatmap at the end
+    pub rows: Vec<u32>,    // ... and in each row
+}
+
+pub const SAMPLE: u64 = 100; // ticks between score samples, a second
+
+// bar heights from empty to full, in eighths of a row
+pub const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+pub const ASCII_BARS: [char; 9] = [' ', ' ', '.', '.', '-', '-', '=', '=', '#'];
+
+impl Stats {
+    // pieces played so far
+    pub fn pieces(&self) -> u32 {
+        self.dealt.iter().sum()
+    }
+
+    // record tetromino p coming into play
+    pub fn deal(&mut self, p: u8) {
+        let set = pieces::get();
+        self.dealt.resize(set.pieces.len(), 0);
+        self.dealt[p as usize] += 1;
+        if set.pieces[p as usize].name == "I" {
+            self.drought = 0;
+        } else {
+            self.drought += 1;
+        }
+    }
+
+    // record a piece locking with its blocks at cells (x, y), on a board w x h
+    pub fn lock(&mut self, cells: impl Iterator<Item = (u8, u8)>, w: u8, h: u8) {
+        self.columns.resize(w as usize, 0);
+        self.rows.resize(h as usize, 0);
+        for (x, y) in cells {
+            self.columns[x as usize] += 1;
+            self.rows[y as usize] += 1;
+        }
+    }
+
+    // the share of blocks locked in the left and right halves of the board,
+    // in percent - the middle column of an odd width counting for neither
+    pub fn lean(&self) -> (u32, u32) {
+        let w = self.columns.len();
+        let left: u32 = self.columns[..w / 2].iter().sum();
+        let right: u32 = self.columns[w.div_ceil(2)..].iter().sum();
+        match left + right {
+            0 => (0, 0),
+            n => (left * 100 / n, right * 100 / n),
+        }
+    }
+}
+
+// samples as a bar chart at most cols wide and rows high, the top row
+// first, a full column standing for top: each column is the last sample of
+// its stretch of them
+pub fn chart(samples: &[u32], top: u32, cols: usize, rows: usize, bars: &[char; 9]) -> Vec<String> {
+    let top = top.max(1) as usize;
+    let n = samples.len();
+    let heights: Vec<usize> = (0..cols.min(n))
+        .map(|c| samples[(c + 1) * n / cols.min(n) - 1] as usize * rows * 8 / top)
+        .collect();
+    (0..rows)
+        .rev()
+        .map(|row| {
+            (heights.iter())
+                .map(|&h| bars[h.saturating_sub(row * 8).min(8)])
+                .collect()
+        })
+        .collect()
+}
+
+// how hot each of counts is, 0 for none and 1 to 4 in quarters of the
+// highest, for drawing a heatmap
+pub fn heat(counts: &[u32]) -> Vec<usize> {
+    let top = counts.iter().copied().max().unwrap_or(0).max(1) as usize;
+    (counts.iter())
+        .map(|&n| (n as usize * 4).div_ceil(top))
+        .collect()
+}
@@ -0,0 +1,421 @@
+use crate::ai::{Difficulty, Style};
+use crate::config::Config;
+use crate::game::Game;
+use crate::input::{Ai, InputSource};
+use crate::keymap::Action;
+use rand::prelude::*;
+use serde::Deserialize;
+
+// The other side of a versus game: its own board, played by the built-in
+// bot or whatever input source it is given.
+pub struct Opponent {
+    pub game: Game,
+    pub name: String, // as the screen shows it
+    pub input: Box<dyn InputSource>,
+}
+
+impl Opponent {
+    pub fn new(cfg: &Config, difficulty: Difficulty, style: Style) -> Opponent {
+        let mut cfg = cfg.clone();
+        cfg.handicap = cfg.opponent;
+        Opponent {
+            game: Game::new(&cfg),
+            name: format!("CPU ({:?} {:?})", difficulty, style),
+            input: Box::new(Ai::new(difficulty, style)),
+        }
+    }
+
+    // the bot's moves and a tick of its game
+    fn play(&mut self) {
+        while let Some(a) = self.input.next(&self.game) {
+            if let Action::Move(m) = a {
+                self.game.apply(m);
+            }
+        }
+        self.game.do_tick();
+        self.game.events.clear(); // nobody shows the bot's
+    }
+}
+
+pub const MAX_OPPONENTS: usize = 3;
+
+// What a versus match is to be: the bots' difficulty and style, how many
+// of them there are and, with more than one, where garbage goes - or, in
+// teams, 2v2: the player and a bot against two more.
+#[derive(Clone, Copy, Debug)]
+pub struct Setup {
+    pub difficulty: Difficulty,
+    pub style: Style,
+    pub opponents: usize,
+    pub target: Target,
+    pub teams: bool,
+}
+
+// A versus match: the player against bots, all in one room - the player
+// being its first and, in teams, the first bot the player's ally.
+pub struct Versus {
+    pub bots: Vec<Opponent>,
+    pub room: Room,
+}
+
+impl Versus {
+    pub fn new(cfg: &Config, setup: &Setup) -> Versus {
+        let n = if setup.teams {
+            3
+        } else {
+            setup.opponents.max(1)
+        };
+        let bots = (0..n)
+            .map(|i| {
+                let mut o = Opponent::new(cfg, setup.difficulty, setup.style);
+                o.name = match (setup.teams, i) {
+                    (true, 0) => "Ally".to_string(),
+                    (true, i) => format!("CPU {}", i),
+                    _ if n > 1 => format!("CPU {}", i + 1),
+                    _ => o.name,
+                };
+                o
+            })
+            .collect::<Vec<_>>();
+        let room = Room::new(n + 1, setup.target);
+        let room = match setup.teams {
+            true => room.with_teams(vec![0, 0, 1, 1]),
+            false => room,
+        };
+        Versus { bots, room }
+    }
+
+    // the bots on the player's team, the first of them
+    pub fn allies(&self) -> usize {
+        let team = self.room.teams[0];
+        self.room.teams[1..].iter().filter(|&&t| t == team).count()
+    }
+
+    // every game in the room, the player's first
+    fn games<'a>(player: &'a Game, bots: &'a [Opponent]) -> Vec<&'a Game> {
+        std::iter::once(player)
+            .chain(bots.iter().map(|b| &b.game))
+            .collect()
+    }
+
+    // a tick for the bots still in, after the player's, and the garbage
+    // sent handed out; false once the match is decided
+    pub fn tick(&mut self, player: &mut Game) -> bool {
+        for b in self.bots.iter_mut().filter(|b| !b.game.over) {
+            b.play();
+        }
+        let mut games: Vec<&mut Game> = std::iter::once(player)
+            .chain(self.bots.iter_mut().map(|b| &mut b.game))
+            .collect();
+        self.room.exchange(&mut games);
+        let games: Vec<&Game> = games.into_iter().map(|g| &*g).collect();
+        self.room.result(&games).is_none()
+    }
+
+    // Some(true) once the player's side has won, Some(false) once it has
+    // lost, None while it is still in it
+    pub fn result(&self, player: &Game) -> Option<bool> {
+        self.room.result(&Versus::games(player, &self.bots))
+    }
+}
+
+// Who gets the garbage when there is more than one opponent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Random, // anyone still in
+    Leader, // the highest score
+    All,    // everyone, the full attack each
+    Badges, // whoever has knocked out the most players
+}
+
+impl Target {
+    pub const ALL: [Target; 4] = [Target::Random, Target::Leader, Target::All, Target::Badges];
+}
+
+impl std::str::FromStr for Target {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Target, ()> {
+        match s {
+            "random" => Ok(Target::Random),
+            "leader" => Ok(Target::Leader),
+            "all" => Ok(Target::All),
+            "badges" => Ok(Target::Badges),
+            _ => Err(()),
+        }
+    }
+}
+
+// knockouts needed for each extra quarter of attack
+const BADGES: [u32; 4] = [2, 6, 14, 30];
+
+// attack boosted by the badges earned with kos knockouts
+pub fn boost(attack: u32, kos: u32) -> u32 {
+    let badges = BADGES.iter().filter(|&&n| kos >= n).count() as u32;
+    attack * (4 + badges) / 4
+}
+
+// A game between any number of players, garbage going where the room's
+// target says. Players can play in teams, 2v2 say: teammates aren't
+// targeted, garbage sent to a team goes to whichever of them has least on
+// the way, a clear counters garbage on its way to a teammate before any is
+// sent, and the last team with anyone still in wins. The room keeps the
+// score - teams and knockouts - while the games are the players', handed
+// in player by player whenever it is asked.
+pub struct Room {
+    pub target: Target,
+    pub teams: Vec<usize>, // each player's team, everyone on their own unless set
+    pub kos: Vec<u32>,
+    attacker: Vec<Option<usize>>, // who last sent each player garbage, credited with the KO
+    out: Vec<bool>,               // topped out and accounted for
+}
+
+// the players of games still in
+fn alive<'a>(games: &'a [&Game]) -> impl Iterator<Item = usize> + 'a {
+    (0..games.len()).filter(|&i| !games[i].over)
+}
+
+impl Room {
+    pub fn new(players: usize, target: Target) -> Room {
+        Room {
+            target,
+            teams: (0..players).collect(),
+            kos: vec![0; players],
+            attacker: vec![None; players],
+            out: vec![false; players],
+        }
+    }
+
+    // the players in teams, teams[i] being player i's
+    pub fn with_teams(mut self, teams: Vec<usize>) -> Room {
+        self.teams = teams;
+        self
+    }
+
+    // the team left once every other is out
+    pub fn winner(&self, games: &[&Game]) -> Option<usize> {
+        let mut teams = alive(games).map(|i| self.teams[i]);
+        let first = teams.next()?;
+        teams.all(|t| t == first).then_some(first)
+    }
+
+    // how it has gone for the first player's team: Some(true) won, Some(false)
+    // all out, None still playing
+    pub fn result(&self, games: &[&Game]) -> Option<bool> {
+        let team = self.teams[0];
+        match self.winner(games) {
+            Some(t) => Some(t == team),
+            None if alive(games).all(|i| self.teams[i] != team) => Some(false),
+            None => None,
+        }
+    }
+
+    // the players from sends to under the room's target, none of them on its team
+    pub fn targets(&self, games: &[&Game], from: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let team = self.teams[from];
+        let rivals: Vec<usize> = alive(games).filter(|&i| self.teams[i] != team).collect();
+        match self.target {
+            Target::All => rivals,
+            Target::Random => rivals.choose(rng).into_iter().copied().collect(),
+            Target::Leader => rivals
+                .iter()
+                .copied()
+                .max_by_key(|&i| games[i].score)
+                .into_iter()
+                .collect(),
+            Target::Badges => rivals
+                .iter()
+                .copied()
+                .max_by_key(|&i| self.kos[i])
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    // hand out what everyone has sent since last time - garbage and
+    // scrambles - and credit new knockouts; call after ticking the players
+    pub fn exchange(&mut self, games: &mut [&mut Game]) {
+        let rng = &mut thread_rng();
+        for from in 0..games.len() {
+            let sent = std::mem::take(&mut games[from].sent);
+            let scramble = std::mem::take(&mut games[from].sent_scramble);
+            if games[from].over {
+                continue;
+            }
+            let mut lines = boost(sent, self.kos[from]);
+            let team = self.teams[from];
+            for (i, g) in games.iter_mut().enumerate() {
+                if i != from && self.teams[i] == team {
+                    lines = g.garbage.cancel(lines);
+                }
+            }
+            if lines == 0 && !scramble {
+                continue;
+            }
+            let targets = {
+                let all: Vec<&Game> = games.iter().map(|g| &**g).collect();
+                self.targets(&all, from, rng)
+            };
+            let mut hit = Vec::new(); // teams sent to, each once
+            for to in targets {
+                let t = self.teams[to];
+                if hit.contains(&t) {
+                    continue;
+                }
+                hit.push(t);
+                // the teammate with least on the way takes it
+                let to = (0..games.len())
+                    .filter(|&i| self.teams[i] == t && !games[i].over)
+                    .min_by_key(|&i| games[i].garbage.pending())
+                    .unwrap_or(to);
+                games[to].scramble |= scramble;
+                if lines > 0 {
+                    games[to].garbage.push(lines);
+                    self.attacker[to] = Some(from);
+                }
+            }
+        }
+        for (i, g) in games.iter().enumerate() {
+            if g.over && !self.out[i] {
+                self.out[i] = true;
+                if let Some(by) = self.attacker[i] {
+                    self.kos[by] += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(n: usize) -> Vec<Game> {
+        (0..n)
+            .map(|i| Game::with_seed(&Config::default(), i as u64))
+            .collect()
+    }
+
+    // who player 0's garbage went to, with what is on its way to each
+    fn sent(room: &mut Room, games: &mut [Game], lines: u32) -> Vec<u32> {
+        games[0].sent = lines;
+        let mut all: Vec<&mut Game> = games.iter_mut().collect();
+        room.exchange(&mut all);
+        games.iter().map(|g| g.garbage.pending()).collect()
+    }
+
+    #[test]
+    fn the_leader_is_the_highest_score() {
+        let mut gs = games(4);
+        gs[2].score = 500;
+        gs[3].score = 100;
+        let mut room = Room::new(4, Target::Leader);
+        assert_eq!(sent(&mut room, &mut gs, 2), [0, 0, 2, 0]);
+    }
+
+    #[test]
+    fn all_sends_everyone_the_full_attack() {
+        let mut gs = games(4);
+        let mut room = Room::new(4, Target::All);
+        assert_eq!(sent(&mut room, &mut gs, 3), [0, 3, 3, 3]);
+    }
+
+    #[test]
+    fn random_sends_to_one_still_in() {
+        let mut gs = games(4);
+        gs[1].over = true;
+        gs[3].over = true;
+        let mut room = Room::new(4, Target::Random);
+        assert_eq!(sent(&mut room, &mut gs, 1), [0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn badges_go_after_the_most_knockouts() {
+        let mut gs = games(4);
+        let mut room = Room::new(4, Target::Badges);
+        room.kos[3] = 2;
+        assert_eq!(sent(&mut room, &mut gs, 2), [0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn knockouts_go_to_the_last_to_send_garbage() {
+        let mut gs = games(3);
+        let mut room = Room::new(3, Target::Leader);
+        gs[1].score = 10;
+        sent(&mut room, &mut gs, 1);
+        gs[1].over = true;
+        sent(&mut room, &mut gs, 0);
+        assert_eq!(room.kos, [1, 0, 0]);
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.result(&all), None);
+        gs[2].over = true;
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.winner(&all), Some(0));
+        assert_eq!(room.result(&all), Some(true));
+    }
+
+    #[test]
+    fn badges_boost_the_attack() {
+        assert_eq!(boost(4, 0), 4);
+        assert_eq!(boost(4, 1), 4);
+        assert_eq!(boost(4, 2), 5);
+        assert_eq!(boost(4, 30), 8);
+    }
+
+    fn teams() -> Room {
+        Room::new(4, Target::All).with_teams(vec![0, 0, 1, 1])
+    }
+
+    #[test]
+    fn teammates_are_never_sent_garbage() {
+        let mut gs = games(4);
+        let mut room = teams();
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.targets(&all, 0, &mut thread_rng()), [2, 3]);
+        // the rival team takes it once, the one of them with less coming
+        gs[2].garbage.push(1);
+        assert_eq!(sent(&mut room, &mut gs, 2), [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn a_clear_counters_a_teammates_garbage_first() {
+        let mut gs = games(4);
+        gs[1].garbage.push(3);
+        let mut room = teams();
+        assert_eq!(sent(&mut room, &mut gs, 2), [0, 1, 0, 0]);
+        assert_eq!(sent(&mut room, &mut gs, 4), [0, 0, 3, 0]);
+    }
+
+    #[test]
+    fn a_team_wins_with_one_of_it_still_in() {
+        let mut gs = games(4);
+        let room = teams();
+        gs[0].over = true;
+        gs[2].over = true;
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.result(&all), None);
+        gs[3].over = true;
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.winner(&all), Some(0));
+        assert_eq!(room.result(&all), Some(true));
+        gs[3].over = false;
+        gs[1].over = true;
+        let all: Vec<&Game> = gs.iter().collect();
+        assert_eq!(room.result(&all), Some(false));
+    }
+
+    #[test]
+    fn two_on_two_puts_an_ally_on_the_players_team() {
+        let setup = Setup {
+            difficulty: Difficulty::Easy,
+            style: Style::Balanced,
+            opponents: 1,
+            target: Target::Random,
+            teams: true,
+        };
+        let v = Versus::new(&Config::default(), &setup);
+        assert_eq!(v.bots.len(), 3);
+        assert_eq!(v.bots[0].name, "Ally");
+        assert_eq!(v.allies(), 1);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::fever::Fever;
+use crate::game::Game;
+use crate::garbage::Batch;
+use crate::hud::Widget;
+use crate::items::Item;
+use crate::mode::Mode;
+use crate::pack::Goal;
+use crate::palette::{self, Background, Ghost, Skin, Stack, Theme};
+use crate::pieces::shape;
+use crossterm::style::Color;
+
+// What the screen shows of a game, copied out of it once a frame so it can
+// be drawn on a thread of its own while the game goes on: the cells as they
+// look, falling piece and all, and what the sidebar needs.
+#[derive(Clone)]
+pub struct View {
+    pub width: u8,
+    pub height: u8,
+    cells: Vec<u8>,        // tetromino+1 row by row, counting the falling piece
+    shadow: Vec<bool>,     // ... and where the ghost piece is
+    hinted: Vec<bool>,     // ... and where the bot would put it, with the hint on
+    falling: Vec<bool>,    // ... and the falling piece
+    pub clearing: Vec<u8>, // rows flashing on their way out
+    pub tick: u64,
+    pub level: u64,
+    pub score: u32,
+    pub lines: u32,
+    pub combo: u32,
+    pub b2b: u32, // difficult clears in a row
+    pub p: u8,    // the falling piece and its orientation
+    pub r: u8,
+    pub y: u8,        // ... its top row
+    pub between: f64, // ... and how far on it is to the next
+    pub slide: i8,    // ... and columns it is drawn off where it is, on its way over
+    pub landing: u8,  // ... and the row it would land on
+    pub next: Vec<(u8, u8)>,
+    pub held: Option<(u8, u8)>,
+    pub can_hold: bool,
+    pub swap: Option<(u8, u8)>, // what a hold would bring into play
+    pub item: Option<Item>,
+    pub fever: Fever,
+    pub splits: Vec<u64>,
+    pub pace: Vec<u64>,
+    pub ghost: Vec<u64>, // the personal best's line timeline
+    pub drought: u32,
+    pub dealt: Vec<u32>,      // pieces played, per tetromino
+    pub heights: Vec<u8>,     // the stack's in each column
+    pub bag: Option<Vec<u8>>, // the pieces left in the bag being dealt from
+    pub garbage: Vec<Batch>,
+    pub goal: Option<(Goal, u32)>, // a puzzle's, and the pieces there are for it
+    pub paused: bool,
+    pub over: bool,
+    // settings it is drawn with
+    pub mode: Mode,
+    pub fevers: bool, // fever on
+    pub background: Background,
+    pub ghost_style: Ghost,
+    pub theme: Theme,
+    pub smooth: bool,
+    pub swap_preview: bool,
+    pub stack: Stack,
+    pub skin: Skin,
+    pub hud: Vec<Widget>,
+}
+
+impl View {
+    pub fn of(g: &Game) -> View {
+        let (w, h) = (g.board.width(), g.board.height());
+        let all = || (0..h).flat_map(move |y| (0..w).map(move |x| (x, y)));
+        let cfg = g.cfg();
+        View {
+            width: w,
+            height: h,
+            cells: all().map(|(x, y)| g.cell(x, y)).collect(),
+            shadow: all().map(|(x, y)| g.ghost_cell(x, y)).collect(),
+            hinted: Vec::new(), // ... nor this: whoever draws the view has the search
+            falling: all()
+                .map(|(x, y)| g.board.get(x, y) != g.cell(x, y))
+                .collect(),
+            clearing: g.clearing().to_vec(),
+            tick: g.tick,
+            level: g.level(),
+            score: g.score,
+            lines: g.lines,
+            combo: g.combo,
+            b2b: g.b2b,
+            p: g.p,
+            r: g.r,
+            y: g.y,
+            between: g.between(),
+            slide: 0, // the game doesn't keep it: it's up to whoever draws the view
+            landing: g.landing(),
+            next: g.next.iter().copied().collect(),
+            held: g.held,
+            can_hold: g.can_hold,
+            swap: g.swap(),
+            item: g.item,
+            fever: g.fever,
+            splits: g.splits.clone(),
+            pace: g.pace.clone(),
+            ghost: g.ghost.clone(),
+            drought: g.stats.drought,
+            dealt: g.stats.dealt.clone(),
+            heights: g.board.column_heights(),
+            bag: g.bag(),
+            garbage: g.garbage.queue.iter().copied().collect(),
+            goal: g.goal,
+            paused: g.paused,
+            over: g.over,
+            mode: cfg.mode,
+            fevers: cfg.fever,
+            background: cfg.background,
+            ghost_style: cfg.ghost,
+            theme: cfg.theme,
+            smooth: cfg.smooth && !cfg.reduced_motion,
+            swap_preview: cfg.swap_preview,
+            stack: cfg.stack,
+            skin: Skin::new(&cfg.glyphs).unwrap_or_default(), // checked with the config
+            hud: cfg.hud.clone(),
+        }
+    }
+
+    // tetromino+1 at (x, y) counting the falling piece, 0 if empty
+    pub fn cell(&self, x: u8, y: u8) -> u8 {
+        self.cells[y as usize * self.width as usize + x as usize]
+    }
+
+    // the first of rows rows to show when they can't all be, following the
+    // falling piece: where it would land as well when that is near enough
+    pub fn top(&self, rows: u8) -> u8 {
+        let bottom = self.landing + 4; // below the piece wherever it lands
+        bottom
+            .saturating_sub(rows)
+            .min(self.y)
+            .min(self.height.saturating_sub(rows))
+    }
+
+    // how the ghost piece is drawn: as a glyph when there is no colour to tell
+    // it by, and in ASCII for the ASCII theme
+    pub fn ghost(&self) -> Ghost {
+        match (self.theme, self.ghost_style) {
+            (Theme::Mono, Ghost::Dim) => Ghost::Glyph,
+            (Theme::Ascii, Ghost::Dim | Ghost::Glyph) => Ghost::Outline,
+            (_, style) => style,
+        }
+    }
+
+    // colour of piece p, wherever it is drawn: the level's colours for it
+    // when the stack is coloured by level
+    pub fn color(&self, p: u8) -> Color {
+        match self.stack {
+            Stack::Level => palette::level(p, self.level),
+            _ => palette::color(p),
+        }
+    }
+
+    // is (x, y) part of the falling piece?
+    pub fn falling(&self, x: u8, y: u8) -> bool {
+        self.falling[y as usize * self.width as usize + x as usize]
+    }
+
+    // is the falling piece drawn half a row on, being most of the way there?
+    pub fn halfway(&self) -> bool {
+        self.smooth && self.between >= 0.5 && self.theme != Theme::Ascii
+    }
+
+    // is the falling piece drawn sliding over from where it was?
+    pub fn sliding(&self) -> bool {
+        self.slide != 0 && !self.halfway() && self.theme != Theme::Ascii
+    }
+
+    // (x, y) with the falling piece half a row lower, as half blocks - None
+    // where it doesn't reach
+    pub fn half_cell(&self, x: u8, y: u8) -> Option<&'static str> {
+        let above = y > 0 && self.falling(x, y - 1);
+        match (above, self.falling(x, y)) {
+            (true, true) => Some("██"),
+            (true, false) => Some("▀▀"),
+            (false, true) => Some("▄▄"),
+            (false, false) => None,
+        }
+    }
+
+    // is (x, y) under the ghost piece?
+    pub fn ghost_cell(&self, x: u8, y: u8) -> bool {
+        self.shadow[y as usize * self.width as usize + x as usize]
+    }
+
+    // the falling piece hinted at in orientation r, its top left at (x, y)
+    pub fn hint(&mut self, r: u8, x: u8, y: u8) {
+        self.hinted = vec![false; self.cells.len()];
+        for &(i, j) in shape(self.p).coor(r) {
+            if let Some(c) = self
+                .hinted
+                .get_mut((y + i) as usize * self.width as usize + (x + j) as usize)
+            {
+                *c = true;
+            }
+        }
+    }
+
+    // is (x, y) where the hint would have the falling piece?
+    pub fn hint_cell(&self, x: u8, y: u8) -> bool {
+        let i = y as usize * self.width as usize + x as usize;
+        self.hinted.get(i).copied().unwrap_or(false)
+    }
+}